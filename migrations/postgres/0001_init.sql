@@ -0,0 +1,74 @@
+-- Decoded pump.fun / pump AMM events. Pubkeys and signatures are base58 text,
+-- amounts are raw integers (lamports, token base units).
+
+CREATE TABLE tokens (
+    mint TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    uri TEXT NOT NULL,
+    creator TEXT NOT NULL,
+    wallet TEXT NOT NULL,
+    bonding_curve TEXT NOT NULL,
+    total_supply BIGINT NOT NULL,
+    slot BIGINT NOT NULL,
+    signature TEXT NOT NULL,
+    time TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX tokens_creator ON tokens (creator);
+
+-- the hypertable's unique key has to include its time column
+CREATE TABLE trades (
+    time TIMESTAMPTZ NOT NULL,
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot BIGINT NOT NULL,
+    venue TEXT NOT NULL,
+    mint TEXT NOT NULL,
+    wallet TEXT NOT NULL,
+    pool TEXT NOT NULL,
+    is_buy BOOLEAN NOT NULL,
+    sol_amount BIGINT NOT NULL,
+    token_amount BIGINT NOT NULL,
+    fee BIGINT NOT NULL,
+    PRIMARY KEY (signature, event_index, time)
+);
+CREATE INDEX trades_mint ON trades (mint, time DESC);
+CREATE INDEX trades_wallet ON trades (wallet, time DESC);
+
+CREATE TABLE migrations (
+    time TIMESTAMPTZ NOT NULL,
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    mint TEXT NOT NULL,
+    wallet TEXT NOT NULL,
+    bonding_curve TEXT,
+    pool TEXT,
+    base_amount BIGINT,
+    quote_amount BIGINT,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX migrations_mint ON migrations (mint);
+
+CREATE TABLE slots (
+    slot BIGINT PRIMARY KEY,
+    parent BIGINT,
+    status TEXT NOT NULL
+);
+
+-- TimescaleDB is optional: without it (or without the rights to create it)
+-- trades stays a plain table.
+DO $$
+BEGIN
+    CREATE EXTENSION IF NOT EXISTS timescaledb;
+EXCEPTION WHEN OTHERS THEN
+    RAISE NOTICE 'timescaledb not available: %', SQLERRM;
+END $$;
+
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM pg_extension WHERE extname = 'timescaledb') THEN
+        PERFORM create_hypertable('trades', 'time', chunk_time_interval => INTERVAL '1 day');
+    END IF;
+END $$;
@@ -0,0 +1,499 @@
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::decoder::DecodedTransaction;
+use crate::sinks::recorder::{PUMP_TOKEN_DECIMALS, SOL_DECIMALS};
+use crate::types::constant::WSOL_MINT;
+use crate::types::swap::{SwapEvent, SwapSide, Venue};
+
+pub const DEFAULT_CANDLE_INTERVALS: &str = "1m";
+/// How long a bar stays open for late trades after its interval ended.
+pub const DEFAULT_LATENESS_SECS: u64 = 5;
+/// Slots are ~400ms apart.
+const SLOTS_PER_SECOND: f64 = 2.5;
+
+/// A bar width, written `1s`, `1m`, `5m`, `1h`...
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Interval {
+    pub secs: u64,
+}
+
+impl FromStr for Interval {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (count, unit) = match s.char_indices().next_back() {
+            Some((at, unit)) => (&s[..at], unit),
+            None => anyhow::bail!("empty candle interval"),
+        };
+        let unit = match unit {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => anyhow::bail!("invalid candle interval {s}, expected e.g. 1s, 1m, 5m, 1h"),
+        };
+        let secs = count
+            .parse::<u64>()
+            .ok()
+            .filter(|count| *count > 0)
+            .and_then(|count| count.checked_mul(unit))
+            .ok_or_else(|| anyhow::anyhow!("invalid candle interval {s}"))?;
+        Ok(Self { secs })
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.secs {
+            secs if secs % 86400 == 0 => write!(f, "{}d", secs / 86400),
+            secs if secs % 3600 == 0 => write!(f, "{}h", secs / 3600),
+            secs if secs % 60 == 0 => write!(f, "{}m", secs / 60),
+            secs => write!(f, "{secs}s"),
+        }
+    }
+}
+
+/// Comma separated intervals, e.g. `1s,1m,5m,1h`.
+pub fn parse_intervals(spec: &str) -> anyhow::Result<Vec<Interval>> {
+    let mut intervals = spec
+        .split(',')
+        .filter(|interval| !interval.trim().is_empty())
+        .map(Interval::from_str)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    intervals.sort();
+    intervals.dedup();
+    Ok(intervals)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Bucketing {
+    /// By the on-chain timestamp of the trade, bars start at multiples of the interval.
+    WallClock,
+    /// By slot, an interval being `secs * 2.5` slots. Immune to the coarse
+    /// (second resolution, sometimes drifting) block time.
+    Slot,
+}
+
+impl Bucketing {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Bucketing::WallClock => "wall_clock",
+            Bucketing::Slot => "slot",
+        }
+    }
+
+    /// Bar width in the unit buckets are counted in: seconds or slots.
+    fn width(&self, interval: Interval) -> u64 {
+        match self {
+            Bucketing::WallClock => interval.secs,
+            Bucketing::Slot => ((interval.secs as f64 * SLOTS_PER_SECOND) as u64).max(1),
+        }
+    }
+}
+
+impl FromStr for Bucketing {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wall_clock" | "wall" | "time" => Ok(Bucketing::WallClock),
+            "slot" => Ok(Bucketing::Slot),
+            _ => anyhow::bail!("invalid candle bucketing {s}, expected wall_clock or slot"),
+        }
+    }
+}
+
+/// Where a trade sits in the chain, to order trades arriving out of order.
+type Position = (u64, u64, usize);
+
+/// One OHLCV bar of a mint, prices in SOL per token.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candle {
+    pub mint: Pubkey,
+    pub interval: Interval,
+    pub bucketing: Bucketing,
+    /// Bar bounds `[start, end)`, unix seconds or slots depending on `bucketing`.
+    pub start: u64,
+    pub end: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    /// SOL traded, lamports.
+    pub volume: u64,
+    pub buy_volume: u64,
+    pub sell_volume: u64,
+    /// Tokens traded, raw amount.
+    pub token_volume: u64,
+    pub buys: u64,
+    pub sells: u64,
+    /// Venue of the closing trade; changes from pump_fun to pump_amm at migration.
+    pub venue: Venue,
+    pub first_slot: u64,
+    pub last_slot: u64,
+    /// SOL price in USD when the bar was last updated, if known.
+    pub sol_usd: Option<f64>,
+    open_position: Position,
+    close_position: Position,
+}
+
+impl Candle {
+    pub fn trades(&self) -> u64 {
+        self.buys + self.sells
+    }
+
+    pub fn usd(&self, price: f64) -> Option<f64> {
+        self.sol_usd.map(|sol_usd| price * sol_usd)
+    }
+
+    fn new(mint: Pubkey, interval: Interval, bucketing: Bucketing, start: u64, width: u64) -> Self {
+        Self {
+            mint,
+            interval,
+            bucketing,
+            start,
+            end: start + width,
+            open: 0.0,
+            high: f64::MIN,
+            low: f64::MAX,
+            close: 0.0,
+            volume: 0,
+            buy_volume: 0,
+            sell_volume: 0,
+            token_volume: 0,
+            buys: 0,
+            sells: 0,
+            venue: Venue::PumpFun,
+            first_slot: u64::MAX,
+            last_slot: 0,
+            sol_usd: None,
+            open_position: (u64::MAX, u64::MAX, usize::MAX),
+            close_position: (0, 0, 0),
+        }
+    }
+
+    fn add(&mut self, swap: &SwapEvent, price: f64, position: Position, sol_usd: Option<f64>) {
+        if position < self.open_position {
+            self.open = price;
+            self.open_position = position;
+        }
+        if position >= self.close_position {
+            self.close = price;
+            self.close_position = position;
+            self.venue = swap.venue;
+        }
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        let sol = swap.quote_amount();
+        self.volume += sol;
+        self.token_volume += swap.base_amount();
+        match swap.side {
+            SwapSide::Buy => {
+                self.buys += 1;
+                self.buy_volume += sol;
+            }
+            SwapSide::Sell => {
+                self.sells += 1;
+                self.sell_volume += sol;
+            }
+        }
+        self.first_slot = self.first_slot.min(swap.slot);
+        self.last_slot = self.last_slot.max(swap.slot);
+        if sol_usd.is_some() {
+            self.sol_usd = sol_usd;
+        }
+    }
+}
+
+/// SOL per token of a pump.fun or pump AMM trade, fees excluded so both venues
+/// and both sides price alike; other swaps aren't priced.
+fn price(swap: &SwapEvent) -> Option<f64> {
+    if !matches!(swap.venue, Venue::PumpFun | Venue::PumpAmm)
+        || swap.quote_mint() != WSOL_MINT
+        || swap.base_amount() == 0
+    {
+        return None;
+    }
+    let sol = swap.quote_amount_without_fees() as f64 / 10f64.powi(SOL_DECIMALS as i32);
+    let tokens = swap.base_amount() as f64 / 10f64.powi(PUMP_TOKEN_DECIMALS as i32);
+    Some(sol / tokens)
+}
+
+/// Aggregates bonding curve and pump AMM trades into OHLCV bars per mint.
+///
+/// Bars are keyed by mint, not pool, so a token's series carries on from the
+/// bonding curve into its AMM pool. A bar is emitted once the newest trade seen
+/// is `lateness` past its end; trades for a bar already emitted are dropped.
+pub struct CandleAggregator {
+    intervals: Vec<Interval>,
+    bucketing: Bucketing,
+    lateness: u64,
+    sol_usd: Option<f64>,
+    /// (bar end, mint, interval) -> bar
+    open: BTreeMap<(u64, Pubkey, Interval), Candle>,
+    /// Newest trade position seen, unix seconds or slot.
+    watermark: u64,
+    late: u64,
+}
+
+impl CandleAggregator {
+    pub fn new(intervals: Vec<Interval>, bucketing: Bucketing, lateness_secs: u64) -> Self {
+        let lateness = match bucketing {
+            Bucketing::WallClock => lateness_secs,
+            Bucketing::Slot => (lateness_secs as f64 * SLOTS_PER_SECOND) as u64,
+        };
+        Self {
+            intervals,
+            bucketing,
+            lateness,
+            sol_usd: None,
+            open: BTreeMap::new(),
+            watermark: 0,
+            late: 0,
+        }
+    }
+
+    /// USD per SOL used for the bars from now on.
+    pub fn set_sol_usd(&mut self, sol_usd: Option<f64>) {
+        self.sol_usd = sol_usd;
+    }
+
+    /// Trades dropped because their bar had already been emitted.
+    pub fn late(&self) -> u64 {
+        self.late
+    }
+
+    /// Adds the transaction's trades and returns the bars that closed, oldest first.
+    pub fn observe(&mut self, decoded: &DecodedTransaction) -> Vec<Candle> {
+        for swap in decoded.swaps() {
+            let Some(price) = price(&swap) else {
+                continue;
+            };
+            let at = match self.bucketing {
+                Bucketing::WallClock => swap.timestamp.max(0) as u64,
+                Bucketing::Slot => swap.slot,
+            };
+            let position = (swap.slot, decoded.index, swap.instruction_index);
+            let mint = swap.base_mint();
+            for &interval in &self.intervals {
+                let width = self.bucketing.width(interval);
+                let start = at - at % width;
+                if start + width + self.lateness <= self.watermark {
+                    self.late += 1;
+                    continue;
+                }
+                self.open
+                    .entry((start + width, mint, interval))
+                    .or_insert_with(|| Candle::new(mint, interval, self.bucketing, start, width))
+                    .add(&swap, price, position, self.sol_usd);
+            }
+            self.watermark = self.watermark.max(at);
+        }
+        self.close_until(self.watermark.saturating_sub(self.lateness))
+    }
+
+    fn close_until(&mut self, until: u64) -> Vec<Candle> {
+        let mut closed = Vec::new();
+        while let Some(entry) = self.open.first_entry() {
+            if entry.key().0 > until {
+                break;
+            }
+            closed.push(entry.remove());
+        }
+        closed
+    }
+
+    /// Every bar still open, e.g. on shutdown.
+    pub fn flush(&mut self) -> Vec<Candle> {
+        self.close_until(u64::MAX)
+    }
+
+    /// The open bars of a mint, the current state of its chart.
+    pub fn current(&self, mint: &Pubkey) -> Vec<&Candle> {
+        self.open
+            .values()
+            .filter(|candle| candle.mint == *mint)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::Action;
+    use crate::types::pump_fun::{BuyEvent, TradeEvent};
+
+    #[test]
+    fn parses_intervals() {
+        assert_eq!(
+            parse_intervals("5m, 1s,1h,1m,1m").unwrap(),
+            [1, 60, 300, 3600].map(|secs| Interval { secs })
+        );
+        assert_eq!(Interval { secs: 90 }.to_string(), "90s");
+        assert_eq!(Interval { secs: 7200 }.to_string(), "2h");
+        for invalid in [
+            "",
+            "m",
+            "0m",
+            "1м",
+            "м",
+            "1x",
+            "-1m",
+            "18446744073709551615d",
+        ] {
+            assert!(Interval::from_str(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    /// A curve trade of `sol` lamports for `tokens` raw tokens.
+    fn trade(mint: Pubkey, timestamp: i64, is_buy: bool, sol: u64, tokens: u64) -> Action {
+        Action::PumpFunTrade(TradeEvent {
+            mint,
+            timestamp,
+            is_buy,
+            sol_amount: sol,
+            token_amount: tokens,
+            ..Default::default()
+        })
+    }
+
+    fn observe(candles: &mut CandleAggregator, slot: u64, action: Action) -> Vec<Candle> {
+        candles.observe(&DecodedTransaction::with_actions(slot, vec![action]))
+    }
+
+    fn minute_candles() -> CandleAggregator {
+        CandleAggregator::new(
+            vec![Interval { secs: 60 }],
+            Bucketing::WallClock,
+            DEFAULT_LATENESS_SECS,
+        )
+    }
+
+    #[test]
+    fn orders_open_and_close_by_chain_position() {
+        let mint = Pubkey::new_unique();
+        let mut candles = minute_candles();
+        // 1, 3 then 2 SOL per 1000 tokens, the last one landing between the others
+        observe(
+            &mut candles,
+            10,
+            trade(mint, 60, true, 1_000_000_000, 1_000_000_000),
+        );
+        observe(
+            &mut candles,
+            12,
+            trade(mint, 70, true, 3_000_000_000, 1_000_000_000),
+        );
+        observe(
+            &mut candles,
+            11,
+            trade(mint, 65, false, 2_000_000_000, 1_000_000_000),
+        );
+        // and one from before the first
+        observe(
+            &mut candles,
+            9,
+            trade(mint, 61, true, 500_000_000, 1_000_000_000),
+        );
+
+        let candle = candles.current(&mint)[0];
+        assert_eq!((candle.start, candle.end), (60, 120));
+        assert_eq!(candle.open, 0.0005);
+        assert_eq!(candle.close, 0.003);
+        assert_eq!((candle.high, candle.low), (0.003, 0.0005));
+        assert_eq!((candle.buys, candle.sells), (3, 1));
+        assert_eq!(candle.buy_volume, 4_500_000_000);
+        assert_eq!(candle.sell_volume, 2_000_000_000);
+        assert_eq!((candle.first_slot, candle.last_slot), (9, 12));
+    }
+
+    #[test]
+    fn closes_bars_after_the_lateness_and_drops_later_trades() {
+        let mint = Pubkey::new_unique();
+        let mut candles = minute_candles();
+        observe(
+            &mut candles,
+            10,
+            trade(mint, 60, true, 1_000_000_000, 1_000_000_000),
+        );
+        // 124 is 4s past the bar's end: still open
+        assert!(
+            observe(
+                &mut candles,
+                20,
+                trade(mint, 124, true, 1_000_000_000, 1_000_000_000)
+            )
+            .is_empty()
+        );
+        // so a late trade is counted
+        assert!(
+            observe(
+                &mut candles,
+                19,
+                trade(mint, 119, true, 1_000_000_000, 1_000_000_000)
+            )
+            .is_empty()
+        );
+        let closed = observe(
+            &mut candles,
+            21,
+            trade(mint, 125, true, 1_000_000_000, 1_000_000_000),
+        );
+        assert_eq!(closed.len(), 1);
+        assert_eq!((closed[0].start, closed[0].trades()), (60, 2));
+
+        // the bar was emitted, the trade is dropped and counted
+        assert!(
+            observe(
+                &mut candles,
+                18,
+                trade(mint, 110, true, 1_000_000_000, 1_000_000_000)
+            )
+            .is_empty()
+        );
+        assert_eq!(candles.late(), 1);
+        let flushed = candles.flush();
+        assert_eq!(flushed.len(), 1);
+        assert_eq!((flushed[0].start, flushed[0].trades()), (120, 2));
+    }
+
+    #[test]
+    fn carries_on_from_the_curve_into_the_pool() {
+        let mint = Pubkey::new_unique();
+        let mut candles = minute_candles();
+        // 0.0004 SOL per token on the curve
+        observe(
+            &mut candles,
+            10,
+            trade(mint, 60, true, 400_000_000, 1_000_000_000),
+        );
+        // 0.0005 on the pool once the fees are taken out of the buy
+        observe(
+            &mut candles,
+            20,
+            Action::PumpAmmBuy {
+                event: BuyEvent {
+                    timestamp: 70,
+                    base_amount_out: 1_000_000_000,
+                    user_quote_amount_in: 506_250_000,
+                    lp_fee: 1_000_000,
+                    protocol_fee: 250_000,
+                    coin_creator_fee: 5_000_000,
+                    ..Default::default()
+                },
+                base_mint: mint,
+                quote_mint: WSOL_MINT,
+            },
+        );
+        let candle = candles.current(&mint)[0];
+        assert_eq!(candle.open, 0.0004);
+        assert_eq!(candle.close, 0.0005);
+        assert_eq!(candle.venue, Venue::PumpAmm);
+        assert_eq!(candle.buys, 2);
+        assert_eq!(candle.token_volume, 2_000_000_000);
+    }
+}
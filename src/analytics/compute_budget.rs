@@ -0,0 +1,183 @@
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use crate::types::constant::COMPUTE_BUDGET_PROGRAM_ID;
+use crate::utils::summary::TransactionSummary;
+
+//ComputeBudgetInstruction tags
+const REQUEST_HEAP_FRAME_TAG: u8 = 1;
+const SET_COMPUTE_UNIT_LIMIT_TAG: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE_TAG: u8 = 3;
+
+const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u64 = 200_000;
+const MAX_COMPUTE_UNIT_LIMIT: u64 = 1_400_000;
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+
+/// Compute budget requested by a transaction's ComputeBudget instructions.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ComputeBudget {
+    pub unit_limit: Option<u32>,
+    /// Micro-lamports per compute unit.
+    pub unit_price: Option<u64>,
+    pub heap_frame: Option<u32>,
+    /// Top-level instructions that are not ComputeBudget ones.
+    pub other_instructions: u64,
+}
+
+impl ComputeBudget {
+    /// `instructions` are the top-level (program id, data) pairs.
+    pub fn from_instructions<'a>(
+        instructions: impl IntoIterator<Item = (Pubkey, &'a [u8])>,
+    ) -> Self {
+        let mut budget = Self::default();
+        for (program_id, data) in instructions {
+            if program_id != COMPUTE_BUDGET_PROGRAM_ID {
+                budget.other_instructions += 1;
+                continue;
+            }
+            match data.split_first() {
+                Some((&REQUEST_HEAP_FRAME_TAG, rest)) => budget.heap_frame = read_u32(rest),
+                Some((&SET_COMPUTE_UNIT_LIMIT_TAG, rest)) => budget.unit_limit = read_u32(rest),
+                Some((&SET_COMPUTE_UNIT_PRICE_TAG, rest)) => budget.unit_price = read_u64(rest),
+                _ => {}
+            }
+        }
+        budget
+    }
+
+    /// Requested limit, or the runtime default of 200k per instruction.
+    pub fn effective_unit_limit(&self) -> u64 {
+        self.unit_limit
+            .map(u64::from)
+            .unwrap_or(self.other_instructions * DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT)
+            .min(MAX_COMPUTE_UNIT_LIMIT)
+    }
+
+    /// Lamports paid on top of the base fee, rounded up like the runtime does.
+    pub fn priority_fee(&self) -> u64 {
+        (self.unit_price.unwrap_or_default() as u128 * self.effective_unit_limit() as u128)
+            .div_ceil(MICRO_LAMPORTS_PER_LAMPORT) as u64
+    }
+}
+
+fn read_u32(data: &[u8]) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(..4)?.try_into().ok()?))
+}
+
+fn read_u64(data: &[u8]) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(..8)?.try_into().ok()?))
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Percentiles {
+    pub p50: u64,
+    pub p75: u64,
+    pub p90: u64,
+    pub p99: u64,
+}
+
+impl Percentiles {
+    /// Nearest-rank percentiles, `None` for no samples.
+    pub fn from_samples(mut samples: Vec<u64>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_unstable();
+        let rank = |p: usize| samples[((samples.len() * p).div_ceil(100)).max(1) - 1];
+        Some(Self {
+            p50: rank(50),
+            p75: rank(75),
+            p90: rank(90),
+            p99: rank(99),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PriorityFeeStats {
+    pub program_id: Pubkey,
+    pub samples: usize,
+    pub first_slot: u64,
+    pub last_slot: u64,
+    /// Micro-lamports per compute unit.
+    pub unit_price: Percentiles,
+    /// Lamports.
+    pub priority_fee: Percentiles,
+}
+
+#[derive(Default)]
+struct FeeWindow {
+    // slot -> program -> (unit price, priority fee) samples
+    slots: BTreeMap<u64, HashMap<Pubkey, Vec<(u64, u64)>>>,
+}
+
+/// Rolling priority fees over the last `window_slots` slots, per program.
+///
+/// Cheap to clone: clones share the same window, so one handle can be fed from the
+/// stream loop while another is queried by the bot before submitting.
+#[derive(Clone)]
+pub struct PriorityFeeTracker {
+    window_slots: u64,
+    programs: Arc<HashSet<Pubkey>>,
+    window: Arc<Mutex<FeeWindow>>,
+}
+
+impl PriorityFeeTracker {
+    pub fn new(window_slots: u64, programs: impl IntoIterator<Item = Pubkey>) -> Self {
+        Self {
+            window_slots,
+            programs: Arc::new(programs.into_iter().collect()),
+            window: Arc::default(),
+        }
+    }
+
+    /// Record a successful transaction for every tracked program it invoked.
+    pub fn record(&self, summary: &TransactionSummary) {
+        if !summary.success {
+            return;
+        }
+        let mut window = self.window.lock().expect("fee window poisoned");
+        for program_id in summary
+            .programs
+            .iter()
+            .filter(|program| self.programs.contains(program))
+        {
+            window
+                .slots
+                .entry(summary.slot)
+                .or_default()
+                .entry(*program_id)
+                .or_default()
+                .push((summary.compute_unit_price, summary.priority_fee));
+        }
+
+        if let Some(&latest) = window.slots.keys().next_back() {
+            let oldest = latest.saturating_sub(self.window_slots.saturating_sub(1));
+            window.slots = window.slots.split_off(&oldest);
+        }
+    }
+
+    pub fn stats(&self, program_id: &Pubkey) -> Option<PriorityFeeStats> {
+        let window = self.window.lock().expect("fee window poisoned");
+        let mut slots = Vec::new();
+        let (unit_prices, priority_fees): (Vec<u64>, Vec<u64>) = window
+            .slots
+            .iter()
+            .filter_map(|(slot, programs)| Some((slot, programs.get(program_id)?)))
+            .flat_map(|(slot, samples)| {
+                slots.push(*slot);
+                samples.iter().copied()
+            })
+            .unzip();
+
+        Some(PriorityFeeStats {
+            program_id: *program_id,
+            samples: unit_prices.len(),
+            first_slot: *slots.first()?,
+            last_slot: *slots.last()?,
+            unit_price: Percentiles::from_samples(unit_prices)?,
+            priority_fee: Percentiles::from_samples(priority_fees)?,
+        })
+    }
+}
@@ -0,0 +1,535 @@
+use log::{error, info};
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::db::{self, pubkey};
+use crate::decoder::{Action, DecodedTransaction};
+use crate::types::pump_fun::{CreateEvent, TradeEvent};
+
+pub const DEFAULT_CREATOR_DB: &str = "./creators.db";
+/// How often (in slots) idle launches are saved and dropped from memory.
+const EVICT_EVERY_SLOTS: u64 = 1_000;
+const SELECT_TOKEN: &str = "SELECT mint, creator, user, name, symbol, created_slot, created_at, completed, bought, sold, sol_in, sol_out, first_sell_slot, first_sell_at
+     FROM dev_tokens";
+
+/// Schema changes, see [`db::open`].
+const MIGRATIONS: &[&str] = &[
+    // 1: initial schema
+    "CREATE TABLE dev_tokens (
+        mint TEXT PRIMARY KEY,
+        creator TEXT NOT NULL,
+        user TEXT NOT NULL,
+        name TEXT NOT NULL,
+        symbol TEXT NOT NULL,
+        created_slot INTEGER NOT NULL,
+        created_at INTEGER NOT NULL,
+        completed INTEGER NOT NULL,
+        bought INTEGER NOT NULL,
+        sold INTEGER NOT NULL,
+        sol_in INTEGER NOT NULL,
+        sol_out INTEGER NOT NULL,
+        first_sell_slot INTEGER,
+        first_sell_at INTEGER
+    );
+    CREATE INDEX dev_tokens_creator ON dev_tokens (creator);",
+];
+
+/// What the dev did with one token they launched. The dev is the creator and
+/// the wallet that signed the create, usually the same.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DevToken {
+    pub mint: Pubkey,
+    pub creator: Pubkey,
+    pub user: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub created_slot: u64,
+    pub created_at: i64,
+    pub completed: bool,
+    /// Tokens the dev bought and sold, raw amounts.
+    pub bought: u64,
+    pub sold: u64,
+    /// Lamports the dev spent buying and got back selling.
+    pub sol_in: u64,
+    pub sol_out: u64,
+    pub first_sell_slot: Option<u64>,
+    pub first_sell_at: Option<i64>,
+}
+
+impl DevToken {
+    fn is_dev(&self, wallet: &Pubkey) -> bool {
+        *wallet == self.creator || *wallet == self.user
+    }
+
+    pub fn has_sold(&self) -> bool {
+        self.first_sell_at.is_some()
+    }
+
+    /// Seconds from the launch to the dev's first sell.
+    pub fn secs_to_first_sell(&self) -> Option<i64> {
+        self.first_sell_at.map(|at| at - self.created_at)
+    }
+
+    /// SOL got back selling minus SOL spent buying, lamports.
+    pub fn realized(&self) -> i64 {
+        self.sol_out as i64 - self.sol_in as i64
+    }
+
+    /// Share of the dev's bought tokens sold so far, 0 to 100.
+    pub fn sold_share(&self) -> f64 {
+        if self.bought == 0 {
+            return 0.0;
+        }
+        (self.sold as f64 * 100.0 / self.bought as f64).min(100.0)
+    }
+}
+
+/// One creator's track record over every launch seen.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CreatorProfile {
+    pub creator: Pubkey,
+    pub launched: u64,
+    pub completed: u64,
+    /// Launches the dev sold into.
+    pub dev_sold: u64,
+    /// Mean seconds from launch to the dev's first sell, over `dev_sold`.
+    pub avg_secs_to_sell: Option<f64>,
+    pub fastest_secs_to_sell: Option<i64>,
+    /// Lamports, summed over the launches.
+    pub realized: i64,
+    pub last_launch_at: Option<i64>,
+}
+
+/// A dev wallet selling a token it launched.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DevSell {
+    pub slot: u64,
+    pub signature: Signature,
+    pub timestamp: i64,
+    pub wallet: Pubkey,
+    pub sol_amount: u64,
+    pub token_amount: u64,
+    /// The dev's first sell of this token.
+    pub first: bool,
+    /// The token right after the sell.
+    pub token: DevToken,
+    /// The creator's record, this sell included.
+    pub profile: CreatorProfile,
+}
+
+fn dev_token(row: &Row) -> rusqlite::Result<DevToken> {
+    Ok(DevToken {
+        mint: pubkey(row, 0)?,
+        creator: pubkey(row, 1)?,
+        user: pubkey(row, 2)?,
+        name: row.get(3)?,
+        symbol: row.get(4)?,
+        created_slot: row.get(5)?,
+        created_at: row.get(6)?,
+        completed: row.get(7)?,
+        bought: row.get(8)?,
+        sold: row.get(9)?,
+        sol_in: row.get(10)?,
+        sol_out: row.get(11)?,
+        first_sell_slot: row.get(12)?,
+        first_sell_at: row.get(13)?,
+    })
+}
+
+/// A launch held in memory and the last slot it traded in.
+struct Active {
+    token: DevToken,
+    last_slot: u64,
+}
+
+/// Builds creator profiles from launches, dev trades and completed curves,
+/// persisted to SQLite so the track records survive restarts.
+///
+/// Only launches traded in the last `max_idle_slots` are kept in memory; older
+/// ones are read back from SQLite when traded again. Profiles are aggregated in
+/// SQLite after saving the pending changes. Changes are otherwise written by
+/// [`CreatorTracker::save`].
+pub struct CreatorTracker {
+    conn: Connection,
+    tokens: HashMap<Pubkey, Active>,
+    dirty: HashSet<Pubkey>,
+    max_idle_slots: u64,
+    last_evicted_slot: u64,
+}
+
+impl CreatorTracker {
+    pub fn open(path: impl AsRef<Path>, max_idle_slots: u64) -> anyhow::Result<Self> {
+        let conn = db::open(path, "creator db", MIGRATIONS)?;
+        let launches: u64 =
+            conn.query_row("SELECT count(*) FROM dev_tokens", [], |row| row.get(0))?;
+        if launches > 0 {
+            info!("creator db holds {launches} launches");
+        }
+        Ok(Self {
+            conn,
+            tokens: HashMap::new(),
+            dirty: HashSet::new(),
+            max_idle_slots,
+            last_evicted_slot: 0,
+        })
+    }
+
+    /// Writes the launches changed since the last save, in one transaction.
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        if self.dirty.is_empty() {
+            return Ok(());
+        }
+        let tx = self.conn.transaction()?;
+        {
+            let mut upsert = tx.prepare_cached(
+                "INSERT OR REPLACE INTO dev_tokens (mint, creator, user, name, symbol, created_slot, created_at, completed, bought, sold, sol_in, sol_out, first_sell_slot, first_sell_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            )?;
+            for token in self
+                .dirty
+                .iter()
+                .filter_map(|mint| self.tokens.get(mint))
+                .map(|active| &active.token)
+            {
+                upsert.execute(params![
+                    token.mint.to_string(),
+                    token.creator.to_string(),
+                    token.user.to_string(),
+                    token.name,
+                    token.symbol,
+                    token.created_slot,
+                    token.created_at,
+                    token.completed,
+                    token.bought,
+                    token.sold,
+                    token.sol_in,
+                    token.sol_out,
+                    token.first_sell_slot,
+                    token.first_sell_at,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        self.dirty.clear();
+        Ok(())
+    }
+
+    /// Updates the launches the transaction touches and returns the dev sells in it.
+    pub fn observe(&mut self, decoded: &DecodedTransaction) -> Vec<DevSell> {
+        let mut sells = Vec::new();
+        for action in &decoded.actions {
+            match &action.action {
+                Action::PumpFunCreate(create) => self.create(create, decoded.slot),
+                Action::PumpFunTrade(trade) => {
+                    if let Some(sell) = self.trade(trade, decoded) {
+                        sells.push(sell);
+                    }
+                }
+                Action::PumpFunComplete(complete) => {
+                    if let Some(active) = self.active(&complete.mint, decoded.slot)
+                        && !active.token.completed
+                    {
+                        active.token.completed = true;
+                        self.dirty.insert(complete.mint);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if decoded.slot >= self.last_evicted_slot + EVICT_EVERY_SLOTS {
+            self.evict_idle(decoded.slot);
+        }
+        sells
+    }
+
+    /// The launch in memory, read back from SQLite if it was evicted.
+    fn active(&mut self, mint: &Pubkey, slot: u64) -> Option<&mut Active> {
+        if !self.tokens.contains_key(mint) {
+            let loaded = self
+                .conn
+                .prepare_cached(&format!("{SELECT_TOKEN} WHERE mint = ?1"))
+                .and_then(|mut select| select.query_row([mint.to_string()], dev_token).optional());
+            match loaded {
+                Ok(Some(token)) => {
+                    self.tokens.insert(
+                        *mint,
+                        Active {
+                            token,
+                            last_slot: slot,
+                        },
+                    );
+                }
+                Ok(None) => return None,
+                Err(error) => {
+                    error!("failed to read {mint} from the creator db: {error}");
+                    return None;
+                }
+            }
+        }
+        let active = self.tokens.get_mut(mint)?;
+        active.last_slot = active.last_slot.max(slot);
+        Some(active)
+    }
+
+    /// Saves, then drops the launches idle for longer than `max_idle_slots`.
+    fn evict_idle(&mut self, slot: u64) {
+        self.last_evicted_slot = slot;
+        if let Err(error) = self.save() {
+            error!("failed to save the creator db, keeping its launches in memory: {error}");
+            return;
+        }
+        let max_idle_slots = self.max_idle_slots;
+        self.tokens
+            .retain(|_, active| slot.saturating_sub(active.last_slot) <= max_idle_slots);
+    }
+
+    fn create(&mut self, create: &CreateEvent, slot: u64) {
+        if self.active(&create.mint, slot).is_some() {
+            return;
+        }
+        let token = DevToken {
+            mint: create.mint,
+            creator: create.creator,
+            user: create.user,
+            name: create.name.clone(),
+            symbol: create.symbol.clone(),
+            created_slot: slot,
+            created_at: create.timestamp,
+            completed: false,
+            bought: 0,
+            sold: 0,
+            sol_in: 0,
+            sol_out: 0,
+            first_sell_slot: None,
+            first_sell_at: None,
+        };
+        self.tokens.insert(
+            create.mint,
+            Active {
+                token,
+                last_slot: slot,
+            },
+        );
+        self.dirty.insert(create.mint);
+    }
+
+    fn trade(&mut self, trade: &TradeEvent, decoded: &DecodedTransaction) -> Option<DevSell> {
+        if !self
+            .active(&trade.mint, decoded.slot)?
+            .token
+            .is_dev(&trade.user)
+        {
+            return None;
+        }
+        self.dirty.insert(trade.mint);
+        let token = &mut self.tokens.get_mut(&trade.mint)?.token;
+        if trade.is_buy {
+            token.bought += trade.token_amount;
+            token.sol_in += trade.sol_amount;
+            return None;
+        }
+        token.sold += trade.token_amount;
+        token.sol_out += trade.sol_amount;
+        let first = token.first_sell_at.is_none();
+        if first {
+            token.first_sell_slot = Some(decoded.slot);
+            token.first_sell_at = Some(trade.timestamp);
+        }
+        let token = token.clone();
+        let profile = self.profile(&token.creator).unwrap_or_else(|error| {
+            error!("failed to read the profile of {}: {error}", token.creator);
+            CreatorProfile {
+                creator: token.creator,
+                ..Default::default()
+            }
+        });
+        Some(DevSell {
+            slot: decoded.slot,
+            signature: decoded.signature,
+            timestamp: trade.timestamp,
+            wallet: trade.user,
+            sol_amount: trade.sol_amount,
+            token_amount: trade.token_amount,
+            first,
+            profile,
+            token,
+        })
+    }
+
+    /// A launch traded in the last `max_idle_slots`.
+    pub fn token(&self, mint: &Pubkey) -> Option<&DevToken> {
+        self.tokens.get(mint).map(|active| &active.token)
+    }
+
+    /// The creator's record over every launch, pending changes included.
+    pub fn profile(&mut self, creator: &Pubkey) -> anyhow::Result<CreatorProfile> {
+        self.aggregate(creator, None)
+    }
+
+    /// The creator's record over their other launches, e.g. to judge `mint`.
+    pub fn profile_without(
+        &mut self,
+        creator: &Pubkey,
+        mint: &Pubkey,
+    ) -> anyhow::Result<CreatorProfile> {
+        self.aggregate(creator, Some(mint))
+    }
+
+    fn aggregate(
+        &mut self,
+        creator: &Pubkey,
+        without: Option<&Pubkey>,
+    ) -> anyhow::Result<CreatorProfile> {
+        self.save()?;
+        let mut select = self.conn.prepare_cached(
+            "SELECT count(*), coalesce(sum(completed), 0), count(first_sell_at),
+                    avg(first_sell_at - created_at), min(first_sell_at - created_at),
+                    coalesce(sum(sol_out - sol_in), 0), max(created_at)
+             FROM dev_tokens WHERE creator = ?1 AND mint IS NOT ?2",
+        )?;
+        let profile = select.query_row(
+            params![creator.to_string(), without.map(Pubkey::to_string)],
+            |row| {
+                Ok(CreatorProfile {
+                    creator: *creator,
+                    launched: row.get(0)?,
+                    completed: row.get(1)?,
+                    dev_sold: row.get(2)?,
+                    avg_secs_to_sell: row.get(3)?,
+                    fastest_secs_to_sell: row.get(4)?,
+                    realized: row.get(5)?,
+                    last_launch_at: row.get(6)?,
+                })
+            },
+        )?;
+        Ok(profile)
+    }
+
+    /// Launches held in memory.
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+}
+
+impl Drop for CreatorTracker {
+    fn drop(&mut self) {
+        if let Err(error) = self.save() {
+            error!("failed to save the creator db: {error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::pump_fun::CompleteEvent;
+    use std::env;
+
+    fn create(creator: Pubkey, mint: Pubkey, timestamp: i64) -> Action {
+        Action::PumpFunCreate(CreateEvent {
+            name: "Token".into(),
+            symbol: "TOK".into(),
+            mint,
+            user: creator,
+            creator,
+            timestamp,
+            ..Default::default()
+        })
+    }
+
+    fn trade(user: Pubkey, mint: Pubkey, is_buy: bool, sol: u64, timestamp: i64) -> Action {
+        Action::PumpFunTrade(TradeEvent {
+            mint,
+            sol_amount: sol,
+            token_amount: 1_000,
+            is_buy,
+            user,
+            timestamp,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn profiles_survive_reopen_and_eviction() {
+        let path = env::temp_dir().join(format!("creators-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let creator = Pubkey::new_unique();
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let mut tracker = CreatorTracker::open(&path, 100).unwrap();
+        tracker.observe(&DecodedTransaction::with_actions(
+            10,
+            vec![
+                create(creator, first, 1_000),
+                trade(creator, first, true, 500, 1_000),
+            ],
+        ));
+        let sells = tracker.observe(&DecodedTransaction::with_actions(
+            20,
+            vec![trade(creator, first, false, 800, 1_030)],
+        ));
+        assert_eq!(sells.len(), 1);
+        assert!(sells[0].first);
+        assert_eq!(sells[0].profile.dev_sold, 1);
+        assert_eq!(sells[0].profile.realized, 300);
+        tracker.observe(&DecodedTransaction::with_actions(
+            30,
+            vec![
+                create(creator, second, 2_000),
+                Action::PumpFunComplete(CompleteEvent {
+                    mint: second,
+                    ..Default::default()
+                }),
+            ],
+        ));
+        tracker.save().unwrap();
+        drop(tracker);
+
+        let mut tracker = CreatorTracker::open(&path, 100).unwrap();
+        assert!(tracker.is_empty());
+        let profile = tracker.profile(&creator).unwrap();
+        assert_eq!(
+            profile,
+            CreatorProfile {
+                creator,
+                launched: 2,
+                completed: 1,
+                dev_sold: 1,
+                avg_secs_to_sell: Some(30.0),
+                fastest_secs_to_sell: Some(30),
+                realized: 300,
+                last_launch_at: Some(2_000),
+            }
+        );
+        let without = tracker.profile_without(&creator, &first).unwrap();
+        assert_eq!((without.launched, without.dev_sold), (1, 0));
+
+        // a later sell reads the launch back and continues its record
+        let sells = tracker.observe(&DecodedTransaction::with_actions(
+            40,
+            vec![trade(creator, first, false, 100, 1_060)],
+        ));
+        assert!(!sells[0].first);
+        assert_eq!(sells[0].token.sold, 2_000);
+        assert_eq!(sells[0].token.first_sell_at, Some(1_030));
+        assert_eq!(sells[0].profile.realized, 400);
+        assert_eq!(tracker.len(), 1);
+
+        // idle launches leave memory but stay in the profile
+        tracker.observe(&DecodedTransaction::with_actions(
+            EVICT_EVERY_SLOTS + 40,
+            vec![],
+        ));
+        assert!(tracker.token(&first).is_none());
+        assert_eq!(tracker.profile(&creator).unwrap().realized, 400);
+        drop(tracker);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
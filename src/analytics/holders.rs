@@ -0,0 +1,472 @@
+use serde_json::json;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_response::{OptionalContext, RpcKeyedAccount};
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::extension::StateWithExtensions;
+use spl_token_2022::state::Account;
+use std::collections::{HashMap, HashSet};
+use yellowstone_grpc_proto::geyser::SubscribeRequestFilterAccounts;
+
+use crate::decoder::{Action, DecodedTransaction};
+use crate::filters::token_accounts_filter;
+use crate::types::constant::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use crate::types::swap::{Venue, bonding_curve_address, canonical_pool_address};
+
+/// Percent of the supply held by the top holders.
+pub const DEFAULT_HOLDER_THRESHOLDS: &str = "20,30,50";
+pub const TOP_HOLDERS: usize = 10;
+/// 1B tokens with 6 decimals, the supply of every pump.fun mint.
+const PUMP_TOTAL_SUPPLY: u64 = 1_000_000_000_000_000;
+
+/// Ascending percents, e.g. `20,30,50`.
+pub fn parse_thresholds(spec: &str) -> anyhow::Result<Vec<f64>> {
+    let mut thresholds = spec
+        .split(',')
+        .map(str::trim)
+        .filter(|threshold| !threshold.is_empty())
+        .map(|threshold| match threshold.parse::<f64>() {
+            Ok(percent) if percent > 0.0 && percent <= 100.0 => Ok(percent),
+            _ => anyhow::bail!("invalid holder threshold {threshold}"),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    thresholds.sort_by(f64::total_cmp);
+    thresholds.dedup();
+    Ok(thresholds)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Holder {
+    pub owner: Pubkey,
+    /// Raw amount over all of the owner's token accounts of the mint.
+    pub amount: u64,
+}
+
+/// The top holders' share of a mint crossed a threshold.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HolderAlert {
+    pub mint: Pubkey,
+    pub slot: u64,
+    /// The furthest threshold crossed, percent.
+    pub threshold: f64,
+    pub rising: bool,
+    /// Percent of the supply held by [`HolderAlert::top`], now and before.
+    pub top_share: f64,
+    pub previous_share: f64,
+    /// Owners with a balance, the curve and pools excluded.
+    pub holders: usize,
+    pub top: Vec<Holder>,
+    pub supply: u64,
+}
+
+struct TokenAccount {
+    mint: Pubkey,
+    owner: Pubkey,
+    amount: u64,
+    /// Of the update it was last set from.
+    slot: u64,
+    write_version: u64,
+}
+
+struct AccountUpdate {
+    pubkey: Pubkey,
+    data: Vec<u8>,
+    slot: u64,
+    write_version: u64,
+}
+
+struct MintHolders {
+    supply: u64,
+    /// owner -> amount
+    balances: HashMap<Pubkey, u64>,
+    /// The bonding curve and pools, holding liquidity rather than a position.
+    excluded: HashSet<Pubkey>,
+    top_share: f64,
+    /// Thresholds at or below `top_share`.
+    level: usize,
+}
+
+impl MintHolders {
+    fn top(&self) -> (Vec<Holder>, usize) {
+        let mut holders = self
+            .balances
+            .iter()
+            .filter(|(owner, amount)| **amount > 0 && !self.excluded.contains(owner))
+            .map(|(owner, amount)| Holder {
+                owner: *owner,
+                amount: *amount,
+            })
+            .collect::<Vec<_>>();
+        let count = holders.len();
+        holders.sort_by(|a, b| b.amount.cmp(&a.amount).then(a.owner.cmp(&b.owner)));
+        holders.truncate(TOP_HOLDERS);
+        (holders, count)
+    }
+}
+
+/// Live owner balances of the tracked mints from their token account updates.
+///
+/// Account subscriptions only send changes, so a mint's holders are partial,
+/// and its first alerts spurious, unless it is [`HolderTracker::load`]ed first.
+/// To miss nothing, subscribe, [`HolderTracker::start_loading`], fetch the
+/// snapshots with [`fetch_token_accounts`], load them and
+/// [`HolderTracker::finish_loading`]: updates arriving in between are held back
+/// and applied on top of the snapshots.
+pub struct HolderTracker {
+    thresholds: Vec<f64>,
+    mints: HashMap<Pubkey, MintHolders>,
+    /// token account -> its state
+    accounts: HashMap<Pubkey, TokenAccount>,
+    /// Updates held back while loading.
+    buffered: Option<Vec<AccountUpdate>>,
+}
+
+impl HolderTracker {
+    pub fn new(thresholds: Vec<f64>) -> Self {
+        Self {
+            thresholds,
+            mints: HashMap::new(),
+            accounts: HashMap::new(),
+            buffered: None,
+        }
+    }
+
+    pub fn track(&mut self, mint: Pubkey) {
+        self.mints.entry(mint).or_insert_with(|| MintHolders {
+            supply: PUMP_TOTAL_SUPPLY,
+            balances: HashMap::new(),
+            excluded: HashSet::from([bonding_curve_address(&mint), canonical_pool_address(&mint)]),
+            top_share: 0.0,
+            level: 0,
+        });
+    }
+
+    pub fn untrack(&mut self, mint: &Pubkey) {
+        if self.mints.remove(mint).is_some() {
+            self.accounts.retain(|_, account| account.mint != *mint);
+        }
+    }
+
+    pub fn mints(&self) -> impl Iterator<Item = &Pubkey> {
+        self.mints.keys()
+    }
+
+    /// The account filters to subscribe with, one per tracked mint.
+    pub fn filters(&self) -> HashMap<String, SubscribeRequestFilterAccounts> {
+        self.mints
+            .keys()
+            .map(|mint| (format!("holders-{mint}"), token_accounts_filter(mint)))
+            .collect()
+    }
+
+    /// The top holders and how many holders there are.
+    pub fn top_holders(&self, mint: &Pubkey) -> Option<(Vec<Holder>, usize)> {
+        Some(self.mints.get(mint)?.top())
+    }
+
+    /// Learns pools and supplies of the tracked mints from transactions.
+    pub fn observe(&mut self, decoded: &DecodedTransaction) {
+        for action in &decoded.actions {
+            match &action.action {
+                Action::PumpFunCreate(create) => {
+                    if let Some(holders) = self.mints.get_mut(&create.mint) {
+                        holders.supply = create.token_total_supply;
+                    }
+                }
+                Action::PumpAmmCreatePool(event) => {
+                    if let Some(holders) = self.mints.get_mut(&event.base_mint) {
+                        holders.excluded.insert(event.pool);
+                    }
+                }
+                _ => {}
+            }
+        }
+        for swap in decoded.swaps() {
+            if swap.venue == Venue::PumpAmm
+                && let Some(holders) = self.mints.get_mut(&swap.base_mint())
+            {
+                holders.excluded.insert(swap.pool);
+            }
+        }
+    }
+
+    /// Applies a token account update, `data` being empty once it is closed.
+    /// Updates older than the account's last one by `(slot, write_version)`
+    /// are ignored.
+    pub fn observe_account(
+        &mut self,
+        pubkey: Pubkey,
+        data: &[u8],
+        slot: u64,
+        write_version: u64,
+    ) -> Option<HolderAlert> {
+        if let Some(buffered) = &mut self.buffered {
+            buffered.push(AccountUpdate {
+                pubkey,
+                data: data.to_vec(),
+                slot,
+                write_version,
+            });
+            return None;
+        }
+        let mint = self.apply(pubkey, data, slot, write_version)?;
+        self.check(&mint, slot)
+    }
+
+    /// Holds back account updates until [`HolderTracker::finish_loading`].
+    pub fn start_loading(&mut self) {
+        self.buffered.get_or_insert_with(Vec::new);
+    }
+
+    /// Sets `accounts` of [`fetch_token_accounts`] as the starting state of
+    /// `mint`, without alerts.
+    pub fn load(&mut self, mint: &Pubkey, accounts: &[(Pubkey, Vec<u8>, u64)]) {
+        self.track(*mint);
+        for (pubkey, data, slot) in accounts {
+            // within a slot the streamed update wins
+            self.apply(*pubkey, data, *slot, 0);
+        }
+        if let Some(holders) = self.mints.get_mut(mint) {
+            let (top, _) = holders.top();
+            holders.top_share = share(&top, holders.supply);
+            holders.level = level(&self.thresholds, holders.top_share);
+        }
+    }
+
+    /// Applies the updates held back since [`HolderTracker::start_loading`].
+    pub fn finish_loading(&mut self) -> Vec<HolderAlert> {
+        self.buffered
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|update| {
+                self.observe_account(
+                    update.pubkey,
+                    &update.data,
+                    update.slot,
+                    update.write_version,
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the mint of a tracked token account that changed.
+    fn apply(
+        &mut self,
+        pubkey: Pubkey,
+        data: &[u8],
+        slot: u64,
+        write_version: u64,
+    ) -> Option<Pubkey> {
+        if let Some(known) = self.accounts.get(&pubkey)
+            && (known.slot, known.write_version) > (slot, write_version)
+        {
+            return None;
+        }
+        let state = StateWithExtensions::<Account>::unpack(data)
+            .ok()
+            .map(|state| state.base)
+            .filter(|account| self.mints.contains_key(&account.mint));
+
+        let previous = self.accounts.remove(&pubkey);
+        if let Some(previous) = &previous
+            && let Some(holders) = self.mints.get_mut(&previous.mint)
+            && let Some(balance) = holders.balances.get_mut(&previous.owner)
+        {
+            *balance = balance.saturating_sub(previous.amount);
+            if *balance == 0 {
+                holders.balances.remove(&previous.owner);
+            }
+        }
+        let Some(account) = state else {
+            // closed, or no longer an account of a tracked mint
+            return previous.map(|previous| previous.mint);
+        };
+        let holders = self.mints.get_mut(&account.mint)?;
+        *holders.balances.entry(account.owner).or_default() += account.amount;
+        self.accounts.insert(
+            pubkey,
+            TokenAccount {
+                mint: account.mint,
+                owner: account.owner,
+                amount: account.amount,
+                slot,
+                write_version,
+            },
+        );
+        Some(account.mint)
+    }
+
+    fn check(&mut self, mint: &Pubkey, slot: u64) -> Option<HolderAlert> {
+        let holders = self.mints.get_mut(mint)?;
+        let (top, count) = holders.top();
+        let previous_share = holders.top_share;
+        holders.top_share = share(&top, holders.supply);
+        let previous_level = holders.level;
+        holders.level = level(&self.thresholds, holders.top_share);
+        if holders.level == previous_level {
+            return None;
+        }
+        let rising = holders.level > previous_level;
+        Some(HolderAlert {
+            mint: *mint,
+            slot,
+            threshold: if rising {
+                self.thresholds[holders.level - 1]
+            } else {
+                self.thresholds[holders.level]
+            },
+            rising,
+            top_share: holders.top_share,
+            previous_share,
+            holders: count,
+            top,
+            supply: holders.supply,
+        })
+    }
+}
+
+/// Every token account of `mint` under both token programs, with the slot of
+/// the RPC snapshot it was read at.
+pub async fn fetch_token_accounts(
+    rpc: &RpcClient,
+    mint: &Pubkey,
+) -> anyhow::Result<Vec<(Pubkey, Vec<u8>, u64)>> {
+    let mut accounts = Vec::new();
+    for program in [TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID] {
+        let config = json!({
+            "encoding": "base64",
+            "commitment": rpc.commitment().commitment,
+            "withContext": true,
+            "filters": [{ "memcmp": { "offset": 0, "bytes": mint.to_string() } }],
+        });
+        let response = rpc
+            .send::<OptionalContext<Vec<RpcKeyedAccount>>>(
+                RpcRequest::GetProgramAccounts,
+                json!([program.to_string(), config]),
+            )
+            .await?;
+        let (slot, keyed) = match response {
+            OptionalContext::Context(response) => (response.context.slot, response.value),
+            OptionalContext::NoContext(_) => anyhow::bail!("getProgramAccounts without a context"),
+        };
+        for keyed in keyed {
+            let data = keyed
+                .account
+                .data
+                .decode()
+                .ok_or_else(|| anyhow::anyhow!("undecodable account {}", keyed.pubkey))?;
+            accounts.push((keyed.pubkey.parse()?, data, slot));
+        }
+    }
+    Ok(accounts)
+}
+
+fn share(top: &[Holder], supply: u64) -> f64 {
+    if supply == 0 {
+        return 0.0;
+    }
+    top.iter().map(|holder| holder.amount as u128).sum::<u128>() as f64 * 100.0 / supply as f64
+}
+
+fn level(thresholds: &[f64], share: f64) -> usize {
+    thresholds
+        .iter()
+        .filter(|threshold| share >= **threshold)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::program_pack::Pack;
+    use spl_token_2022::state::AccountState;
+
+    fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Vec<u8> {
+        let mut data = vec![0; Account::LEN];
+        Account {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            state: AccountState::Initialized,
+            ..Default::default()
+        }
+        .pack_into_slice(&mut data);
+        data
+    }
+
+    fn amount(tracker: &HolderTracker, mint: &Pubkey, owner: &Pubkey) -> u64 {
+        let (top, _) = tracker.top_holders(mint).unwrap();
+        top.iter()
+            .find(|holder| holder.owner == *owner)
+            .map_or(0, |holder| holder.amount)
+    }
+
+    #[test]
+    fn updates_apply_in_slot_and_write_version_order() {
+        let (mint, owner, account) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut tracker = HolderTracker::new(vec![20.0]);
+        tracker.track(mint);
+        tracker.observe_account(account, &token_account(&mint, &owner, 5), 10, 2);
+        // an earlier write in the same slot is ignored
+        tracker.observe_account(account, &token_account(&mint, &owner, 3), 10, 1);
+        assert_eq!(amount(&tracker, &mint, &owner), 5);
+        tracker.observe_account(account, &token_account(&mint, &owner, 7), 10, 3);
+        assert_eq!(amount(&tracker, &mint, &owner), 7);
+        tracker.observe_account(account, &token_account(&mint, &owner, 9), 9, 100);
+        assert_eq!(amount(&tracker, &mint, &owner), 7);
+    }
+
+    #[test]
+    fn updates_while_loading_go_on_top_of_the_snapshot() {
+        let mint = Pubkey::new_unique();
+        let (whale, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (whale_account, other_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut tracker = HolderTracker::new(vec![20.0, 50.0]);
+        tracker.track(mint);
+        tracker.start_loading();
+
+        // a stale update from before the snapshot, a sell after it
+        let stale = token_account(&mint, &other, 1);
+        assert!(
+            tracker
+                .observe_account(other_account, &stale, 90, 1)
+                .is_none()
+        );
+        let sold = token_account(&mint, &whale, PUMP_TOTAL_SUPPLY / 10);
+        assert!(
+            tracker
+                .observe_account(whale_account, &sold, 101, 1)
+                .is_none()
+        );
+        assert_eq!(tracker.top_holders(&mint).unwrap().1, 0);
+
+        tracker.load(
+            &mint,
+            &[
+                (
+                    whale_account,
+                    token_account(&mint, &whale, PUMP_TOTAL_SUPPLY * 3 / 10),
+                    100,
+                ),
+                (other_account, token_account(&mint, &other, 2), 100),
+            ],
+        );
+        assert_eq!(amount(&tracker, &mint, &whale), PUMP_TOTAL_SUPPLY * 3 / 10);
+
+        let alerts = tracker.finish_loading();
+        assert_eq!(amount(&tracker, &mint, &whale), PUMP_TOTAL_SUPPLY / 10);
+        assert_eq!(amount(&tracker, &mint, &other), 2);
+        assert_eq!(alerts.len(), 1);
+        assert!(!alerts[0].rising);
+        assert_eq!(alerts[0].threshold, 20.0);
+
+        // applies as soon as the load finishes
+        tracker.observe_account(other_account, &[], 102, 1);
+        assert_eq!(tracker.top_holders(&mint).unwrap().1, 1);
+    }
+}
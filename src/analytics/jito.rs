@@ -0,0 +1,233 @@
+use solana_sdk::{pubkey, pubkey::Pubkey, signature::Signature};
+use std::collections::BTreeMap;
+
+use crate::decoder::{Action, DecodedTransaction};
+
+pub const JITO_TIP_ACCOUNTS: [Pubkey; 8] = [
+    pubkey!("96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5"),
+    pubkey!("HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe"),
+    pubkey!("Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY"),
+    pubkey!("ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49"),
+    pubkey!("DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh"),
+    pubkey!("ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt"),
+    pubkey!("DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL"),
+    pubkey!("3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT"),
+];
+
+pub const MAX_BUNDLE_SIZE: usize = 5;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct JitoTip {
+    pub payer: Pubkey,
+    pub tip_account: Pubkey,
+    pub lamports: u64,
+    pub instruction_index: usize,
+}
+
+pub fn is_tip_account(account: &Pubkey) -> bool {
+    JITO_TIP_ACCOUNTS.contains(account)
+}
+
+/// System transfers to a Jito tip account, top-level or CPI.
+pub fn find_tips(decoded: &DecodedTransaction) -> Vec<JitoTip> {
+    decoded
+        .actions
+        .iter()
+        .filter_map(|decoded| match &decoded.action {
+            Action::SystemTransfer { from, to, lamports } if is_tip_account(to) => Some(JitoTip {
+                payer: *from,
+                tip_account: *to,
+                lamports: *lamports,
+                instruction_index: decoded.instruction_index,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BundledBuy {
+    pub signature: Signature,
+    pub user: Pubkey,
+    pub sol_amount: u64,
+    pub token_amount: u64,
+}
+
+/// "created + N bundled buys" for one token launch.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LaunchReport {
+    pub mint: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub creator: Pubkey,
+    pub slot: u64,
+    pub signature: Signature,
+    /// Members of the inferred bundle in block order, empty when the create
+    /// transaction was not bundled.
+    pub bundle: Vec<Signature>,
+    pub tip_lamports: u64,
+    pub bundled_buys: Vec<BundledBuy>,
+}
+
+impl LaunchReport {
+    pub fn bundled_sol(&self) -> u64 {
+        self.bundled_buys.iter().map(|buy| buy.sol_amount).sum()
+    }
+}
+
+struct ObservedTransaction {
+    decoded: DecodedTransaction,
+    tips: Vec<JitoTip>,
+}
+
+impl ObservedTransaction {
+    fn buys<'a>(&'a self, mint: &'a Pubkey) -> impl Iterator<Item = BundledBuy> + 'a {
+        self.decoded
+            .actions
+            .iter()
+            .filter_map(move |action| match &action.action {
+                Action::PumpFunTrade(trade) if trade.is_buy && trade.mint == *mint => {
+                    Some(BundledBuy {
+                        signature: self.decoded.signature,
+                        user: trade.user,
+                        sol_amount: trade.sol_amount,
+                        token_amount: trade.token_amount,
+                    })
+                }
+                _ => None,
+            })
+    }
+}
+
+/// Infers Jito bundles from the transactions seen in the stream.
+///
+/// A bundle is a run of up to 5 transactions with consecutive block indexes that ends
+/// with a tipping transaction; consecutive tipping transactions from the same payer are
+/// kept in the same bundle. Slots are only evaluated once `settle_slots` newer slots
+/// have been seen, since transactions of a slot may still be arriving.
+pub struct BundleTracker {
+    settle_slots: u64,
+    pending: BTreeMap<u64, Vec<ObservedTransaction>>,
+}
+
+impl BundleTracker {
+    pub fn new(settle_slots: u64) -> Self {
+        Self {
+            settle_slots,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the launch reports of every slot that settled.
+    pub fn observe(&mut self, decoded: &DecodedTransaction) -> Vec<LaunchReport> {
+        let tips = find_tips(decoded);
+        self.pending
+            .entry(decoded.slot)
+            .or_default()
+            .push(ObservedTransaction {
+                decoded: decoded.clone(),
+                tips,
+            });
+
+        let settled = decoded.slot.saturating_sub(self.settle_slots);
+        let keep = self.pending.split_off(&settled);
+        let done = std::mem::replace(&mut self.pending, keep);
+        done.into_values().flat_map(Self::evaluate_slot).collect()
+    }
+
+    /// Evaluate every pending slot, e.g. on shutdown.
+    pub fn flush(&mut self) -> Vec<LaunchReport> {
+        std::mem::take(&mut self.pending)
+            .into_values()
+            .flat_map(Self::evaluate_slot)
+            .collect()
+    }
+
+    fn evaluate_slot(mut transactions: Vec<ObservedTransaction>) -> Vec<LaunchReport> {
+        transactions.sort_by_key(|tx| tx.decoded.index);
+        transactions.dedup_by_key(|tx| tx.decoded.index);
+
+        let bundles = Self::infer_bundles(&transactions);
+        let mut reports = Vec::new();
+        for (position, tx) in transactions.iter().enumerate() {
+            for action in &tx.decoded.actions {
+                let Action::PumpFunCreate(create) = &action.action else {
+                    continue;
+                };
+                let members = bundles
+                    .iter()
+                    .find(|members| members.contains(&position))
+                    .into_iter()
+                    .flatten()
+                    .map(|member| &transactions[*member])
+                    .collect::<Vec<_>>();
+
+                reports.push(LaunchReport {
+                    mint: create.mint,
+                    name: create.name.clone(),
+                    symbol: create.symbol.clone(),
+                    creator: create.creator,
+                    slot: tx.decoded.slot,
+                    signature: tx.decoded.signature,
+                    bundle: members
+                        .iter()
+                        .map(|member| member.decoded.signature)
+                        .collect(),
+                    tip_lamports: members
+                        .iter()
+                        .flat_map(|member| &member.tips)
+                        .map(|tip| tip.lamports)
+                        .sum(),
+                    bundled_buys: members
+                        .iter()
+                        .flat_map(|member| member.buys(&create.mint))
+                        .collect(),
+                });
+            }
+        }
+        reports
+    }
+
+    /// Bundles as positions into `transactions` (sorted by block index).
+    fn infer_bundles(transactions: &[ObservedTransaction]) -> Vec<Vec<usize>> {
+        let mut bundles: Vec<Vec<usize>> = Vec::new();
+        let mut start = 0;
+        for (position, tx) in transactions.iter().enumerate() {
+            let Some(tip) = tx.tips.first() else {
+                continue;
+            };
+
+            let contiguous = |a: usize, b: usize| {
+                transactions[b].decoded.index == transactions[a].decoded.index + 1
+            };
+            // same payer tipping again right after the previous bundle: extend it
+            if let Some(last) = bundles.last_mut() {
+                let previous = *last.last().expect("bundle is never empty");
+                let same_payer = transactions[previous]
+                    .tips
+                    .first()
+                    .is_some_and(|previous_tip| previous_tip.payer == tip.payer);
+                if same_payer
+                    && previous + 1 == position
+                    && contiguous(previous, position)
+                    && last.len() < MAX_BUNDLE_SIZE
+                {
+                    last.push(position);
+                    start = position + 1;
+                    continue;
+                }
+            }
+
+            let mut first = position;
+            while first > start
+                && position - first + 1 < MAX_BUNDLE_SIZE
+                && contiguous(first - 1, first)
+            {
+                first -= 1;
+            }
+            bundles.push((first..=position).collect());
+            start = position + 1;
+        }
+        bundles
+    }
+}
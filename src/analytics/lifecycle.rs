@@ -0,0 +1,533 @@
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::collections::HashMap;
+
+use crate::decoder::{Action, DecodedTransaction};
+use crate::types::pump_fun::{CompleteEvent, CreateEvent, CreatePoolEvent, TradeEvent};
+use crate::types::swap::{SwapEvent, SwapSide, Venue};
+
+/// Tokens without a trade for this many slots (~1 day) are forgotten.
+pub const DEFAULT_MAX_IDLE_SLOTS: u64 = 216_000;
+/// How often (in slots) idle tokens are looked for.
+const EVICT_EVERY_SLOTS: u64 = 1_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// Trading on the bonding curve.
+    BondingCurve,
+    /// The curve sold out; waiting for the pump AMM pool.
+    Complete,
+    /// Trading on the pump AMM pool.
+    Migrated,
+}
+
+impl Stage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::BondingCurve => "bonding_curve",
+            Stage::Complete => "complete",
+            Stage::Migrated => "migrated",
+        }
+    }
+}
+
+/// What is known about a token seen created on pump.fun.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenState {
+    pub mint: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub creator: Pubkey,
+    pub bonding_curve: Pubkey,
+    pub stage: Stage,
+    pub created_slot: u64,
+    pub created_at: i64,
+    pub create_signature: Signature,
+    pub token_total_supply: u64,
+    /// Real token reserves at creation, what the curve sells before completing.
+    pub initial_real_token_reserves: u64,
+    /// The curve's virtual reserves, the pool's reserves once migrated.
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    /// Lamports traded either way, on the curve and then the pool, fees excluded.
+    pub volume: u64,
+    pub buys: u64,
+    pub sells: u64,
+    pub last_trade_slot: u64,
+    /// Net tokens bought per wallet, from the curve and pool trades seen.
+    /// Transfers aren't seen, so the holder count derived from it is an estimate.
+    balances: HashMap<Pubkey, u64>,
+    pub complete_slot: Option<u64>,
+    pub completed_at: Option<i64>,
+    pub pool: Option<Pubkey>,
+    pub migrated_slot: Option<u64>,
+    pub migrated_at: Option<i64>,
+}
+
+impl TokenState {
+    fn new(create: &CreateEvent, slot: u64, signature: Signature) -> Self {
+        Self {
+            mint: create.mint,
+            name: create.name.clone(),
+            symbol: create.symbol.clone(),
+            uri: create.uri.clone(),
+            creator: create.creator,
+            bonding_curve: create.bonding_curve,
+            stage: Stage::BondingCurve,
+            created_slot: slot,
+            created_at: create.timestamp,
+            create_signature: signature,
+            token_total_supply: create.token_total_supply,
+            initial_real_token_reserves: create.real_token_reserves,
+            virtual_sol_reserves: create.virtual_sol_reserves,
+            virtual_token_reserves: create.virtual_token_reserves,
+            real_sol_reserves: 0,
+            real_token_reserves: create.real_token_reserves,
+            volume: 0,
+            buys: 0,
+            sells: 0,
+            last_trade_slot: slot,
+            balances: HashMap::new(),
+            complete_slot: None,
+            completed_at: None,
+            pool: None,
+            migrated_slot: None,
+            migrated_at: None,
+        }
+    }
+
+    pub fn trade_count(&self) -> u64 {
+        self.buys + self.sells
+    }
+
+    /// Share of the curve's tokens sold, 0 to 100.
+    pub fn progress(&self) -> f64 {
+        if self.stage != Stage::BondingCurve {
+            return 100.0;
+        }
+        if self.initial_real_token_reserves == 0 {
+            return 0.0;
+        }
+        let sold = self
+            .initial_real_token_reserves
+            .saturating_sub(self.real_token_reserves);
+        sold as f64 * 100.0 / self.initial_real_token_reserves as f64
+    }
+
+    /// Market cap at the curve price (the pool price once migrated), lamports.
+    pub fn market_cap(&self) -> u64 {
+        if self.virtual_token_reserves == 0 {
+            return 0;
+        }
+        (self.virtual_sol_reserves as u128 * self.token_total_supply as u128
+            / self.virtual_token_reserves as u128) as u64
+    }
+
+    /// Wallets holding tokens bought on the curve or the pool.
+    pub fn holders(&self) -> usize {
+        self.balances
+            .values()
+            .filter(|balance| **balance > 0)
+            .count()
+    }
+
+    /// Seconds from creation to the curve completing.
+    pub fn time_to_complete(&self) -> Option<i64> {
+        self.completed_at.map(|at| at - self.created_at)
+    }
+
+    fn trade(&mut self, trade: &TradeEvent, slot: u64) {
+        self.virtual_sol_reserves = trade.virtual_sol_reserves;
+        self.virtual_token_reserves = trade.virtual_token_reserves;
+        self.real_sol_reserves = trade.real_sol_reserves;
+        self.real_token_reserves = trade.real_token_reserves;
+        self.volume += trade.sol_amount;
+        self.last_trade_slot = slot;
+        self.count(trade.user, trade.is_buy, trade.token_amount);
+    }
+
+    fn swap(&mut self, swap: &SwapEvent) {
+        self.virtual_sol_reserves = swap.reserves.quote;
+        self.virtual_token_reserves = swap.reserves.base;
+        self.volume += swap.quote_amount_without_fees();
+        self.last_trade_slot = swap.slot;
+        self.count(swap.trader, swap.side == SwapSide::Buy, swap.base_amount());
+    }
+
+    fn count(&mut self, wallet: Pubkey, is_buy: bool, tokens: u64) {
+        let balance = self.balances.entry(wallet).or_default();
+        if is_buy {
+            self.buys += 1;
+            *balance += tokens;
+        } else {
+            self.sells += 1;
+            *balance = balance.saturating_sub(tokens);
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LifecycleTransition {
+    /// The stage entered.
+    pub stage: Stage,
+    pub slot: u64,
+    pub signature: Signature,
+    /// The token right after the transition.
+    pub token: TokenState,
+}
+
+/// Follows every token created on pump.fun through its curve trades, the curve
+/// completing, the pump AMM pool being created for it and the pool's swaps.
+pub struct LifecycleTracker {
+    tokens: HashMap<Pubkey, TokenState>,
+    max_idle_slots: u64,
+    last_evicted_slot: u64,
+}
+
+impl LifecycleTracker {
+    pub fn new(max_idle_slots: u64) -> Self {
+        Self {
+            tokens: HashMap::new(),
+            max_idle_slots,
+            last_evicted_slot: 0,
+        }
+    }
+
+    /// Updates the tokens the transaction touches and returns the stages they entered.
+    pub fn observe(&mut self, decoded: &DecodedTransaction) -> Vec<LifecycleTransition> {
+        let mut transitions = Vec::new();
+        for action in &decoded.actions {
+            let entered = match &action.action {
+                Action::PumpFunCreate(create) => self.create(create, decoded),
+                Action::PumpFunTrade(trade) => {
+                    if let Some(token) = self.tokens.get_mut(&trade.mint) {
+                        token.trade(trade, decoded.slot);
+                    }
+                    None
+                }
+                Action::PumpFunComplete(complete) => self.complete(complete, decoded.slot),
+                Action::PumpAmmCreatePool(create_pool) => self.migrate(create_pool, decoded.slot),
+                _ => None,
+            };
+            if let Some(token) = entered {
+                transitions.push(LifecycleTransition {
+                    stage: token.stage,
+                    slot: decoded.slot,
+                    signature: decoded.signature,
+                    token,
+                });
+            }
+        }
+
+        // after migration the token trades on its pool, keyed by the base mint
+        for swap in decoded
+            .swaps()
+            .iter()
+            .filter(|swap| swap.venue == Venue::PumpAmm)
+        {
+            if let Some(token) = self.tokens.get_mut(&swap.base_mint())
+                && token.pool == Some(swap.pool)
+            {
+                token.swap(swap);
+            }
+        }
+
+        if decoded.slot >= self.last_evicted_slot + EVICT_EVERY_SLOTS {
+            self.evict_idle(decoded.slot);
+        }
+        transitions
+    }
+
+    fn create(&mut self, create: &CreateEvent, decoded: &DecodedTransaction) -> Option<TokenState> {
+        let token = TokenState::new(create, decoded.slot, decoded.signature);
+        self.tokens.insert(create.mint, token.clone());
+        Some(token)
+    }
+
+    fn complete(&mut self, complete: &CompleteEvent, slot: u64) -> Option<TokenState> {
+        let token = self.tokens.get_mut(&complete.mint)?;
+        token.stage = Stage::Complete;
+        token.complete_slot = Some(slot);
+        token.completed_at = Some(complete.timestamp);
+        Some(token.clone())
+    }
+
+    fn migrate(&mut self, create_pool: &CreatePoolEvent, slot: u64) -> Option<TokenState> {
+        let token = self.tokens.get_mut(&create_pool.base_mint)?;
+        token.stage = Stage::Migrated;
+        token.pool = Some(create_pool.pool);
+        token.virtual_sol_reserves = create_pool.pool_quote_amount;
+        token.virtual_token_reserves = create_pool.pool_base_amount;
+        token.last_trade_slot = slot;
+        token.migrated_slot = Some(slot);
+        token.migrated_at = Some(create_pool.timestamp);
+        Some(token.clone())
+    }
+
+    fn evict_idle(&mut self, slot: u64) {
+        self.last_evicted_slot = slot;
+        let max_idle_slots = self.max_idle_slots;
+        self.tokens
+            .retain(|_, token| slot.saturating_sub(token.last_trade_slot) <= max_idle_slots);
+    }
+
+    pub fn get(&self, mint: &Pubkey) -> Option<&TokenState> {
+        self.tokens.get(mint)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    pub fn tokens(&self) -> impl Iterator<Item = &TokenState> {
+        self.tokens.values()
+    }
+
+    pub fn by_stage(&self, stage: Stage) -> impl Iterator<Item = &TokenState> {
+        self.tokens
+            .values()
+            .filter(move |token| token.stage == stage)
+    }
+
+    pub fn by_creator<'a>(&'a self, creator: &'a Pubkey) -> impl Iterator<Item = &'a TokenState> {
+        self.tokens
+            .values()
+            .filter(move |token| token.creator == *creator)
+    }
+
+    /// The `n` curves closest to completing, closest first.
+    pub fn closest_to_complete(&self, n: usize) -> Vec<&TokenState> {
+        let mut tokens = self.by_stage(Stage::BondingCurve).collect::<Vec<_>>();
+        tokens.sort_by(|a, b| b.progress().total_cmp(&a.progress()));
+        tokens.truncate(n);
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::pump_fun::{BuyEvent, SellEvent};
+
+    const SUPPLY: u64 = 1_000_000_000_000_000;
+
+    fn create(mint: Pubkey, creator: Pubkey) -> Action {
+        Action::PumpFunCreate(CreateEvent {
+            name: "Test".into(),
+            symbol: "TST".into(),
+            mint,
+            creator,
+            user: creator,
+            timestamp: 1_700_000_000,
+            virtual_token_reserves: 1_073_000_000_000_000,
+            virtual_sol_reserves: 30_000_000_000,
+            real_token_reserves: 793_100_000_000_000,
+            token_total_supply: SUPPLY,
+            ..Default::default()
+        })
+    }
+
+    /// Buys `tokens` off the curve, the reserves following the constant product.
+    fn buy(mint: Pubkey, user: Pubkey, sol: u64, tokens: u64, sold_before: u64) -> Action {
+        Action::PumpFunTrade(TradeEvent {
+            mint,
+            user,
+            is_buy: true,
+            sol_amount: sol,
+            token_amount: tokens,
+            timestamp: 1_700_000_010,
+            virtual_sol_reserves: 30_000_000_000 + sol,
+            virtual_token_reserves: 1_073_000_000_000_000 - sold_before - tokens,
+            real_sol_reserves: sol,
+            real_token_reserves: 793_100_000_000_000 - sold_before - tokens,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn follows_create_trade_complete_and_migration() {
+        let (mint, creator, whale) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let pool = Pubkey::new_unique();
+        let mut tracker = LifecycleTracker::new(DEFAULT_MAX_IDLE_SLOTS);
+
+        let transitions = tracker.observe(&DecodedTransaction::with_actions(
+            100,
+            vec![create(mint, creator)],
+        ));
+        assert_eq!(transitions.len(), 1);
+        assert_eq!(transitions[0].stage, Stage::BondingCurve);
+        assert_eq!(transitions[0].slot, 100);
+        let token = tracker.get(&mint).unwrap();
+        assert_eq!(token.progress(), 0.0);
+        assert_eq!(token.market_cap(), 27_958_993_476);
+
+        // a trade moves the curve but isn't a transition
+        let half = 396_550_000_000_000;
+        assert!(
+            tracker
+                .observe(&DecodedTransaction::with_actions(
+                    101,
+                    vec![buy(mint, whale, 40_000_000_000, half, 0)]
+                ))
+                .is_empty()
+        );
+        let token = tracker.get(&mint).unwrap();
+        assert_eq!(token.progress(), 50.0);
+        assert_eq!(token.holders(), 1);
+        assert_eq!(token.volume, 40_000_000_000);
+        assert_eq!(tracker.closest_to_complete(1)[0].mint, mint);
+
+        let transitions = tracker.observe(&DecodedTransaction::with_actions(
+            150,
+            vec![
+                buy(mint, creator, 45_000_000_000, half, half),
+                Action::PumpFunComplete(CompleteEvent {
+                    mint,
+                    timestamp: 1_700_000_600,
+                    ..Default::default()
+                }),
+            ],
+        ));
+        assert_eq!(transitions.len(), 1);
+        assert_eq!(transitions[0].stage, Stage::Complete);
+        let token = &transitions[0].token;
+        assert_eq!(token.progress(), 100.0);
+        assert_eq!(token.buys, 2);
+        assert_eq!(token.time_to_complete(), Some(600));
+        assert_eq!(tracker.by_stage(Stage::Complete).count(), 1);
+
+        let transitions = tracker.observe(&DecodedTransaction::with_actions(
+            152,
+            vec![Action::PumpAmmCreatePool(CreatePoolEvent {
+                base_mint: mint,
+                pool,
+                timestamp: 1_700_000_620,
+                pool_base_amount: 206_900_000_000_000,
+                pool_quote_amount: 84_990_359_679,
+                ..Default::default()
+            })],
+        ));
+        assert_eq!(transitions.len(), 1);
+        assert_eq!(transitions[0].stage, Stage::Migrated);
+        assert_eq!(transitions[0].token.pool, Some(pool));
+        assert_eq!(transitions[0].token.migrated_slot, Some(152));
+        assert_eq!(transitions[0].token.market_cap(), 410_779_892_116);
+    }
+
+    #[test]
+    fn applies_pool_swaps_after_migration() {
+        let (mint, creator, trader) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let pool = Pubkey::new_unique();
+        let mut tracker = LifecycleTracker::new(DEFAULT_MAX_IDLE_SLOTS);
+        tracker.observe(&DecodedTransaction::with_actions(
+            100,
+            vec![create(mint, creator)],
+        ));
+        tracker.observe(&DecodedTransaction::with_actions(
+            200,
+            vec![Action::PumpAmmCreatePool(CreatePoolEvent {
+                base_mint: mint,
+                pool,
+                pool_base_amount: 206_900_000_000_000,
+                pool_quote_amount: 84_990_359_679,
+                ..Default::default()
+            })],
+        ));
+        let amm_buy = |pool| Action::PumpAmmBuy {
+            event: BuyEvent {
+                pool,
+                user: trader,
+                base_amount_out: 1_000_000_000_000,
+                pool_base_token_reserves: 206_900_000_000_000,
+                pool_quote_token_reserves: 84_990_359_679,
+                quote_amount_in: 412_769_000,
+                quote_amount_in_with_lp_fee: 413_594_000,
+                lp_fee: 825_000,
+                protocol_fee: 206_000,
+                user_quote_amount_in: 414_625_000,
+                ..Default::default()
+            },
+            base_mint: mint,
+            quote_mint: crate::types::constant::WSOL_MINT,
+        };
+        // another pool of the same mint isn't the migrated one
+        tracker.observe(&DecodedTransaction::with_actions(
+            201,
+            vec![amm_buy(Pubkey::new_unique())],
+        ));
+        assert_eq!(tracker.get(&mint).unwrap().buys, 0);
+
+        tracker.observe(&DecodedTransaction::with_actions(202, vec![amm_buy(pool)]));
+        tracker.observe(&DecodedTransaction::with_actions(
+            203,
+            vec![Action::PumpAmmSell {
+                event: SellEvent {
+                    pool,
+                    user: trader,
+                    base_amount_in: 1_000_000_000_000,
+                    pool_base_token_reserves: 205_900_000_000_000,
+                    pool_quote_token_reserves: 85_403_953_679,
+                    quote_amount_out: 412_760_000,
+                    lp_fee: 825_000,
+                    protocol_fee: 206_000,
+                    user_quote_amount_out: 411_729_000,
+                    ..Default::default()
+                },
+                base_mint: mint,
+                quote_mint: crate::types::constant::WSOL_MINT,
+            }],
+        ));
+        let token = tracker.get(&mint).unwrap();
+        assert_eq!((token.buys, token.sells), (1, 1));
+        assert_eq!(token.holders(), 0);
+        assert_eq!(token.last_trade_slot, 203);
+        // fees excluded on both sides
+        assert_eq!(token.volume, 413_594_000 + 412_760_000);
+        assert_eq!(token.virtual_token_reserves, 206_900_000_000_000);
+    }
+
+    #[test]
+    fn forgets_idle_tokens() {
+        let (idle, active) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut tracker = LifecycleTracker::new(5_000);
+        tracker.observe(&DecodedTransaction::with_actions(
+            1_000,
+            vec![
+                create(idle, Pubkey::new_unique()),
+                create(active, Pubkey::new_unique()),
+            ],
+        ));
+        tracker.observe(&DecodedTransaction::with_actions(
+            4_000,
+            vec![buy(
+                active,
+                Pubkey::new_unique(),
+                1_000_000_000,
+                1_000_000_000,
+                0,
+            )],
+        ));
+        // idle for 5000 slots exactly is kept
+        tracker.observe(&DecodedTransaction::with_actions(6_000, vec![]));
+        assert_eq!(tracker.len(), 2);
+        tracker.observe(&DecodedTransaction::with_actions(7_001, vec![]));
+        assert!(tracker.get(&idle).is_none());
+        assert!(tracker.get(&active).is_some());
+        tracker.observe(&DecodedTransaction::with_actions(9_001, vec![]));
+        assert!(tracker.is_empty());
+    }
+}
@@ -0,0 +1,658 @@
+use anyhow::Context;
+use hyper::client::connect::dns::Name;
+use log::{info, warn};
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::{Url, redirect};
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::error::Error;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::sync::mpsc::{self, Receiver, Sender, UnboundedSender, error::TrySendError};
+use tokio::task::JoinSet;
+
+pub const DEFAULT_IPFS_GATEWAY: &str = "https://ipfs.io";
+pub const DEFAULT_METADATA_WORKERS: usize = 8;
+pub const DEFAULT_METADATA_TIMEOUT: Duration = Duration::from_secs(5);
+/// pump.fun metadata is well under 1KB.
+pub const DEFAULT_METADATA_MAX_BYTES: usize = 64 * 1024;
+pub const DEFAULT_METADATA_CACHE_SIZE: usize = 10_000;
+const QUEUE_CAPACITY: usize = 1_000;
+const MAX_REDIRECTS: usize = 5;
+
+#[derive(Clone, Debug)]
+pub struct ResolverConfig {
+    /// `ipfs://` URIs are fetched from here. When set explicitly, URLs on
+    /// other gateways (`https://<host>/ipfs/<cid>`) are rewritten to it too.
+    pub gateway: Option<String>,
+    pub workers: usize,
+    /// Per fetch, redirects and body included.
+    pub timeout: Duration,
+    pub max_bytes: usize,
+    pub cache_size: usize,
+    /// Allow fetching from loopback, private and link-local addresses. URIs
+    /// come from whoever creates a token, so this is off unless testing
+    /// against a local server.
+    pub allow_private: bool,
+}
+
+impl Default for ResolverConfig {
+    fn default() -> Self {
+        Self {
+            gateway: None,
+            workers: DEFAULT_METADATA_WORKERS,
+            timeout: DEFAULT_METADATA_TIMEOUT,
+            max_bytes: DEFAULT_METADATA_MAX_BYTES,
+            cache_size: DEFAULT_METADATA_CACHE_SIZE,
+            allow_private: false,
+        }
+    }
+}
+
+impl ResolverConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mut config = Self::default();
+        if let Ok(gateway) = env::var("IPFS_GATEWAY") {
+            Url::parse(&gateway).context("invalid IPFS_GATEWAY")?;
+            config.gateway = Some(gateway);
+        }
+        if let Ok(workers) = env::var("METADATA_WORKERS") {
+            config.workers = workers.parse().context("invalid METADATA_WORKERS")?;
+        }
+        if let Ok(secs) = env::var("METADATA_TIMEOUT_SECS") {
+            config.timeout =
+                Duration::from_secs_f64(secs.parse().context("invalid METADATA_TIMEOUT_SECS")?);
+        }
+        if let Ok(kb) = env::var("METADATA_MAX_KB") {
+            config.max_bytes = kb.parse::<usize>().context("invalid METADATA_MAX_KB")? * 1024;
+        }
+        config.allow_private =
+            env::var("METADATA_ALLOW_PRIVATE").is_ok_and(|allow| allow == "true");
+        Ok(config)
+    }
+
+    /// The URL to fetch `uri` from, `None` for schemes other than http(s)/ipfs/ar.
+    pub fn url(&self, uri: &str) -> Option<String> {
+        let uri = uri.trim();
+        let gateway = self
+            .gateway
+            .as_deref()
+            .unwrap_or(DEFAULT_IPFS_GATEWAY)
+            .trim_end_matches('/');
+        if let Some(path) = uri.strip_prefix("ipfs://") {
+            let path = path.strip_prefix("ipfs/").unwrap_or(path);
+            return Some(format!("{gateway}/ipfs/{path}"));
+        }
+        if let Some(path) = uri.strip_prefix("ar://") {
+            return Some(format!("https://arweave.net/{path}"));
+        }
+        let url = Url::parse(uri).ok()?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return None;
+        }
+        // other gateways are only replaced when IPFS_GATEWAY is set
+        if self.gateway.is_some()
+            && let Some(index) = url.path().find("/ipfs/")
+            && !uri.starts_with(gateway)
+        {
+            let mut rewritten = format!("{gateway}{}", &url.path()[index..]);
+            if let Some(query) = url.query() {
+                rewritten.push('?');
+                rewritten.push_str(query);
+            }
+            return Some(rewritten);
+        }
+        Some(url.into())
+    }
+}
+
+/// The off-chain JSON a `CreateEvent` URI points at.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OffchainMetadata {
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub description: Option<String>,
+    /// Through the gateway when it is an `ipfs://` URI.
+    pub image: Option<String>,
+    pub twitter: Option<String>,
+    pub telegram: Option<String>,
+    pub website: Option<String>,
+}
+
+impl OffchainMetadata {
+    /// Reads the top level fields, or Metaplex style `extensions`.
+    pub fn from_json(value: &Value, config: &ResolverConfig) -> Self {
+        let field = |key: &str| {
+            [Some(value), value.get("extensions")]
+                .into_iter()
+                .flatten()
+                .filter_map(|object| object.get(key)?.as_str())
+                .map(str::trim)
+                .find(|text| !text.is_empty())
+                .map(str::to_string)
+        };
+        Self {
+            name: field("name"),
+            symbol: field("symbol"),
+            description: field("description"),
+            image: field("image").map(|image| {
+                if image.starts_with("ipfs://") || image.starts_with("ar://") {
+                    config.url(&image).unwrap_or(image)
+                } else {
+                    image
+                }
+            }),
+            twitter: field("twitter"),
+            telegram: field("telegram"),
+            website: field("website"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ResolvedMetadata {
+    pub mint: Pubkey,
+    pub uri: String,
+    /// What was fetched, after gateway rewriting.
+    pub url: Option<String>,
+    pub slot: u64,
+    pub metadata: Result<Arc<OffchainMetadata>, String>,
+    pub cached: bool,
+    pub elapsed: Duration,
+}
+
+/// Successful fetches by URL, oldest evicted first.
+struct Cache {
+    capacity: usize,
+    entries: HashMap<String, Arc<OffchainMetadata>>,
+    order: VecDeque<String>,
+}
+
+impl Cache {
+    fn get(&self, url: &str) -> Option<Arc<OffchainMetadata>> {
+        self.entries.get(url).cloned()
+    }
+
+    fn insert(&mut self, url: String, metadata: Arc<OffchainMetadata>) {
+        if self.capacity == 0 || self.entries.contains_key(&url) {
+            return;
+        }
+        while self.entries.len() >= self.capacity {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+        self.order.push_back(url.clone());
+        self.entries.insert(url, metadata);
+    }
+}
+
+struct Job {
+    mint: Pubkey,
+    uri: String,
+    url: String,
+    slot: u64,
+}
+
+/// Whether `ip` is on the public internet: not loopback, private, link-local,
+/// shared (100.64/10), unspecified, broadcast, documentation or multicast.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                || (a == 100 && b & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(ip.into()),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local()
+                    || ip.is_multicast())
+            }
+        },
+    }
+}
+
+/// Rejects URLs to non-public IP literals. Host names don't resolve here but in
+/// [`PublicResolver`] when connecting.
+fn check_host(url: &Url) -> Result<(), String> {
+    let host = url.host_str().ok_or("no host")?;
+    // IPv6 in brackets
+    match host.trim_start_matches('[').trim_end_matches(']').parse() {
+        Ok(ip) if !is_public(ip) => Err(format!("non-public address {ip}")),
+        _ => Ok(()),
+    }
+}
+
+/// The system resolver, failing for names with any non-public address so a
+/// connection (redirects included) can't be pointed inside the network.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .collect::<Vec<_>>();
+            // reject if any address is not public, the connect could pick a private one
+            if let Some(addr) = addrs.iter().find(|addr| !is_public(addr.ip())) {
+                return Err(format!(
+                    "{} resolves to non-public address {}",
+                    name.as_str(),
+                    addr.ip()
+                )
+                .into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn client(config: &ResolverConfig) -> reqwest::Result<reqwest::Client> {
+    let builder = reqwest::Client::builder().timeout(config.timeout);
+    if config.allow_private {
+        return builder.build();
+    }
+    builder
+        .dns_resolver(Arc::new(PublicResolver))
+        // a proxy would resolve the names itself
+        .no_proxy()
+        .redirect(redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                return attempt.error("too many redirects");
+            }
+            match check_host(attempt.url()) {
+                Ok(()) => attempt.follow(),
+                Err(error) => attempt.error(error),
+            }
+        }))
+        .build()
+}
+
+/// The error with its causes, without the URL.
+fn describe(error: reqwest::Error) -> String {
+    let error = error.without_url();
+    let mut text = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        text.push_str(&format!(": {cause}"));
+        source = cause.source();
+    }
+    text
+}
+
+async fn fetch(
+    client: &reqwest::Client,
+    url: &str,
+    config: &ResolverConfig,
+) -> Result<OffchainMetadata, String> {
+    if !config.allow_private {
+        check_host(&Url::parse(url).map_err(|error| error.to_string())?)?;
+    }
+    let mut response = client
+        .get(url)
+        .header(reqwest::header::ACCEPT, "application/json")
+        .send()
+        .await
+        .map_err(describe)?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("HTTP {status}"));
+    }
+    let too_large = || format!("body over {} bytes", config.max_bytes);
+    if response
+        .content_length()
+        .is_some_and(|length| length > config.max_bytes as u64)
+    {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(describe)? {
+        if body.len() + chunk.len() > config.max_bytes {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let value =
+        serde_json::from_slice::<Value>(&body).map_err(|error| format!("invalid JSON: {error}"))?;
+    if !value.is_object() {
+        return Err("not a JSON object".into());
+    }
+    Ok(OffchainMetadata::from_json(&value, config))
+}
+
+struct Worker {
+    client: reqwest::Client,
+    config: ResolverConfig,
+    cache: Arc<Mutex<Cache>>,
+    results: UnboundedSender<ResolvedMetadata>,
+}
+
+impl Worker {
+    async fn resolve(&self, job: Job) {
+        let started = Instant::now();
+        // another mint with the same URI may have fetched it while this one was queued
+        let cached = self.cache.lock().unwrap().get(&job.url);
+        let (metadata, hit) = match cached {
+            Some(metadata) => (Ok(metadata), true),
+            None => match fetch(&self.client, &job.url, &self.config).await {
+                Ok(metadata) => {
+                    let metadata = Arc::new(metadata);
+                    self.cache
+                        .lock()
+                        .unwrap()
+                        .insert(job.url.clone(), metadata.clone());
+                    (Ok(metadata), false)
+                }
+                Err(error) => (Err(error), false),
+            },
+        };
+        let _ = self.results.send(ResolvedMetadata {
+            mint: job.mint,
+            uri: job.uri,
+            url: Some(job.url),
+            slot: job.slot,
+            metadata,
+            cached: hit,
+            elapsed: started.elapsed(),
+        });
+    }
+
+    async fn run(self, mut rx: Receiver<Job>) {
+        let worker = Arc::new(self);
+        let permits = Arc::new(Semaphore::new(worker.config.workers.max(1)));
+        let mut tasks = JoinSet::new();
+        while let Some(job) = rx.recv().await {
+            let Ok(permit) = permits.clone().acquire_owned().await else {
+                break;
+            };
+            let worker = worker.clone();
+            tasks.spawn(async move {
+                worker.resolve(job).await;
+                drop(permit);
+            });
+            while tasks.try_join_next().is_some() {}
+        }
+        if !tasks.is_empty() {
+            info!("waiting for {} pending metadata fetches", tasks.len());
+        }
+        while tasks.join_next().await.is_some() {}
+    }
+}
+
+/// Fetches `CreateEvent` URIs on spawned tasks, at most `workers` at a time,
+/// and sends the results to a channel so the stream loop never waits on HTTP.
+pub struct MetadataResolver {
+    config: ResolverConfig,
+    tx: Sender<Job>,
+    cache: Arc<Mutex<Cache>>,
+    results: UnboundedSender<ResolvedMetadata>,
+    dropped: u64,
+}
+
+impl MetadataResolver {
+    /// Must be called within a tokio runtime.
+    pub fn new(
+        config: ResolverConfig,
+        results: UnboundedSender<ResolvedMetadata>,
+    ) -> anyhow::Result<Self> {
+        let cache = Arc::new(Mutex::new(Cache {
+            capacity: config.cache_size,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }));
+        let worker = Worker {
+            client: client(&config)?,
+            config: config.clone(),
+            cache: cache.clone(),
+            results: results.clone(),
+        };
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(worker.run(rx));
+        Ok(Self {
+            config,
+            tx,
+            cache,
+            results,
+            dropped: 0,
+        })
+    }
+
+    /// Queues `uri` without waiting. Cached URLs and unsupported URIs are
+    /// answered right away, empty URIs ignored.
+    pub fn resolve(&mut self, mint: Pubkey, uri: &str, slot: u64) {
+        if uri.trim().is_empty() {
+            return;
+        }
+        let resolved = |url, metadata| ResolvedMetadata {
+            mint,
+            uri: uri.to_string(),
+            url,
+            slot,
+            metadata,
+            cached: true,
+            elapsed: Duration::ZERO,
+        };
+        let Some(url) = self.config.url(uri) else {
+            let _ = self.results.send(ResolvedMetadata {
+                cached: false,
+                ..resolved(None, Err("unsupported URI".into()))
+            });
+            return;
+        };
+        if let Some(metadata) = self.cache.lock().unwrap().get(&url) {
+            let _ = self.results.send(resolved(Some(url), Ok(metadata)));
+            return;
+        }
+        let job = Job {
+            mint,
+            uri: uri.to_string(),
+            url,
+            slot,
+        };
+        if let Err(TrySendError::Full(job)) = self.tx.try_send(job) {
+            self.dropped += 1;
+            if self.dropped.is_power_of_two() {
+                warn!(
+                    "metadata queue full, {} URIs dropped, latest {}",
+                    self.dropped, job.uri
+                );
+            }
+        }
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::extract::{Path, State};
+    use axum::http::{StatusCode, header};
+    use axum::response::{IntoResponse, Redirect};
+    use axum::routing::get;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::mpsc::UnboundedReceiver;
+
+    #[test]
+    fn only_public_hosts_pass() {
+        for url in [
+            "http://127.0.0.1/x",
+            "http://127.1.2.3/x",
+            "http://2130706433/x",
+            "http://0x7f.1/x",
+            "http://0.0.0.0/x",
+            "http://10.1.2.3/x",
+            "http://172.16.0.1/x",
+            "http://192.168.1.1/x",
+            "http://169.254.169.254/latest/meta-data",
+            "http://100.64.0.1/x",
+            "http://[::1]/x",
+            "http://[::]/x",
+            "http://[::ffff:127.0.0.1]/x",
+            "http://[fd00::1]/x",
+            "http://[fe80::1]/x",
+        ] {
+            assert!(check_host(&Url::parse(url).unwrap()).is_err(), "{url}");
+        }
+        for url in [
+            "https://ipfs.io/ipfs/cid",
+            "http://1.1.1.1/x",
+            "http://[2606:4700:4700::1111]/x",
+            // host names are checked by PublicResolver when connecting
+            "http://localhost/x",
+        ] {
+            assert!(check_host(&Url::parse(url).unwrap()).is_ok(), "{url}");
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn public_resolver_refuses_local_names() {
+        let error = PublicResolver
+            .resolve("localhost".parse().unwrap())
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("non-public"), "{error}");
+    }
+
+    async fn serve() -> (String, Arc<AtomicUsize>) {
+        async fn metadata(
+            State(hits): State<Arc<AtomicUsize>>,
+            Path(cid): Path<String>,
+        ) -> axum::response::Response {
+            hits.fetch_add(1, Ordering::SeqCst);
+            match cid.as_str() {
+                "big" => "x".repeat(4096).into_response(),
+                "text" => "not json".into_response(),
+                "missing" => StatusCode::NOT_FOUND.into_response(),
+                _ => (
+                    [(header::CONTENT_TYPE, "application/json")],
+                    json!({
+                        "name": "Cat",
+                        "symbol": "CAT",
+                        "image": format!("ipfs://{cid}-image"),
+                        "twitter": " https://x.com/cat ",
+                        "extensions": { "website": "https://cat.example" },
+                    })
+                    .to_string(),
+                )
+                    .into_response(),
+            }
+        }
+        let hits = Arc::new(AtomicUsize::new(0));
+        let app = Router::new()
+            .route("/ipfs/{cid}", get(metadata))
+            .route(
+                "/moved",
+                get(|| async { Redirect::temporary("/ipfs/moved") }),
+            )
+            .with_state(hits.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, hits)
+    }
+
+    async fn next(rx: &mut UnboundedReceiver<ResolvedMetadata>) -> ResolvedMetadata {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn refuses_private_addresses_by_default() {
+        let (url, hits) = serve().await;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let config = ResolverConfig {
+            gateway: Some(url.clone()),
+            ..Default::default()
+        };
+        let mut resolver = MetadataResolver::new(config, tx).unwrap();
+        let mint = Pubkey::new_unique();
+        resolver.resolve(mint, "ipfs://cid", 1);
+        let error = next(&mut rx).await.metadata.unwrap_err();
+        assert!(error.contains("non-public address 127.0.0.1"), "{error}");
+        let local = url.replace("127.0.0.1", "localhost");
+        resolver.resolve(mint, &format!("{local}/ipfs/cid"), 1);
+        let error = next(&mut rx).await.metadata.unwrap_err();
+        assert!(error.contains("non-public"), "{error}");
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn fetches_from_a_local_server_when_allowed() {
+        let (url, hits) = serve().await;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let config = ResolverConfig {
+            gateway: Some(url.clone()),
+            max_bytes: 1024,
+            allow_private: true,
+            ..Default::default()
+        };
+        let mut resolver = MetadataResolver::new(config, tx).unwrap();
+        let mint = Pubkey::new_unique();
+
+        // /ipfs/ URLs of other gateways are rewritten to the local one
+        resolver.resolve(mint, "https://gateway.example/ipfs/cat", 7);
+        let resolved = next(&mut rx).await;
+        assert_eq!(resolved.url, Some(format!("{url}/ipfs/cat")));
+        assert_eq!(resolved.slot, 7);
+        assert!(!resolved.cached);
+        let metadata = resolved.metadata.unwrap();
+        assert_eq!(
+            *metadata,
+            OffchainMetadata {
+                name: Some("Cat".into()),
+                symbol: Some("CAT".into()),
+                description: None,
+                image: Some(format!("{url}/ipfs/cat-image")),
+                twitter: Some("https://x.com/cat".into()),
+                telegram: None,
+                website: Some("https://cat.example".into()),
+            }
+        );
+
+        resolver.resolve(Pubkey::new_unique(), "ipfs://cat", 8);
+        let resolved = next(&mut rx).await;
+        assert!(resolved.cached);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        resolver.resolve(mint, &format!("{url}/moved"), 9);
+        assert_eq!(
+            next(&mut rx).await.metadata.unwrap().image,
+            Some(format!("{url}/ipfs/moved-image"))
+        );
+        for (cid, expected) in [
+            ("big", "body over 1024 bytes"),
+            ("text", "invalid JSON"),
+            ("missing", "HTTP 404"),
+        ] {
+            resolver.resolve(mint, &format!("ipfs://{cid}"), 10);
+            let error = next(&mut rx).await.metadata.unwrap_err();
+            assert!(error.contains(expected), "{cid}: {error}");
+        }
+        resolver.resolve(mint, "file:///etc/passwd", 11);
+        assert_eq!(next(&mut rx).await.metadata.unwrap_err(), "unsupported URI");
+    }
+}
@@ -0,0 +1,10 @@
+pub mod candles;
+pub mod compute_budget;
+pub mod creators;
+pub mod holders;
+pub mod jito;
+pub mod lifecycle;
+pub mod metadata;
+pub mod positions;
+pub mod risk;
+pub mod snipers;
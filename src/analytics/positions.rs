@@ -0,0 +1,654 @@
+use log::{error, info};
+use rusqlite::{Connection, params};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use crate::db::{self, pubkey};
+use crate::decoder::DecodedTransaction;
+use crate::sinks::recorder::{PUMP_TOKEN_DECIMALS, SOL_DECIMALS};
+use crate::types::constant::WSOL_MINT;
+use crate::types::swap::{SwapEvent, SwapSide, Venue};
+
+pub const DEFAULT_POSITIONS_DB: &str = "./positions.db";
+
+/// Schema changes, see [`db::open`].
+const MIGRATIONS: &[&str] = &[
+    // 1: initial schema
+    "CREATE TABLE positions (
+        wallet TEXT NOT NULL,
+        mint TEXT NOT NULL,
+        tokens INTEGER NOT NULL,
+        cost INTEGER NOT NULL,
+        realized INTEGER NOT NULL,
+        fees INTEGER NOT NULL,
+        buys INTEGER NOT NULL,
+        sells INTEGER NOT NULL,
+        bought_sol INTEGER NOT NULL,
+        sold_sol INTEGER NOT NULL,
+        last_slot INTEGER NOT NULL,
+        PRIMARY KEY (wallet, mint)
+    );
+
+    CREATE TABLE marks (
+        mint TEXT PRIMARY KEY,
+        base_reserves INTEGER NOT NULL,
+        quote_reserves INTEGER NOT NULL,
+        venue TEXT NOT NULL,
+        slot INTEGER NOT NULL
+    );",
+];
+
+/// Comma separated wallets, e.g. the `WATCH_WALLETS` env.
+pub fn parse_watchlist(spec: &str) -> anyhow::Result<HashSet<Pubkey>> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|wallet| !wallet.is_empty())
+        .map(|wallet| {
+            wallet
+                .parse()
+                .map_err(|_| anyhow::anyhow!("invalid wallet {wallet}"))
+        })
+        .collect()
+}
+
+/// Latest pool reserves of a mint, the mark price.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mark {
+    pub base_reserves: u64,
+    pub quote_reserves: u64,
+    pub venue: Venue,
+    pub slot: u64,
+}
+
+impl Mark {
+    /// Lamports the tokens are worth at the pool price, ignoring slippage.
+    pub fn value(&self, tokens: u64) -> u64 {
+        if self.base_reserves == 0 {
+            return 0;
+        }
+        (tokens as u128 * self.quote_reserves as u128 / self.base_reserves as u128) as u64
+    }
+
+    /// SOL per token.
+    pub fn price(&self) -> f64 {
+        if self.base_reserves == 0 {
+            return 0.0;
+        }
+        let sol = self.quote_reserves as f64 / 10f64.powi(SOL_DECIMALS as i32);
+        let tokens = self.base_reserves as f64 / 10f64.powi(PUMP_TOKEN_DECIMALS as i32);
+        sol / tokens
+    }
+}
+
+/// A wallet's holding of one mint. SOL amounts are lamports, fees included.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Position {
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
+    /// Raw token amount held.
+    pub tokens: u64,
+    /// Cost basis of the tokens held.
+    pub cost: u64,
+    pub realized: i64,
+    pub fees: u64,
+    pub buys: u64,
+    pub sells: u64,
+    /// SOL paid buying and received selling.
+    pub bought_sol: u64,
+    pub sold_sol: u64,
+    pub last_slot: u64,
+}
+
+impl Position {
+    /// SOL per token paid for the tokens held.
+    pub fn avg_cost(&self) -> f64 {
+        if self.tokens == 0 {
+            return 0.0;
+        }
+        let sol = self.cost as f64 / 10f64.powi(SOL_DECIMALS as i32);
+        let tokens = self.tokens as f64 / 10f64.powi(PUMP_TOKEN_DECIMALS as i32);
+        sol / tokens
+    }
+
+    pub fn unrealized(&self, mark: Option<&Mark>) -> i64 {
+        match mark {
+            Some(mark) => mark.value(self.tokens) as i64 - self.cost as i64,
+            None => 0,
+        }
+    }
+
+    /// Sells more tokens than tracked (bought before the wallet was watched)
+    /// realize the extra at zero cost.
+    fn apply(&mut self, side: SwapSide, tokens: u64, sol: u64, fees: u64, slot: u64) {
+        self.fees += fees;
+        self.last_slot = self.last_slot.max(slot);
+        match side {
+            SwapSide::Buy => {
+                self.buys += 1;
+                self.tokens += tokens;
+                self.cost += sol;
+                self.bought_sol += sol;
+            }
+            SwapSide::Sell => {
+                self.sells += 1;
+                self.sold_sol += sol;
+                let sold = tokens.min(self.tokens);
+                let cost = if self.tokens == 0 {
+                    0
+                } else {
+                    (self.cost as u128 * sold as u128 / self.tokens as u128) as u64
+                };
+                self.tokens -= sold;
+                self.cost -= cost;
+                self.realized += sol as i64 - cost as i64;
+            }
+        }
+    }
+}
+
+/// SOL the trader paid (buy) or received (sell), fees included. Pump.fun
+/// charges its fees on top of `sol_amount`; pump AMM user amounts already include them.
+fn trader_sol(swap: &SwapEvent) -> u64 {
+    let fees = swap.fees.total();
+    match (swap.venue, swap.side) {
+        (Venue::PumpFun, SwapSide::Buy) => swap.quote_amount() + fees,
+        (Venue::PumpFun, SwapSide::Sell) => swap.quote_amount().saturating_sub(fees),
+        _ => swap.quote_amount(),
+    }
+}
+
+/// A trade of a watched wallet and its position after it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PositionUpdate {
+    pub slot: u64,
+    pub signature: Signature,
+    pub venue: Venue,
+    pub side: SwapSide,
+    pub token_amount: u64,
+    /// Lamports paid or received, fees included.
+    pub sol_amount: u64,
+    pub fees: u64,
+    pub position: Position,
+    pub mark: Option<Mark>,
+}
+
+/// Positions and PnL of a watchlist of wallets over their pump.fun and pump AMM
+/// trades, persisted to SQLite so they survive restarts and can be queried
+/// from the command line.
+pub struct PositionTracker {
+    conn: Connection,
+    watchlist: HashSet<Pubkey>,
+    positions: HashMap<(Pubkey, Pubkey), Position>,
+    marks: HashMap<Pubkey, Mark>,
+    dirty: HashSet<(Pubkey, Pubkey)>,
+    dirty_marks: HashSet<Pubkey>,
+}
+
+impl PositionTracker {
+    pub fn open(path: impl AsRef<Path>, watchlist: HashSet<Pubkey>) -> anyhow::Result<Self> {
+        let conn = db::open(path, "positions db", MIGRATIONS)?;
+        let positions = load_positions(&conn, None)?
+            .into_iter()
+            .map(|position| ((position.wallet, position.mint), position))
+            .collect::<HashMap<_, _>>();
+        let marks = load_marks(&conn)?;
+        if !positions.is_empty() {
+            info!("loaded {} positions from the positions db", positions.len());
+        }
+        Ok(Self {
+            conn,
+            watchlist,
+            positions,
+            marks,
+            dirty: HashSet::new(),
+            dirty_marks: HashSet::new(),
+        })
+    }
+
+    pub fn watchlist(&self) -> &HashSet<Pubkey> {
+        &self.watchlist
+    }
+
+    /// Updates marks and the watched wallets' positions, returning their trades.
+    pub fn observe(&mut self, decoded: &DecodedTransaction) -> Vec<PositionUpdate> {
+        let mut updates = Vec::new();
+        for swap in decoded.swaps() {
+            if !matches!(swap.venue, Venue::PumpFun | Venue::PumpAmm)
+                || swap.quote_mint() != WSOL_MINT
+            {
+                continue;
+            }
+            let mint = swap.base_mint();
+            let watched = self.watchlist.contains(&swap.trader);
+            if watched || self.marks.contains_key(&mint) {
+                self.marks.insert(
+                    mint,
+                    Mark {
+                        base_reserves: swap.reserves.base,
+                        quote_reserves: swap.reserves.quote,
+                        venue: swap.venue,
+                        slot: swap.slot,
+                    },
+                );
+                self.dirty_marks.insert(mint);
+            }
+            if !watched {
+                continue;
+            }
+            let key = (swap.trader, mint);
+            let position = self.positions.entry(key).or_insert_with(|| Position {
+                wallet: swap.trader,
+                mint,
+                ..Default::default()
+            });
+            let sol = trader_sol(&swap);
+            let fees = swap.fees.total();
+            position.apply(swap.side, swap.base_amount(), sol, fees, swap.slot);
+            self.dirty.insert(key);
+            updates.push(PositionUpdate {
+                slot: swap.slot,
+                signature: swap.signature,
+                venue: swap.venue,
+                side: swap.side,
+                token_amount: swap.base_amount(),
+                sol_amount: sol,
+                fees,
+                position: position.clone(),
+                mark: self.marks.get(&mint).copied(),
+            });
+        }
+        updates
+    }
+
+    pub fn position(&self, wallet: &Pubkey, mint: &Pubkey) -> Option<&Position> {
+        self.positions.get(&(*wallet, *mint))
+    }
+
+    pub fn positions_of(&self, wallet: &Pubkey) -> Vec<&Position> {
+        self.positions
+            .values()
+            .filter(|position| position.wallet == *wallet)
+            .collect()
+    }
+
+    pub fn mark(&self, mint: &Pubkey) -> Option<&Mark> {
+        self.marks.get(mint)
+    }
+
+    /// Writes the positions and marks changed since the last save, in one transaction.
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        if self.dirty.is_empty() && self.dirty_marks.is_empty() {
+            return Ok(());
+        }
+        let tx = self.conn.transaction()?;
+        {
+            let mut upsert_position = tx.prepare_cached(
+                "INSERT OR REPLACE INTO positions (wallet, mint, tokens, cost, realized, fees, buys, sells, bought_sol, sold_sol, last_slot)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?;
+            for position in self.dirty.iter().filter_map(|key| self.positions.get(key)) {
+                upsert_position.execute(params![
+                    position.wallet.to_string(),
+                    position.mint.to_string(),
+                    position.tokens,
+                    position.cost,
+                    position.realized,
+                    position.fees,
+                    position.buys,
+                    position.sells,
+                    position.bought_sol,
+                    position.sold_sol,
+                    position.last_slot,
+                ])?;
+            }
+            let mut upsert_mark = tx.prepare_cached(
+                "INSERT OR REPLACE INTO marks (mint, base_reserves, quote_reserves, venue, slot)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for (mint, mark) in self
+                .dirty_marks
+                .iter()
+                .filter_map(|mint| Some((mint, self.marks.get(mint)?)))
+            {
+                upsert_mark.execute(params![
+                    mint.to_string(),
+                    mark.base_reserves,
+                    mark.quote_reserves,
+                    mark.venue.as_str(),
+                    mark.slot,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        self.dirty.clear();
+        self.dirty_marks.clear();
+        Ok(())
+    }
+}
+
+impl Drop for PositionTracker {
+    fn drop(&mut self) {
+        if let Err(error) = self.save() {
+            error!("failed to save the positions db: {error}");
+        }
+    }
+}
+
+fn load_positions(conn: &Connection, wallet: Option<&Pubkey>) -> anyhow::Result<Vec<Position>> {
+    let mut select = conn.prepare(
+        "SELECT wallet, mint, tokens, cost, realized, fees, buys, sells, bought_sol, sold_sol, last_slot
+         FROM positions WHERE ?1 IS NULL OR wallet = ?1 ORDER BY wallet, last_slot DESC",
+    )?;
+    let positions = select
+        .query_map([wallet.map(ToString::to_string)], |row| {
+            Ok(Position {
+                wallet: pubkey(row, 0)?,
+                mint: pubkey(row, 1)?,
+                tokens: row.get(2)?,
+                cost: row.get(3)?,
+                realized: row.get(4)?,
+                fees: row.get(5)?,
+                buys: row.get(6)?,
+                sells: row.get(7)?,
+                bought_sol: row.get(8)?,
+                sold_sol: row.get(9)?,
+                last_slot: row.get(10)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(positions)
+}
+
+fn load_marks(conn: &Connection) -> anyhow::Result<HashMap<Pubkey, Mark>> {
+    let mut select =
+        conn.prepare("SELECT mint, base_reserves, quote_reserves, venue, slot FROM marks")?;
+    let marks = select
+        .query_map([], |row| {
+            let venue = match row.get::<_, String>(3)?.as_str() {
+                "pump_amm" => Venue::PumpAmm,
+                _ => Venue::PumpFun,
+            };
+            Ok((
+                pubkey(row, 0)?,
+                Mark {
+                    base_reserves: row.get(1)?,
+                    quote_reserves: row.get(2)?,
+                    venue,
+                    slot: row.get(4)?,
+                },
+            ))
+        })?
+        .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+    Ok(marks)
+}
+
+fn sol(lamports: i64) -> String {
+    format!("{:.4}", lamports as f64 / 10f64.powi(SOL_DECIMALS as i32))
+}
+
+/// `positions [wallet]`: prints the stored positions and PnL per wallet.
+pub fn print_positions(path: impl AsRef<Path>, wallet: Option<&str>) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    if !path.exists() {
+        anyhow::bail!("no positions db at {}", path.display());
+    }
+    let wallet = wallet
+        .map(|wallet| {
+            wallet
+                .parse::<Pubkey>()
+                .map_err(|_| anyhow::anyhow!("invalid wallet {wallet}"))
+        })
+        .transpose()?;
+    let conn = db::open(path, "positions db", MIGRATIONS)?;
+    let marks = load_marks(&conn)?;
+
+    let mut wallets: BTreeMap<Pubkey, Vec<Position>> = BTreeMap::new();
+    for position in load_positions(&conn, wallet.as_ref())? {
+        wallets.entry(position.wallet).or_default().push(position);
+    }
+    if wallets.is_empty() {
+        println!("no positions");
+    }
+    for (wallet, positions) in wallets {
+        let (mut realized, mut unrealized, mut fees) = (0, 0, 0);
+        println!("{wallet}");
+        println!(
+            "  {:<44} {:>16} {:>14} {:>14} {:>12} {:>12} {:>10} {:>10}",
+            "mint", "tokens", "avg cost", "mark", "realized", "unrealized", "fees", "mark slot"
+        );
+        for position in positions {
+            let mark = marks.get(&position.mint);
+            let position_unrealized = position.unrealized(mark);
+            realized += position.realized;
+            unrealized += position_unrealized;
+            fees += position.fees as i64;
+            println!(
+                "  {:<44} {:>16.6} {:>14.10} {:>14.10} {:>12} {:>12} {:>10} {:>10}",
+                position.mint.to_string(),
+                position.tokens as f64 / 10f64.powi(PUMP_TOKEN_DECIMALS as i32),
+                position.avg_cost(),
+                mark.map(Mark::price).unwrap_or_default(),
+                sol(position.realized),
+                sol(position_unrealized),
+                sol(position.fees as i64),
+                mark.map(|mark| mark.slot.to_string())
+                    .unwrap_or_else(|| "-".into()),
+            );
+        }
+        println!(
+            "  total realized {} SOL, unrealized {} SOL, fees {} SOL",
+            sol(realized),
+            sol(unrealized),
+            sol(fees)
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::Action;
+    use crate::types::pump_fun::{BuyEvent, SellEvent, TradeEvent};
+    use std::env;
+
+    const SOL: u64 = 1_000_000_000;
+    /// One million tokens, raw.
+    const TOKENS: u64 = 1_000_000_000_000;
+
+    fn tracker(name: &str, wallet: Pubkey) -> (PositionTracker, std::path::PathBuf) {
+        let path = env::temp_dir().join(format!("positions-{name}-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        (
+            PositionTracker::open(&path, HashSet::from([wallet])).unwrap(),
+            path,
+        )
+    }
+
+    fn curve_trade(wallet: Pubkey, mint: Pubkey, is_buy: bool, sol: u64, tokens: u64) -> Action {
+        Action::PumpFunTrade(TradeEvent {
+            mint,
+            sol_amount: sol,
+            token_amount: tokens,
+            is_buy,
+            user: wallet,
+            fee: sol / 100,
+            creator_fee: sol / 200,
+            virtual_token_reserves: 1_000 * TOKENS,
+            virtual_sol_reserves: 30 * SOL,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn averages_cost_and_realizes_a_partial_sell() {
+        let (wallet, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut tracker, path) = tracker("partial", wallet);
+        tracker.observe(&DecodedTransaction::with_actions(
+            1,
+            vec![
+                curve_trade(wallet, mint, true, SOL, TOKENS),
+                curve_trade(wallet, mint, true, 2 * SOL, TOKENS),
+                // not watched, only moves the mark
+                curve_trade(Pubkey::new_unique(), mint, true, SOL, TOKENS),
+            ],
+        ));
+        let position = tracker.position(&wallet, &mint).unwrap();
+        // 1% + 0.5% fees on top of each buy
+        assert_eq!(position.cost, 1_015_000_000 + 2_030_000_000);
+        assert_eq!(position.tokens, 2 * TOKENS);
+        assert!((position.avg_cost() - 0.0000015225).abs() < 1e-15);
+
+        let updates = tracker.observe(&DecodedTransaction::with_actions(
+            2,
+            vec![curve_trade(wallet, mint, false, SOL, TOKENS / 2)],
+        ));
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].sol_amount, 985_000_000);
+        let position = &updates[0].position;
+        assert_eq!(position.tokens, 3 * TOKENS / 2);
+        // a quarter of the tokens carries a quarter of the cost
+        assert_eq!(position.cost, 2_283_750_000);
+        assert_eq!(position.realized, 985_000_000 - 761_250_000);
+        assert_eq!(position.fees, 45_000_000 + 15_000_000);
+        assert_eq!((position.buys, position.sells), (2, 1));
+        assert_eq!(
+            (position.bought_sol, position.sold_sol),
+            (3_045_000_000, 985_000_000)
+        );
+        // 1.5M tokens at 30 SOL per 1000M
+        assert_eq!(
+            position.unrealized(updates[0].mark.as_ref()),
+            45_000_000 - 2_283_750_000
+        );
+        assert!(tracker.position(&wallet, &Pubkey::new_unique()).is_none());
+        assert_eq!(tracker.positions_of(&wallet).len(), 1);
+        drop(tracker);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn oversells_realize_at_zero_cost() {
+        let (wallet, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut tracker, path) = tracker("oversell", wallet);
+        let sell = |sol, tokens| {
+            let mut action = curve_trade(wallet, mint, false, sol, tokens);
+            if let Action::PumpFunTrade(trade) = &mut action {
+                (trade.fee, trade.creator_fee) = (0, 0);
+            }
+            action
+        };
+
+        // bought before the wallet was watched
+        tracker.observe(&DecodedTransaction::with_actions(
+            1,
+            vec![sell(SOL, TOKENS)],
+        ));
+        let position = tracker.position(&wallet, &mint).unwrap();
+        assert_eq!((position.tokens, position.cost), (0, 0));
+        assert_eq!(position.realized, SOL as i64);
+
+        let mut buy = curve_trade(wallet, mint, true, SOL, TOKENS);
+        if let Action::PumpFunTrade(trade) = &mut buy {
+            (trade.fee, trade.creator_fee) = (0, 0);
+        }
+        tracker.observe(&DecodedTransaction::with_actions(2, vec![buy]));
+        tracker.observe(&DecodedTransaction::with_actions(
+            3,
+            vec![sell(3 * SOL, 2 * TOKENS)],
+        ));
+        let position = tracker.position(&wallet, &mint).unwrap();
+        assert_eq!((position.tokens, position.cost), (0, 0));
+        assert_eq!(position.realized, (SOL + 3 * SOL - SOL) as i64);
+        assert_eq!(position.avg_cost(), 0.0);
+        drop(tracker);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn counts_fees_per_venue() {
+        let (wallet, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let pool = Pubkey::new_unique();
+        let (mut tracker, path) = tracker("fees", wallet);
+
+        // pump.fun charges on top of sol_amount
+        let updates = tracker.observe(&DecodedTransaction::with_actions(
+            1,
+            vec![
+                curve_trade(wallet, mint, true, SOL, TOKENS),
+                curve_trade(wallet, mint, false, SOL, TOKENS / 2),
+            ],
+        ));
+        assert_eq!(
+            updates
+                .iter()
+                .map(|update| (update.sol_amount, update.fees))
+                .collect::<Vec<_>>(),
+            [(1_015_000_000, 15_000_000), (985_000_000, 15_000_000)]
+        );
+
+        // pump AMM user amounts already include the fees
+        let updates = tracker.observe(&DecodedTransaction::with_actions(
+            2,
+            vec![
+                Action::PumpAmmBuy {
+                    event: BuyEvent {
+                        pool,
+                        user: wallet,
+                        base_amount_out: TOKENS / 2,
+                        pool_base_token_reserves: 200 * TOKENS,
+                        pool_quote_token_reserves: 80 * SOL,
+                        quote_amount_in_with_lp_fee: 200_400_000,
+                        lp_fee: 400_000,
+                        protocol_fee: 100_000,
+                        coin_creator_fee: 100_000,
+                        user_quote_amount_in: 200_600_000,
+                        ..Default::default()
+                    },
+                    base_mint: mint,
+                    quote_mint: WSOL_MINT,
+                },
+                Action::PumpAmmSell {
+                    event: SellEvent {
+                        pool,
+                        user: wallet,
+                        base_amount_in: TOKENS,
+                        pool_base_token_reserves: 199 * TOKENS,
+                        pool_quote_token_reserves: 80 * SOL,
+                        quote_amount_out_without_lp_fee: 400_000_000,
+                        lp_fee: 800_000,
+                        protocol_fee: 200_000,
+                        coin_creator_fee: 200_000,
+                        user_quote_amount_out: 398_800_000,
+                        ..Default::default()
+                    },
+                    base_mint: mint,
+                    quote_mint: WSOL_MINT,
+                },
+            ],
+        ));
+        assert_eq!(
+            updates
+                .iter()
+                .map(|update| (update.venue, update.sol_amount, update.fees))
+                .collect::<Vec<_>>(),
+            [
+                (Venue::PumpAmm, 200_600_000, 600_000),
+                (Venue::PumpAmm, 398_800_000, 1_200_000),
+            ]
+        );
+        let position = tracker.position(&wallet, &mint).unwrap();
+        assert_eq!(position.tokens, 0);
+        assert_eq!(position.fees, 30_000_000 + 600_000 + 1_200_000);
+        assert_eq!(position.bought_sol, 1_015_000_000 + 200_600_000);
+        assert_eq!(position.sold_sol, 985_000_000 + 398_800_000);
+        assert_eq!(
+            position.realized,
+            (position.sold_sol - position.bought_sol) as i64
+        );
+        assert_eq!(tracker.mark(&mint).unwrap().venue, Venue::PumpAmm);
+        drop(tracker);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
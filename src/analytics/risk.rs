@@ -0,0 +1,726 @@
+use log::error;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::extension::default_account_state::DefaultAccountState;
+use spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use spl_token_2022::extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions};
+use spl_token_2022::state::{AccountState, Mint};
+use spl_token_metadata_interface::state::TokenMetadata;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use super::creators::{CreatorProfile, CreatorTracker};
+use super::snipers::SniperReport;
+use crate::decoder::{Action, DecodedTransaction};
+use crate::types::constant::{METADATA_PROGRAM_ID, METADATA_SEED};
+use crate::types::pump_fun::CreateEvent;
+
+const EVICT_EVERY_SLOTS: u64 = 1_000;
+const FETCH_ATTEMPTS: u32 = 3;
+const FETCH_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// Metaplex `Key::MetadataV1`.
+const METADATA_V1_KEY: u8 = 4;
+
+/// The Metaplex metadata account of `mint`.
+pub fn metadata_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[METADATA_SEED, METADATA_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &METADATA_PROGRAM_ID,
+    )
+    .0
+}
+
+/// `is_mutable` of a Metaplex metadata account.
+fn metaplex_is_mutable(data: &[u8]) -> Option<bool> {
+    if *data.first()? != METADATA_V1_KEY {
+        return None;
+    }
+    let read_u32 = |offset: usize| -> Option<usize> {
+        Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?) as usize)
+    };
+    // key, update authority, mint
+    let mut offset = 1 + 32 + 32;
+    // name, symbol, uri
+    for _ in 0..3 {
+        offset += 4 + read_u32(offset)?;
+    }
+    // seller fee basis points
+    offset += 2;
+    offset += match data.get(offset)? {
+        0 => 1,
+        // address, verified, share
+        1 => 5 + 34 * read_u32(offset + 1)?,
+        _ => return None,
+    };
+    // primary sale happened
+    offset += 1;
+    Some(*data.get(offset)? != 0)
+}
+
+/// Fetches [`RiskTracker::accounts`] of `mint` for [`RiskTracker::observe_account`],
+/// retrying while the RPC node hasn't seen the mint yet.
+pub async fn fetch_accounts(
+    rpc: &RpcClient,
+    mint: &Pubkey,
+) -> anyhow::Result<Vec<(Pubkey, Vec<u8>, u64)>> {
+    let keys = RiskTracker::accounts(mint);
+    for attempt in 1..=FETCH_ATTEMPTS {
+        let response = rpc
+            .get_multiple_accounts_with_commitment(&keys, rpc.commitment())
+            .await?;
+        if response.value[0].is_some() || attempt == FETCH_ATTEMPTS {
+            return Ok(keys
+                .into_iter()
+                .zip(response.value)
+                .filter_map(|(key, account)| Some((key, account?.data, response.context.slot)))
+                .collect());
+        }
+        tokio::time::sleep(FETCH_RETRY_DELAY).await;
+    }
+    unreachable!("the last attempt returns")
+}
+
+/// One thing adding to a score.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RiskReason {
+    pub code: &'static str,
+    pub points: u32,
+    pub detail: String,
+}
+
+/// A mint's score out of 100 and why, sent when it changes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RiskScore {
+    pub mint: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub creator: Pubkey,
+    pub slot: u64,
+    pub score: u32,
+    /// `None` for the first score of a mint.
+    pub previous_score: Option<u32>,
+    /// Highest first.
+    pub reasons: Vec<RiskReason>,
+    /// Whether the mint account has been seen; its risks are missing until then.
+    pub mint_account_seen: bool,
+}
+
+impl RiskScore {
+    pub fn level(&self) -> &'static str {
+        match self.score {
+            0..30 => "low",
+            30..60 => "medium",
+            _ => "high",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct MintFacts {
+    mint_authority: bool,
+    freeze_authority: bool,
+    extensions: Vec<ExtensionType>,
+    default_frozen: bool,
+    transfer_fee_bps: Option<u16>,
+    /// Token-2022 metadata with an update authority.
+    metadata_mutable: Option<bool>,
+}
+
+struct TokenRisk {
+    create: CreateEvent,
+    /// The creator's other launches.
+    history: CreatorProfile,
+    mint: Option<MintFacts>,
+    metaplex_mutable: Option<bool>,
+    /// Percent of its bought tokens the dev sold.
+    dev_sold_share: f64,
+    /// sniper share, insider share, clusters of 3 or more wallets
+    snipers: Option<(f64, f64, usize)>,
+    score: Option<u32>,
+    codes: Vec<&'static str>,
+    last_slot: u64,
+}
+
+impl TokenRisk {
+    fn reasons(&self) -> Vec<RiskReason> {
+        let mut reasons = Vec::new();
+        let mut add = |code, points, detail: String| {
+            reasons.push(RiskReason {
+                code,
+                points,
+                detail,
+            })
+        };
+
+        if let Some(mint) = &self.mint {
+            if mint.mint_authority {
+                add("mint_authority", 30, "Mint authority is not revoked".into());
+            }
+            if mint.freeze_authority {
+                add("freeze_authority", 30, "Freeze authority is set".into());
+            }
+            for extension in &mint.extensions {
+                match extension {
+                    ExtensionType::PermanentDelegate => add(
+                        "permanent_delegate",
+                        40,
+                        "A permanent delegate can move or burn any holder's tokens".into(),
+                    ),
+                    ExtensionType::NonTransferable => {
+                        add("non_transferable", 40, "Tokens can't be transferred".into())
+                    }
+                    ExtensionType::TransferHook => add(
+                        "transfer_hook",
+                        25,
+                        "Transfers run a program chosen by the authority".into(),
+                    ),
+                    ExtensionType::Pausable => {
+                        add("pausable", 30, "Transfers can be paused".into())
+                    }
+                    ExtensionType::TransferFeeConfig => add(
+                        "transfer_fee",
+                        15,
+                        format!(
+                            "Transfer fee of {} bps",
+                            mint.transfer_fee_bps.unwrap_or_default()
+                        ),
+                    ),
+                    ExtensionType::DefaultAccountState if mint.default_frozen => add(
+                        "default_frozen",
+                        30,
+                        "New token accounts start frozen".into(),
+                    ),
+                    ExtensionType::MintCloseAuthority => {
+                        add("mint_close_authority", 10, "The mint can be closed".into())
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if self.metaplex_mutable == Some(true)
+            || self.mint.as_ref().and_then(|mint| mint.metadata_mutable) == Some(true)
+        {
+            add(
+                "mutable_metadata",
+                10,
+                "Metadata can still be changed".into(),
+            );
+        }
+        if self.create.uri.trim().is_empty() {
+            add(
+                "no_metadata_uri",
+                10,
+                "The create has no metadata URI".into(),
+            );
+        }
+
+        let history = &self.history;
+        if history.launched >= 5 && history.completed == 0 {
+            add(
+                "serial_creator",
+                15,
+                format!(
+                    "Creator launched {} tokens before, none completed",
+                    history.launched
+                ),
+            );
+        }
+        if history.launched >= 3 && history.dev_sold * 5 >= history.launched * 4 {
+            add(
+                "creator_dumps",
+                15,
+                format!(
+                    "Creator sold {} of their {} previous tokens",
+                    history.dev_sold, history.launched
+                ),
+            );
+        }
+        if let Some(secs) = history.avg_secs_to_sell
+            && secs < 60.0
+        {
+            add(
+                "creator_sells_fast",
+                10,
+                format!("Creator sells {secs:.0}s after launch on average"),
+            );
+        }
+
+        let sold = self.dev_sold_share;
+        match sold {
+            99.0.. => add("dev_sold", 25, "Creator sold everything".into()),
+            50.0.. => add("dev_sold", 15, format!("Creator sold {sold:.0}%")),
+            _ if sold > 0.0 => add("dev_sold", 5, format!("Creator sold {sold:.0}%")),
+            _ => {}
+        }
+
+        if let Some((snipers, insiders, clusters)) = self.snipers {
+            match insiders {
+                20.0.. => add("insiders", 20, format!("Insiders hold {insiders:.1}%")),
+                10.0.. => add("insiders", 10, format!("Insiders hold {insiders:.1}%")),
+                _ => {}
+            }
+            if snipers >= 30.0 {
+                add("snipers", 10, format!("Early buyers hold {snipers:.1}%"));
+            }
+            if clusters > 0 {
+                add(
+                    "funded_clusters",
+                    10,
+                    format!("{clusters} clusters of 3+ early buyers share funders"),
+                );
+            }
+        }
+
+        reasons.sort_by(|a, b| b.points.cmp(&a.points).then(a.code.cmp(b.code)));
+        reasons
+    }
+}
+
+/// Scores new pump.fun mints from their create, mint account, metadata,
+/// creator history, dev sells and early buyers.
+///
+/// Mint and metadata accounts aren't in the transaction stream: they come from
+/// account updates or an RPC fetch of [`RiskTracker::accounts`].
+pub struct RiskTracker {
+    max_idle_slots: u64,
+    tokens: HashMap<Pubkey, TokenRisk>,
+    /// metadata account -> mint
+    metadata: HashMap<Pubkey, Pubkey>,
+    last_evicted_slot: u64,
+}
+
+impl RiskTracker {
+    pub fn new(max_idle_slots: u64) -> Self {
+        Self {
+            max_idle_slots,
+            tokens: HashMap::new(),
+            metadata: HashMap::new(),
+            last_evicted_slot: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// The mint and its Metaplex metadata account.
+    pub fn accounts(mint: &Pubkey) -> [Pubkey; 2] {
+        [*mint, metadata_address(mint)]
+    }
+
+    /// Scores new mints and rescores the ones traded. Run after `creators`
+    /// saw the same transaction.
+    pub fn observe(
+        &mut self,
+        decoded: &DecodedTransaction,
+        creators: &mut CreatorTracker,
+    ) -> Vec<RiskScore> {
+        let mut touched = Vec::new();
+        for action in &decoded.actions {
+            match &action.action {
+                Action::PumpFunCreate(create) => {
+                    let history = creators
+                        .profile_without(&create.creator, &create.mint)
+                        .unwrap_or_else(|error| {
+                            error!("failed to read the profile of {}: {error}", create.creator);
+                            CreatorProfile::default()
+                        });
+                    self.metadata
+                        .insert(metadata_address(&create.mint), create.mint);
+                    self.tokens.insert(
+                        create.mint,
+                        TokenRisk {
+                            create: create.clone(),
+                            history,
+                            mint: None,
+                            metaplex_mutable: None,
+                            dev_sold_share: 0.0,
+                            snipers: None,
+                            score: None,
+                            codes: Vec::new(),
+                            last_slot: decoded.slot,
+                        },
+                    );
+                    touched.push(create.mint);
+                }
+                Action::PumpFunTrade(trade) => {
+                    if let Some(token) = self.tokens.get_mut(&trade.mint) {
+                        token.last_slot = decoded.slot;
+                        if let Some(dev) = creators.token(&trade.mint) {
+                            token.dev_sold_share = dev.sold_share();
+                        }
+                        touched.push(trade.mint);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if decoded.slot >= self.last_evicted_slot + EVICT_EVERY_SLOTS {
+            self.last_evicted_slot = decoded.slot;
+            let oldest = decoded.slot.saturating_sub(self.max_idle_slots);
+            self.tokens.retain(|_, token| token.last_slot >= oldest);
+            let tokens = &self.tokens;
+            self.metadata.retain(|_, mint| tokens.contains_key(mint));
+        }
+
+        let mut seen = HashSet::new();
+        touched
+            .into_iter()
+            .filter(|mint| seen.insert(*mint))
+            .filter_map(|mint| self.rescore(&mint, decoded.slot))
+            .collect()
+    }
+
+    pub fn observe_sniper_report(&mut self, report: &SniperReport) -> Option<RiskScore> {
+        let token = self.tokens.get_mut(&report.mint)?;
+        let clusters = report
+            .clusters
+            .iter()
+            .filter(|cluster| cluster.wallets.len() >= 3)
+            .count();
+        token.snipers = Some((report.sniper_share(), report.insider_share(), clusters));
+        let slot = token.last_slot;
+        self.rescore(&report.mint, slot)
+    }
+
+    /// A mint or Metaplex metadata account of a scored mint.
+    pub fn observe_account(
+        &mut self,
+        pubkey: &Pubkey,
+        data: &[u8],
+        slot: u64,
+    ) -> Option<RiskScore> {
+        let mint = match self.metadata.get(pubkey) {
+            Some(mint) => {
+                let mint = *mint;
+                self.tokens.get_mut(&mint)?.metaplex_mutable = metaplex_is_mutable(data);
+                mint
+            }
+            None => {
+                let token = self.tokens.get_mut(pubkey)?;
+                let state = StateWithExtensions::<Mint>::unpack(data).ok()?;
+                token.mint = Some(MintFacts {
+                    mint_authority: state.base.mint_authority.is_some(),
+                    freeze_authority: state.base.freeze_authority.is_some(),
+                    extensions: state.get_extension_types().unwrap_or_default(),
+                    default_frozen: state
+                        .get_extension::<DefaultAccountState>()
+                        .is_ok_and(|default| default.state == AccountState::Frozen as u8),
+                    transfer_fee_bps: state.get_extension::<TransferFeeConfig>().ok().map(
+                        |config| u16::from(config.newer_transfer_fee.transfer_fee_basis_points),
+                    ),
+                    metadata_mutable: state
+                        .get_variable_len_extension::<TokenMetadata>()
+                        .ok()
+                        .map(|metadata| {
+                            Option::<Pubkey>::from(metadata.update_authority).is_some()
+                        }),
+                });
+                *pubkey
+            }
+        };
+        self.rescore(&mint, slot)
+    }
+
+    fn rescore(&mut self, mint: &Pubkey, slot: u64) -> Option<RiskScore> {
+        let token = self.tokens.get_mut(mint)?;
+        let reasons = token.reasons();
+        let score = reasons
+            .iter()
+            .map(|reason| reason.points)
+            .sum::<u32>()
+            .min(100);
+        let codes = reasons.iter().map(|reason| reason.code).collect::<Vec<_>>();
+        if token.score == Some(score) && token.codes == codes {
+            return None;
+        }
+        let previous_score = token.score.replace(score);
+        token.codes = codes;
+        Some(RiskScore {
+            mint: *mint,
+            name: token.create.name.clone(),
+            symbol: token.create.symbol.clone(),
+            creator: token.create.creator,
+            slot,
+            score,
+            previous_score,
+            reasons,
+            mint_account_seen: token.mint.is_some(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::snipers::{EarlyBuyer, WalletCluster};
+    use crate::types::pump_fun::TradeEvent;
+    use solana_sdk::program_option::COption;
+    use solana_sdk::program_pack::Pack;
+    use solana_sdk::signature::Signature;
+    use std::env;
+
+    /// A Metaplex `MetadataV1` account as the token metadata program writes it:
+    /// name, symbol and uri padded to 32, 10 and 200 bytes, the optional fields
+    /// after `is_mutable` set like a pump.fun token's, zero filled to 679 bytes.
+    /// No recorded account is available offline, so the layout follows the
+    /// program's `Metadata` struct field by field.
+    fn metadata_account(mint: &Pubkey, creators: &[(Pubkey, bool, u8)], mutable: bool) -> Vec<u8> {
+        let mut data = vec![METADATA_V1_KEY];
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(mint.as_ref());
+        for (value, padded) in [
+            ("fully autistic retards trading", 32),
+            ("fart", 10),
+            (
+                "https://ipfs.io/ipfs/QmS1rcU1VvPTVpuJqWrTkc5g6C9GNqbiHzv824VimWGKgP",
+                200,
+            ),
+        ] {
+            data.extend_from_slice(&(padded as u32).to_le_bytes());
+            let mut bytes = value.as_bytes().to_vec();
+            bytes.resize(padded, 0);
+            data.extend_from_slice(&bytes);
+        }
+        // seller fee basis points
+        data.extend_from_slice(&0u16.to_le_bytes());
+        if creators.is_empty() {
+            data.push(0);
+        } else {
+            data.push(1);
+            data.extend_from_slice(&(creators.len() as u32).to_le_bytes());
+            for (address, verified, share) in creators {
+                data.extend_from_slice(address.as_ref());
+                data.extend_from_slice(&[u8::from(*verified), *share]);
+            }
+        }
+        // primary sale happened, is mutable
+        data.extend_from_slice(&[0, u8::from(mutable)]);
+        // edition nonce Some(254), token standard Some(Fungible), then no
+        // collection, uses, collection details or programmable config
+        data.extend_from_slice(&[1, 254, 1, 2, 0, 0, 0, 0]);
+        data.resize(679, 0);
+        data
+    }
+
+    fn mint_account(mint_authority: bool, freeze_authority: bool) -> Vec<u8> {
+        let authority = |set| match set {
+            true => COption::Some(Pubkey::new_unique()),
+            false => COption::None,
+        };
+        let mut data = vec![0; Mint::LEN];
+        Mint {
+            mint_authority: authority(mint_authority),
+            supply: 1_000_000_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: authority(freeze_authority),
+        }
+        .pack_into_slice(&mut data);
+        data
+    }
+
+    fn codes(score: &RiskScore) -> Vec<&'static str> {
+        score.reasons.iter().map(|reason| reason.code).collect()
+    }
+
+    fn creators(name: &str) -> (CreatorTracker, std::path::PathBuf) {
+        let path = env::temp_dir().join(format!("risk-{name}-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        (CreatorTracker::open(&path, 1_000).unwrap(), path)
+    }
+
+    fn create(creator: Pubkey, mint: Pubkey) -> Action {
+        Action::PumpFunCreate(CreateEvent {
+            name: "Token".into(),
+            symbol: "TOK".into(),
+            uri: "https://ipfs.io/ipfs/Qm".into(),
+            mint,
+            user: creator,
+            creator,
+            ..Default::default()
+        })
+    }
+
+    fn trade(creator: Pubkey, mint: Pubkey, is_buy: bool, tokens: u64, timestamp: i64) -> Action {
+        Action::PumpFunTrade(TradeEvent {
+            mint,
+            sol_amount: 1_000_000,
+            token_amount: tokens,
+            is_buy,
+            user: creator,
+            timestamp,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn reads_is_mutable_with_and_without_creators() {
+        let mint = Pubkey::new_unique();
+        assert_eq!(
+            metaplex_is_mutable(&metadata_account(&mint, &[], false)),
+            Some(false)
+        );
+        assert_eq!(
+            metaplex_is_mutable(&metadata_account(&mint, &[], true)),
+            Some(true)
+        );
+        let creators = [
+            (Pubkey::new_unique(), true, 60),
+            (Pubkey::new_unique(), false, 40),
+        ];
+        assert_eq!(
+            metaplex_is_mutable(&metadata_account(&mint, &creators, true)),
+            Some(true)
+        );
+        assert_eq!(
+            metaplex_is_mutable(&metadata_account(&mint, &creators, false)),
+            Some(false)
+        );
+
+        // cut before is_mutable, or not a MetadataV1 account
+        let data = metadata_account(&mint, &creators, true);
+        let is_mutable = 1 + 32 + 32 + (4 + 32) + (4 + 10) + (4 + 200) + 2 + 1 + 4 + 2 * 34 + 1;
+        assert_eq!(metaplex_is_mutable(&data[..is_mutable]), None);
+        assert_eq!(metaplex_is_mutable(&data[..=is_mutable]), Some(true));
+        let mut edition = data;
+        edition[0] = 6;
+        assert_eq!(metaplex_is_mutable(&edition), None);
+    }
+
+    #[test]
+    fn rescoring_tracks_reasons_and_previous_scores() {
+        let (mut creators, path) = creators("transitions");
+        let (creator, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut risk = RiskTracker::new(1_000);
+        let mut observe = |risk: &mut RiskTracker, slot, actions| {
+            let decoded = DecodedTransaction::with_actions(slot, actions);
+            creators.observe(&decoded);
+            risk.observe(&decoded, &mut creators)
+        };
+
+        let scores = observe(&mut risk, 10, vec![create(creator, mint)]);
+        assert_eq!(scores.len(), 1);
+        assert_eq!((scores[0].score, scores[0].previous_score), (0, None));
+        assert!(!scores[0].mint_account_seen);
+        assert_eq!(risk.len(), 1);
+
+        let score = risk
+            .observe_account(&mint, &mint_account(true, false), 11)
+            .unwrap();
+        assert_eq!((score.score, score.previous_score), (30, Some(0)));
+        assert_eq!(codes(&score), ["mint_authority"]);
+        assert!(score.mint_account_seen);
+        // nothing changed, nothing sent
+        assert!(
+            risk.observe_account(&mint, &mint_account(true, false), 12)
+                .is_none()
+        );
+
+        let [_, metadata] = RiskTracker::accounts(&mint);
+        let score = risk
+            .observe_account(&metadata, &metadata_account(&mint, &[], true), 12)
+            .unwrap();
+        assert_eq!((score.score, score.previous_score), (40, Some(30)));
+        assert_eq!(codes(&score), ["mint_authority", "mutable_metadata"]);
+
+        observe(&mut risk, 13, vec![trade(creator, mint, true, 1_000, 0)]);
+        let scores = observe(&mut risk, 14, vec![trade(creator, mint, false, 600, 0)]);
+        assert_eq!((scores[0].score, scores[0].previous_score), (55, Some(40)));
+        assert_eq!(scores[0].reasons[1].detail, "Creator sold 60%");
+
+        let score = risk
+            .observe_account(&mint, &mint_account(false, false), 15)
+            .unwrap();
+        assert_eq!((score.score, score.previous_score), (25, Some(55)));
+        assert_eq!(codes(&score), ["dev_sold", "mutable_metadata"]);
+
+        let buyer = |tokens_held, insider, cluster| EarlyBuyer {
+            wallet: Pubkey::new_unique(),
+            slot: 10,
+            slot_offset: 0,
+            first_signature: Signature::default(),
+            sol_amount: 0,
+            tokens_held,
+            funders: Vec::new(),
+            dev: false,
+            creator_funded: false,
+            insider,
+            cluster,
+        };
+        let report = SniperReport {
+            mint,
+            name: String::new(),
+            symbol: String::new(),
+            creator,
+            slot: 10,
+            signature: Signature::default(),
+            window_slots: 3,
+            token_total_supply: 1_000,
+            buyers: vec![
+                buyer(100, true, Some(0)),
+                buyer(100, true, Some(0)),
+                buyer(50, true, Some(0)),
+                buyer(100, false, None),
+            ],
+            clusters: vec![WalletCluster {
+                wallets: vec![Pubkey::new_unique(); 3],
+                funders: vec![Pubkey::new_unique()],
+            }],
+        };
+        let score = risk.observe_sniper_report(&report).unwrap();
+        assert_eq!((score.score, score.previous_score), (65, Some(25)));
+        assert_eq!(
+            codes(&score),
+            [
+                "insiders",
+                "dev_sold",
+                "funded_clusters",
+                "mutable_metadata",
+                "snipers"
+            ]
+        );
+        drop(creators);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn scores_the_creators_other_launches() {
+        let (mut creators, path) = creators("history");
+        let creator = Pubkey::new_unique();
+        let mut risk = RiskTracker::new(1_000);
+        // five launches, none completed, each sold 40s in
+        for slot in 1..=5 {
+            let mint = Pubkey::new_unique();
+            creators.observe(&DecodedTransaction::with_actions(
+                slot,
+                vec![
+                    create(creator, mint),
+                    trade(creator, mint, true, 1_000, 10),
+                    trade(creator, mint, false, 1_000, 40),
+                ],
+            ));
+        }
+
+        let mint = Pubkey::new_unique();
+        let decoded = DecodedTransaction::with_actions(6, vec![create(creator, mint)]);
+        creators.observe(&decoded);
+        let scores = risk.observe(&decoded, &mut creators);
+        assert_eq!(scores[0].score, 40);
+        assert_eq!(
+            codes(&scores[0]),
+            ["creator_dumps", "serial_creator", "creator_sells_fast"]
+        );
+        assert_eq!(
+            scores[0].reasons[1].detail,
+            "Creator launched 5 tokens before, none completed"
+        );
+        drop(creators);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
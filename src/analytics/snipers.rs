@@ -0,0 +1,524 @@
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::decoder::{Action, DecodedTransaction};
+use crate::types::pump_fun::{CreateEvent, TradeEvent};
+
+/// Buys in the create slot and this many slots after it are early.
+pub const DEFAULT_SNIPER_WINDOW_SLOTS: u64 = 3;
+/// Transfers below this (0.01 SOL) don't count as funding a wallet.
+const MIN_FUNDING_LAMPORTS: u64 = 10_000_000;
+/// Funding older than this many slots (~1 day) is forgotten.
+const FUNDING_TTL_SLOTS: u64 = 216_000;
+const EVICT_EVERY_SLOTS: u64 = 1_000;
+/// Funders remembered per wallet, the most recent ones.
+const MAX_FUNDERS: usize = 8;
+
+/// A wallet that bought in the launch window.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EarlyBuyer {
+    pub wallet: Pubkey,
+    /// Slot of the first buy and how many slots after the create it landed.
+    pub slot: u64,
+    pub slot_offset: u64,
+    pub first_signature: Signature,
+    /// Lamports spent buying in the window.
+    pub sol_amount: u64,
+    /// Tokens bought minus tokens sold in the window, raw amount.
+    pub tokens_held: u64,
+    /// Wallets seen sending it SOL.
+    pub funders: Vec<Pubkey>,
+    /// The creator or the wallet that signed the create.
+    pub dev: bool,
+    /// Funded by the creator or the create signer.
+    pub creator_funded: bool,
+    /// Dev, creator funded, sharing a funder with the creator, or clustered with any of those.
+    pub insider: bool,
+    /// Index into [`SniperReport::clusters`].
+    pub cluster: Option<usize>,
+}
+
+/// Early buyers sharing at least one funder.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WalletCluster {
+    pub wallets: Vec<Pubkey>,
+    pub funders: Vec<Pubkey>,
+}
+
+/// Who bought a launch in its first slots, at the end of the window.
+///
+/// Funding comes from the System transfers in the transactions the stream
+/// delivers, which with the default subscription means only transfers inside
+/// pump.fun transactions. A buyer funded by a plain transfer is not linked to
+/// its funder, so `funders`, `creator_funded`, `insider` and the clusters
+/// undercount.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SniperReport {
+    pub mint: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub creator: Pubkey,
+    pub slot: u64,
+    pub signature: Signature,
+    pub window_slots: u64,
+    pub token_total_supply: u64,
+    /// In order of the first buy.
+    pub buyers: Vec<EarlyBuyer>,
+    pub clusters: Vec<WalletCluster>,
+}
+
+impl SniperReport {
+    fn share(&self, filter: impl Fn(&EarlyBuyer) -> bool) -> f64 {
+        if self.token_total_supply == 0 {
+            return 0.0;
+        }
+        let held: u64 = self
+            .buyers
+            .iter()
+            .filter(|buyer| filter(buyer))
+            .map(|buyer| buyer.tokens_held)
+            .sum();
+        held as f64 * 100.0 / self.token_total_supply as f64
+    }
+
+    /// Percent of the supply held by early buyers other than the dev.
+    pub fn sniper_share(&self) -> f64 {
+        self.share(|buyer| !buyer.dev)
+    }
+
+    /// Percent of the supply held by insiders, the dev included.
+    pub fn insider_share(&self) -> f64 {
+        self.share(|buyer| buyer.insider)
+    }
+
+    pub fn same_slot_buyers(&self) -> usize {
+        self.buyers
+            .iter()
+            .filter(|buyer| buyer.slot_offset == 0)
+            .count()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Buy {
+    slot: u64,
+    signature: Signature,
+    sol_amount: u64,
+    bought: u64,
+    sold: u64,
+}
+
+struct PendingLaunch {
+    create: CreateEvent,
+    slot: u64,
+    signature: Signature,
+    buys: HashMap<Pubkey, Buy>,
+}
+
+/// Finds snipers and insiders of new launches.
+///
+/// Funding comes from the system transfers seen in the stream, so it only
+/// covers transfers in subscribed transactions. A launch is reported once a
+/// transaction past its window arrives.
+pub struct SniperTracker {
+    window_slots: u64,
+    pending: BTreeMap<u64, Vec<Pubkey>>,
+    launches: HashMap<Pubkey, PendingLaunch>,
+    /// wallet -> (funder, slot), most recent last
+    funders: HashMap<Pubkey, Vec<(Pubkey, u64)>>,
+    last_evicted_slot: u64,
+}
+
+impl SniperTracker {
+    pub fn new(window_slots: u64) -> Self {
+        Self {
+            window_slots,
+            pending: BTreeMap::new(),
+            launches: HashMap::new(),
+            funders: HashMap::new(),
+            last_evicted_slot: 0,
+        }
+    }
+
+    /// Returns the reports of the launches whose window closed.
+    pub fn observe(&mut self, decoded: &DecodedTransaction) -> Vec<SniperReport> {
+        for action in &decoded.actions {
+            match &action.action {
+                Action::SystemTransfer { from, to, lamports }
+                    if *lamports >= MIN_FUNDING_LAMPORTS =>
+                {
+                    self.funded(*from, *to, decoded.slot)
+                }
+                Action::PumpFunCreate(create) => {
+                    self.pending
+                        .entry(decoded.slot)
+                        .or_default()
+                        .push(create.mint);
+                    self.launches.insert(
+                        create.mint,
+                        PendingLaunch {
+                            create: create.clone(),
+                            slot: decoded.slot,
+                            signature: decoded.signature,
+                            buys: HashMap::new(),
+                        },
+                    );
+                }
+                Action::PumpFunTrade(trade) => self.trade(trade, decoded),
+                _ => {}
+            }
+        }
+
+        if decoded.slot >= self.last_evicted_slot + EVICT_EVERY_SLOTS {
+            self.last_evicted_slot = decoded.slot;
+            let oldest = decoded.slot.saturating_sub(FUNDING_TTL_SLOTS);
+            self.funders.retain(|_, funders| {
+                funders.retain(|(_, slot)| *slot >= oldest);
+                !funders.is_empty()
+            });
+        }
+
+        // launches created before this slot minus the window are done
+        let closed = decoded.slot.saturating_sub(self.window_slots);
+        let keep = self.pending.split_off(&closed);
+        let done = std::mem::replace(&mut self.pending, keep);
+        done.into_values()
+            .flatten()
+            .filter_map(|mint| self.report(&mint))
+            .collect()
+    }
+
+    /// Reports every launch still in its window, e.g. on shutdown.
+    pub fn flush(&mut self) -> Vec<SniperReport> {
+        std::mem::take(&mut self.pending)
+            .into_values()
+            .flatten()
+            .filter_map(|mint| self.report(&mint))
+            .collect()
+    }
+
+    fn funded(&mut self, from: Pubkey, to: Pubkey, slot: u64) {
+        if from == to {
+            return;
+        }
+        let funders = self.funders.entry(to).or_default();
+        funders.retain(|(funder, _)| *funder != from);
+        funders.push((from, slot));
+        if funders.len() > MAX_FUNDERS {
+            funders.remove(0);
+        }
+    }
+
+    fn trade(&mut self, trade: &TradeEvent, decoded: &DecodedTransaction) {
+        let Some(launch) = self.launches.get_mut(&trade.mint) else {
+            return;
+        };
+        // slots before the create don't belong to this launch
+        if decoded.slot < launch.slot || decoded.slot > launch.slot + self.window_slots {
+            return;
+        }
+        if trade.is_buy {
+            let buy = launch.buys.entry(trade.user).or_insert_with(|| Buy {
+                slot: decoded.slot,
+                signature: decoded.signature,
+                sol_amount: 0,
+                bought: 0,
+                sold: 0,
+            });
+            if decoded.slot < buy.slot {
+                buy.slot = decoded.slot;
+                buy.signature = decoded.signature;
+            }
+            buy.sol_amount += trade.sol_amount;
+            buy.bought += trade.token_amount;
+        } else if let Some(buy) = launch.buys.get_mut(&trade.user) {
+            buy.sold += trade.token_amount;
+        }
+    }
+
+    fn funders_of(&self, wallet: &Pubkey) -> Vec<Pubkey> {
+        self.funders
+            .get(wallet)
+            .into_iter()
+            .flatten()
+            .map(|(funder, _)| *funder)
+            .collect()
+    }
+
+    fn report(&mut self, mint: &Pubkey) -> Option<SniperReport> {
+        let launch = self.launches.remove(mint)?;
+        let create = &launch.create;
+        let devs = [create.creator, create.user];
+        let dev_funders = devs
+            .iter()
+            .flat_map(|dev| self.funders_of(dev))
+            .collect::<HashSet<_>>();
+
+        let mut buys = launch.buys.into_iter().collect::<Vec<_>>();
+        buys.sort_by_key(|(wallet, buy)| (buy.slot, buy.signature, *wallet));
+        let mut buyers = buys
+            .into_iter()
+            .map(|(wallet, buy)| {
+                let funders = self.funders_of(&wallet);
+                let dev = devs.contains(&wallet);
+                let creator_funded = funders.iter().any(|funder| devs.contains(funder));
+                EarlyBuyer {
+                    wallet,
+                    slot: buy.slot,
+                    slot_offset: buy.slot - launch.slot,
+                    first_signature: buy.signature,
+                    sol_amount: buy.sol_amount,
+                    tokens_held: buy.bought.saturating_sub(buy.sold),
+                    insider: dev
+                        || creator_funded
+                        || funders.iter().any(|funder| dev_funders.contains(funder)),
+                    funders,
+                    dev,
+                    creator_funded,
+                    cluster: None,
+                }
+            })
+            .collect::<Vec<_>>();
+        let clusters = cluster(&mut buyers);
+
+        Some(SniperReport {
+            mint: create.mint,
+            name: create.name.clone(),
+            symbol: create.symbol.clone(),
+            creator: create.creator,
+            slot: launch.slot,
+            signature: launch.signature,
+            window_slots: self.window_slots,
+            token_total_supply: create.token_total_supply,
+            buyers,
+            clusters,
+        })
+    }
+}
+
+/// Groups the buyers sharing a funder, directly or through other buyers, and
+/// spreads `insider` over each group.
+fn cluster(buyers: &mut [EarlyBuyer]) -> Vec<WalletCluster> {
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut parent = (0..buyers.len()).collect::<Vec<_>>();
+    let mut first_funded = HashMap::new();
+    for (i, buyer) in buyers.iter().enumerate() {
+        for funder in &buyer.funders {
+            let first = *first_funded.entry(*funder).or_insert(i);
+            let (a, b) = (root(&mut parent, first), root(&mut parent, i));
+            parent[b] = a;
+        }
+    }
+
+    let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for i in 0..buyers.len() {
+        let group = root(&mut parent, i);
+        groups.entry(group).or_default().push(i);
+    }
+    let mut clusters = Vec::new();
+    for members in groups.into_values().filter(|members| members.len() > 1) {
+        let mut counts: HashMap<Pubkey, usize> = HashMap::new();
+        for member in &members {
+            for funder in &buyers[*member].funders {
+                *counts.entry(*funder).or_default() += 1;
+            }
+        }
+        let mut funders = counts
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(funder, _)| funder)
+            .collect::<Vec<_>>();
+        funders.sort();
+        let insider = members.iter().any(|member| buyers[*member].insider);
+        for member in &members {
+            buyers[*member].cluster = Some(clusters.len());
+            buyers[*member].insider |= insider;
+        }
+        clusters.push(WalletCluster {
+            wallets: members
+                .iter()
+                .map(|member| buyers[*member].wallet)
+                .collect(),
+            funders,
+        });
+    }
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create(creator: Pubkey, mint: Pubkey) -> Action {
+        Action::PumpFunCreate(CreateEvent {
+            mint,
+            user: creator,
+            creator,
+            token_total_supply: 1_000_000,
+            ..Default::default()
+        })
+    }
+
+    fn buy(user: Pubkey, mint: Pubkey, tokens: u64) -> Action {
+        Action::PumpFunTrade(TradeEvent {
+            mint,
+            sol_amount: 1_000_000,
+            token_amount: tokens,
+            is_buy: true,
+            user,
+            ..Default::default()
+        })
+    }
+
+    fn fund(from: Pubkey, to: Pubkey) -> Action {
+        Action::SystemTransfer {
+            from,
+            to,
+            lamports: MIN_FUNDING_LAMPORTS,
+        }
+    }
+
+    fn tx(slot: u64, actions: Vec<Action>) -> DecodedTransaction {
+        DecodedTransaction::with_actions(slot, actions)
+    }
+
+    #[test]
+    fn reports_once_the_window_closes() {
+        let (creator, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (early, late) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut tracker = SniperTracker::new(3);
+        assert!(
+            tracker
+                .observe(&tx(99, vec![buy(late, mint, 10)]))
+                .is_empty()
+        );
+        assert!(
+            tracker
+                .observe(&tx(100, vec![create(creator, mint)]))
+                .is_empty()
+        );
+        assert!(
+            tracker
+                .observe(&tx(100, vec![buy(early, mint, 100)]))
+                .is_empty()
+        );
+        assert!(
+            tracker
+                .observe(&tx(103, vec![buy(early, mint, 50)]))
+                .is_empty()
+        );
+
+        // the first transaction past the window closes it, its own buy is too late
+        let reports = tracker.observe(&tx(104, vec![buy(late, mint, 10)]));
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert_eq!((report.slot, report.window_slots), (100, 3));
+        assert_eq!(report.buyers.len(), 1);
+        assert_eq!(report.buyers[0].wallet, early);
+        assert_eq!(report.buyers[0].slot_offset, 0);
+        assert_eq!(report.buyers[0].tokens_held, 150);
+        assert_eq!(report.same_slot_buyers(), 1);
+        assert!(tracker.observe(&tx(105, vec![])).is_empty());
+        assert!(tracker.flush().is_empty());
+    }
+
+    #[test]
+    fn flags_dev_creator_funded_and_insiders() {
+        let (creator, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let funded = Pubkey::new_unique();
+        let sibling = Pubkey::new_unique();
+        let dust = Pubkey::new_unique();
+        let outsider = Pubkey::new_unique();
+        let shared = Pubkey::new_unique();
+        let mut tracker = SniperTracker::new(3);
+        tracker.observe(&tx(
+            50,
+            vec![
+                fund(creator, funded),
+                fund(shared, creator),
+                fund(shared, sibling),
+                Action::SystemTransfer {
+                    from: creator,
+                    to: dust,
+                    lamports: MIN_FUNDING_LAMPORTS - 1,
+                },
+                fund(Pubkey::new_unique(), outsider),
+            ],
+        ));
+        tracker.observe(&tx(
+            100,
+            vec![
+                create(creator, mint),
+                buy(creator, mint, 100_000),
+                buy(funded, mint, 50_000),
+                buy(sibling, mint, 20_000),
+                buy(dust, mint, 20_000),
+                buy(outsider, mint, 10_000),
+            ],
+        ));
+        let report = tracker.flush().remove(0);
+        let flags = |wallet: Pubkey| {
+            let buyer = report.buyers.iter().find(|b| b.wallet == wallet).unwrap();
+            (buyer.dev, buyer.creator_funded, buyer.insider)
+        };
+        assert_eq!(flags(creator), (true, false, true));
+        assert_eq!(flags(funded), (false, true, true));
+        assert_eq!(flags(sibling), (false, false, true));
+        assert_eq!(flags(dust), (false, false, false));
+        assert_eq!(flags(outsider), (false, false, false));
+        assert_eq!(report.sniper_share(), 10.0);
+        assert_eq!(report.insider_share(), 17.0);
+    }
+
+    #[test]
+    fn clusters_buyers_through_shared_funders() {
+        let (creator, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let wallets = [(); 5].map(|_| Pubkey::new_unique());
+        let (x, y, z) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut tracker = SniperTracker::new(3);
+        // x links 0 and 1, y links 1 and 2, so 0-1-2 is one cluster; z links 3 and 4
+        tracker.observe(&tx(
+            50,
+            vec![
+                fund(x, wallets[0]),
+                fund(x, wallets[1]),
+                fund(y, wallets[1]),
+                fund(y, wallets[2]),
+                fund(creator, wallets[2]),
+                fund(z, wallets[3]),
+                fund(z, wallets[4]),
+            ],
+        ));
+        tracker.observe(&tx(100, vec![create(creator, mint)]));
+        for (slot, wallet) in (100..).zip(wallets) {
+            tracker.observe(&tx(slot.min(103), vec![buy(wallet, mint, 1_000)]));
+        }
+        let report = tracker.flush().remove(0);
+        assert_eq!(report.clusters.len(), 2);
+
+        let mut linked = vec![x, y];
+        linked.sort();
+        assert_eq!(report.clusters[0].wallets, wallets[..3]);
+        assert_eq!(report.clusters[0].funders, linked);
+        assert_eq!(report.clusters[1].wallets, wallets[3..]);
+        assert_eq!(report.clusters[1].funders, vec![z]);
+
+        // the creator funded wallet 2, which makes its whole cluster insiders
+        for (i, buyer) in report.buyers.iter().enumerate() {
+            assert_eq!(buyer.cluster, Some(usize::from(i >= 3)));
+            assert_eq!(buyer.insider, i < 3);
+            assert_eq!(buyer.creator_funded, i == 2);
+        }
+    }
+}
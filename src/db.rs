@@ -0,0 +1,29 @@
+use log::info;
+use rusqlite::{Connection, Row, types::Type};
+use solana_sdk::pubkey::Pubkey;
+use std::path::Path;
+
+/// Opens the SQLite file at `path` in WAL mode and applies the `migrations`
+/// not applied yet, in order. `PRAGMA user_version` is the number applied so
+/// far, so a migration list must only ever be appended to. `name` is for logs.
+pub fn open(path: impl AsRef<Path>, name: &str, migrations: &[&str]) -> anyhow::Result<Connection> {
+    let mut conn = Connection::open(path)?;
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (i, migration) in migrations.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+        info!("{name} schema migrated to version {}", i + 1);
+    }
+    Ok(conn)
+}
+
+/// A base58 pubkey column.
+pub fn pubkey(row: &Row, index: usize) -> rusqlite::Result<Pubkey> {
+    row.get::<_, String>(index)?.parse().map_err(|error| {
+        rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(error))
+    })
+}
@@ -0,0 +1,473 @@
+pub mod pump_amm;
+pub mod pump_fun;
+pub mod raydium;
+pub mod spl_token;
+pub mod system;
+
+use anyhow::Context;
+use base64::{Engine, engine::general_purpose};
+use chrono::{DateTime, Utc};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::collections::HashMap;
+use yellowstone_grpc_proto::prelude::SubscribeUpdateTransaction;
+
+use crate::types::pump_fun::{
+    BuyEvent, CompleteEvent, CreateEvent, CreatePoolEvent, SellEvent, TradeEvent,
+};
+use crate::types::raydium::RaydiumSwap;
+use crate::types::swap::SwapEvent;
+
+pub use pump_amm::PumpAmmDecoder;
+pub use pump_fun::PumpFunDecoder;
+pub use raydium::RaydiumDecoder;
+pub use spl_token::SplTokenDecoder;
+pub use system::SystemDecoder;
+
+const PROGRAM_DATA: &str = "Program data: ";
+const PROGRAM_LOG: &str = "Program log: ";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    PumpFunCreate(CreateEvent),
+    PumpFunTrade(TradeEvent),
+    PumpFunComplete(CompleteEvent),
+    PumpAmmBuy {
+        event: BuyEvent,
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+    },
+    PumpAmmSell {
+        event: SellEvent,
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+    },
+    PumpAmmCreatePool(CreatePoolEvent),
+    RaydiumSwap(RaydiumSwap),
+    TokenTransfer {
+        source: Pubkey,
+        destination: Pubkey,
+        authority: Pubkey,
+        mint: Option<Pubkey>,
+        amount: u64,
+    },
+    TokenMintTo {
+        mint: Pubkey,
+        account: Pubkey,
+        amount: u64,
+    },
+    TokenBurn {
+        mint: Pubkey,
+        account: Pubkey,
+        amount: u64,
+    },
+    SystemTransfer {
+        from: Pubkey,
+        to: Pubkey,
+        lamports: u64,
+    },
+    SystemCreateAccount {
+        from: Pubkey,
+        to: Pubkey,
+        lamports: u64,
+        space: u64,
+        owner: Pubkey,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedAction {
+    pub program_id: Pubkey,
+    /// Index of the top-level instruction.
+    pub instruction_index: usize,
+    /// Position in that instruction's inner instruction list, `None` for the top-level one.
+    pub inner_index: Option<usize>,
+    /// Position of the log line it was read from among the top-level instruction's
+    /// logs, `None` when decoded from instruction data.
+    pub log_index: Option<usize>,
+    pub action: Action,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedTransaction {
+    pub signature: Signature,
+    pub slot: u64,
+    /// Position of the transaction in its block.
+    pub index: u64,
+    pub fee_payer: Pubkey,
+    pub success: bool,
+    pub actions: Vec<DecodedAction>,
+    /// When the update was decoded, i.e. shortly after it arrived from the stream.
+    pub received_at: DateTime<Utc>,
+}
+
+impl DecodedTransaction {
+    /// Every swap of the transaction in the venue-independent shape, with slot/signature filled.
+    pub fn swaps(&self) -> Vec<SwapEvent> {
+        self.actions
+            .iter()
+            .filter_map(|decoded| {
+                let swap = match &decoded.action {
+                    Action::PumpFunTrade(event) => SwapEvent::from(event.clone()),
+                    Action::PumpAmmBuy {
+                        event,
+                        base_mint,
+                        quote_mint,
+                    } => SwapEvent::from(event.clone()).with_mints(*base_mint, *quote_mint),
+                    Action::PumpAmmSell {
+                        event,
+                        base_mint,
+                        quote_mint,
+                    } => SwapEvent::from(event.clone()).with_mints(*base_mint, *quote_mint),
+                    Action::RaydiumSwap(swap) => SwapEvent::from(swap.clone()),
+                    _ => return None,
+                };
+                Some(swap.with_context(self.slot, self.signature, decoded.instruction_index))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+impl DecodedTransaction {
+    /// A successful transaction with one top-level instruction per action.
+    pub(crate) fn with_actions(slot: u64, actions: Vec<Action>) -> Self {
+        Self {
+            signature: Signature::new_unique(),
+            slot,
+            index: 0,
+            fee_payer: Pubkey::default(),
+            success: true,
+            actions: actions
+                .into_iter()
+                .enumerate()
+                .map(|(instruction_index, action)| DecodedAction {
+                    program_id: Pubkey::default(),
+                    instruction_index,
+                    inner_index: None,
+                    log_index: None,
+                    action,
+                })
+                .collect(),
+            received_at: Utc::now(),
+        }
+    }
+}
+
+/// Transaction-wide data shared by every instruction.
+pub struct TransactionContext {
+    pub signature: Signature,
+    pub slot: u64,
+    pub account_keys: Vec<Pubkey>,
+    /// token account -> mint, from pre/post token balances.
+    pub token_mints: HashMap<Pubkey, Pubkey>,
+}
+
+pub struct InstructionView<'a> {
+    pub context: &'a TransactionContext,
+    pub program_id: Pubkey,
+    pub accounts: Vec<Pubkey>,
+    pub data: &'a [u8],
+    pub instruction_index: usize,
+    pub inner_index: Option<usize>,
+}
+
+impl<'a> InstructionView<'a> {
+    fn new(
+        context: &'a TransactionContext,
+        program_id_index: u32,
+        accounts: &[u8],
+        data: &'a [u8],
+        instruction_index: usize,
+        inner_index: Option<usize>,
+    ) -> Option<Self> {
+        Some(Self {
+            context,
+            program_id: *context.account_keys.get(program_id_index as usize)?,
+            accounts: accounts
+                .iter()
+                .map(|index| context.account_keys.get(*index as usize).copied())
+                .collect::<Option<Vec<_>>>()?,
+            data,
+            instruction_index,
+            inner_index,
+        })
+    }
+
+    pub fn account(&self, index: usize) -> Option<Pubkey> {
+        self.accounts.get(index).copied()
+    }
+}
+
+pub enum ProgramLog<'a> {
+    /// `Program data: <base64>`, already decoded.
+    Data(&'a [u8]),
+    /// `Program log: <message>`.
+    Message(&'a str),
+}
+
+pub trait Decoder: Send + Sync {
+    fn program_ids(&self) -> Vec<Pubkey>;
+
+    fn decode_instruction(&self, _ix: &InstructionView) -> Option<Action> {
+        None
+    }
+
+    /// `ix` is the instruction that was executing when the log was written,
+    /// `None` when logs can't be matched to instructions (e.g. truncated logs).
+    fn decode_log(&self, _ix: Option<&InstructionView>, _log: &ProgramLog) -> Option<Action> {
+        None
+    }
+}
+
+#[derive(Default)]
+pub struct DecoderRegistry {
+    decoders: HashMap<Pubkey, Vec<usize>>,
+    registered: Vec<Box<dyn Decoder>>,
+}
+
+impl DecoderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// pump.fun, pump AMM, Raydium AMM v4, SPL Token / Token-2022 and System.
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry
+            .register(PumpFunDecoder)
+            .register(PumpAmmDecoder)
+            .register(RaydiumDecoder)
+            .register(SplTokenDecoder)
+            .register(SystemDecoder);
+        registry
+    }
+
+    pub fn register<D: Decoder + 'static>(&mut self, decoder: D) -> &mut Self {
+        let index = self.registered.len();
+        for program_id in decoder.program_ids() {
+            self.decoders.entry(program_id).or_default().push(index);
+        }
+        self.registered.push(Box::new(decoder));
+        self
+    }
+
+    fn decoders_for(&self, program_id: &Pubkey) -> impl Iterator<Item = &dyn Decoder> {
+        self.decoders
+            .get(program_id)
+            .into_iter()
+            .flatten()
+            .map(|index| self.registered[*index].as_ref())
+    }
+
+    pub fn decode(&self, msg: &SubscribeUpdateTransaction) -> anyhow::Result<DecodedTransaction> {
+        let info = msg
+            .transaction
+            .as_ref()
+            .context("no transaction in the message")?;
+        let signature =
+            Signature::try_from(info.signature.as_slice()).context("invalid signature")?;
+        let message = info
+            .transaction
+            .as_ref()
+            .and_then(|tx| tx.message.as_ref())
+            .context("no message in the transaction")?;
+        let meta = info.meta.as_ref();
+
+        let mut account_keys = message.account_keys.iter().collect::<Vec<_>>();
+        if let Some(meta) = meta {
+            account_keys.extend(meta.loaded_writable_addresses.iter());
+            account_keys.extend(meta.loaded_readonly_addresses.iter());
+        }
+        let account_keys = account_keys
+            .into_iter()
+            .map(|key| Pubkey::try_from(key.as_slice()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| anyhow::anyhow!("invalid account key"))?;
+
+        let mut token_mints = HashMap::new();
+        if let Some(meta) = meta {
+            for balance in meta
+                .pre_token_balances
+                .iter()
+                .chain(&meta.post_token_balances)
+            {
+                if let (Some(account), Ok(mint)) = (
+                    account_keys.get(balance.account_index as usize),
+                    balance.mint.parse::<Pubkey>(),
+                ) {
+                    token_mints.insert(*account, mint);
+                }
+            }
+        }
+
+        let context = TransactionContext {
+            signature,
+            slot: msg.slot,
+            account_keys,
+            token_mints,
+        };
+
+        // (instruction index, inner index) -> instruction, in execution order
+        let mut instructions: HashMap<(usize, Option<usize>), InstructionView> = HashMap::new();
+        for (index, ix) in message.instructions.iter().enumerate() {
+            if let Some(ix) = InstructionView::new(
+                &context,
+                ix.program_id_index,
+                &ix.accounts,
+                &ix.data,
+                index,
+                None,
+            ) {
+                instructions.insert((index, None), ix);
+            }
+        }
+        for inner in meta.iter().flat_map(|meta| &meta.inner_instructions) {
+            let index = inner.index as usize;
+            for (position, ix) in inner.instructions.iter().enumerate() {
+                if let Some(ix) = InstructionView::new(
+                    &context,
+                    ix.program_id_index,
+                    &ix.accounts,
+                    &ix.data,
+                    index,
+                    Some(position),
+                ) {
+                    instructions.insert((index, Some(position)), ix);
+                }
+            }
+        }
+
+        // sort key: (instruction index, 0 for top-level / position + 1 for inner, 0 ix / 1 log)
+        let mut actions: Vec<((usize, usize, u8), DecodedAction)> = Vec::new();
+        let key =
+            |ix: usize, inner: Option<usize>, rank: u8| (ix, inner.map_or(0, |p| p + 1), rank);
+
+        for ix in instructions.values() {
+            for decoder in self.decoders_for(&ix.program_id) {
+                if let Some(action) = decoder.decode_instruction(ix) {
+                    actions.push((
+                        key(ix.instruction_index, ix.inner_index, 0),
+                        DecodedAction {
+                            program_id: ix.program_id,
+                            instruction_index: ix.instruction_index,
+                            inner_index: ix.inner_index,
+                            log_index: None,
+                            action,
+                        },
+                    ));
+                }
+            }
+        }
+
+        let logs = meta
+            .map(|meta| meta.log_messages.as_slice())
+            .unwrap_or_default();
+        for frame in walk_logs(logs) {
+            let ix = instructions.get(&(frame.instruction_index, frame.inner_index));
+            // logs are only trusted for the instruction when the program matches
+            let ix = ix.filter(|ix| ix.program_id == frame.program_id);
+            let data;
+            let log = match frame.line.strip_prefix(PROGRAM_DATA) {
+                Some(payload) => match general_purpose::STANDARD.decode(payload) {
+                    Ok(bytes) => {
+                        data = bytes;
+                        ProgramLog::Data(&data)
+                    }
+                    Err(_) => continue,
+                },
+                None => match frame.line.strip_prefix(PROGRAM_LOG) {
+                    Some(message) => ProgramLog::Message(message),
+                    None => continue,
+                },
+            };
+            for decoder in self.decoders_for(&frame.program_id) {
+                if let Some(action) = decoder.decode_log(ix, &log) {
+                    actions.push((
+                        key(frame.instruction_index, frame.inner_index, 1),
+                        DecodedAction {
+                            program_id: frame.program_id,
+                            instruction_index: frame.instruction_index,
+                            inner_index: frame.inner_index,
+                            log_index: Some(frame.log_index),
+                            action,
+                        },
+                    ));
+                }
+            }
+        }
+
+        actions.sort_by_key(|(key, _)| *key);
+
+        Ok(DecodedTransaction {
+            signature,
+            slot: msg.slot,
+            index: info.index,
+            fee_payer: context.account_keys.first().copied().unwrap_or_default(),
+            success: meta.is_some_and(|meta| meta.err.is_none()),
+            actions: actions.into_iter().map(|(_, action)| action).collect(),
+            received_at: Utc::now(),
+        })
+    }
+}
+
+struct LogFrame<'a> {
+    program_id: Pubkey,
+    instruction_index: usize,
+    inner_index: Option<usize>,
+    /// Position among the frames of the top-level instruction.
+    log_index: usize,
+    line: &'a str,
+}
+
+/// Attribute each log line to the program (and instruction) on top of the invoke stack.
+///
+/// Every `invoke [1]` starts the next top-level instruction, every deeper invoke is the
+/// next entry of that instruction's inner instruction list.
+fn walk_logs(logs: &[String]) -> Vec<LogFrame<'_>> {
+    let mut frames = Vec::new();
+    let mut stack: Vec<(Pubkey, Option<usize>)> = Vec::new();
+    let mut instruction_index: Option<usize> = None;
+    let mut inner_count = 0;
+    let mut log_count = 0;
+
+    for line in logs {
+        if let Some(rest) = line.strip_prefix("Program ") {
+            let mut parts = rest.split(' ');
+            if let (Some(program), Some("invoke"), Some(depth)) =
+                (parts.next(), parts.next(), parts.next())
+            {
+                let Ok(program_id) = program.parse::<Pubkey>() else {
+                    continue;
+                };
+                if depth == "[1]" {
+                    instruction_index = Some(instruction_index.map_or(0, |index| index + 1));
+                    inner_count = 0;
+                    log_count = 0;
+                    stack.clear();
+                    stack.push((program_id, None));
+                } else {
+                    stack.push((program_id, Some(inner_count)));
+                    inner_count += 1;
+                }
+                continue;
+            }
+            if rest.ends_with(" success") || rest.contains(" failed: ") {
+                stack.pop();
+                continue;
+            }
+        }
+
+        if let (Some(index), Some((program_id, inner_index))) = (instruction_index, stack.last()) {
+            frames.push(LogFrame {
+                program_id: *program_id,
+                instruction_index: index,
+                inner_index: *inner_index,
+                log_index: log_count,
+                line,
+            });
+            log_count += 1;
+        }
+    }
+
+    frames
+}
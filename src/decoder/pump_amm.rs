@@ -0,0 +1,46 @@
+use solana_sdk::pubkey::Pubkey;
+
+use super::{Action, Decoder, InstructionView, ProgramLog};
+use crate::types::constant::{PUMP_AMM_PROGRAM_ID, WSOL_MINT};
+use crate::types::pump_fun::{BuyEvent, CreatePoolEvent, EventTrait, SellEvent};
+
+//buy/sell accounts: pool, user, global_config, base_mint, quote_mint, ...
+const BASE_MINT_INDEX: usize = 3;
+const QUOTE_MINT_INDEX: usize = 4;
+
+pub struct PumpAmmDecoder;
+
+impl Decoder for PumpAmmDecoder {
+    fn program_ids(&self) -> Vec<Pubkey> {
+        vec![PUMP_AMM_PROGRAM_ID]
+    }
+
+    fn decode_log(&self, ix: Option<&InstructionView>, log: &ProgramLog) -> Option<Action> {
+        let ProgramLog::Data(data) = log else {
+            return None;
+        };
+
+        let base_mint = ix
+            .and_then(|ix| ix.account(BASE_MINT_INDEX))
+            .unwrap_or_default();
+        let quote_mint = ix
+            .and_then(|ix| ix.account(QUOTE_MINT_INDEX))
+            .unwrap_or(WSOL_MINT);
+
+        if let Some(event) = BuyEvent::decode(data) {
+            return Some(Action::PumpAmmBuy {
+                event,
+                base_mint,
+                quote_mint,
+            });
+        }
+        if let Some(event) = SellEvent::decode(data) {
+            return Some(Action::PumpAmmSell {
+                event,
+                base_mint,
+                quote_mint,
+            });
+        }
+        CreatePoolEvent::decode(data).map(Action::PumpAmmCreatePool)
+    }
+}
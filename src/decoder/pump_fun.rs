@@ -0,0 +1,27 @@
+use solana_sdk::pubkey::Pubkey;
+
+use super::{Action, Decoder, InstructionView, ProgramLog};
+use crate::types::constant::PUMP_FUN_PROGRAM_ID;
+use crate::types::pump_fun::{CompleteEvent, CreateEvent, EventTrait, TradeEvent};
+
+pub struct PumpFunDecoder;
+
+impl Decoder for PumpFunDecoder {
+    fn program_ids(&self) -> Vec<Pubkey> {
+        vec![PUMP_FUN_PROGRAM_ID]
+    }
+
+    fn decode_log(&self, _ix: Option<&InstructionView>, log: &ProgramLog) -> Option<Action> {
+        let ProgramLog::Data(data) = log else {
+            return None;
+        };
+
+        if let Some(event) = TradeEvent::decode(data) {
+            return Some(Action::PumpFunTrade(event));
+        }
+        if let Some(event) = CreateEvent::decode(data) {
+            return Some(Action::PumpFunCreate(event));
+        }
+        CompleteEvent::decode(data).map(Action::PumpFunComplete)
+    }
+}
@@ -0,0 +1,67 @@
+use base64::{Engine, engine::general_purpose};
+use borsh::BorshDeserialize;
+use solana_sdk::pubkey::Pubkey;
+
+use super::{Action, Decoder, InstructionView, ProgramLog};
+use crate::types::constant::RAYDIUM_AMM_V4_PROGRAM_ID;
+use crate::types::raydium::{
+    RAY_LOG, RaydiumSwap, RaydiumSwapLog, SWAP_BASE_IN_LOG_TYPE, SWAP_BASE_IN_TAG,
+    SWAP_BASE_OUT_LOG_TYPE, SWAP_BASE_OUT_TAG, SwapBaseInLog, SwapBaseOutLog,
+};
+
+const AMM_INDEX: usize = 1;
+
+pub struct RaydiumDecoder;
+
+impl RaydiumDecoder {
+    fn parse_ray_log(message: &str) -> Option<RaydiumSwapLog> {
+        let payload = message.strip_prefix(RAY_LOG)?;
+        let bytes = general_purpose::STANDARD.decode(payload).ok()?;
+        match *bytes.first()? {
+            SWAP_BASE_IN_LOG_TYPE => SwapBaseInLog::try_from_slice(&bytes)
+                .ok()
+                .map(RaydiumSwapLog::BaseIn),
+            SWAP_BASE_OUT_LOG_TYPE => SwapBaseOutLog::try_from_slice(&bytes)
+                .ok()
+                .map(RaydiumSwapLog::BaseOut),
+            _ => None,
+        }
+    }
+}
+
+impl Decoder for RaydiumDecoder {
+    fn program_ids(&self) -> Vec<Pubkey> {
+        vec![RAYDIUM_AMM_V4_PROGRAM_ID]
+    }
+
+    /// The swap amounts only exist in `ray_log`, the instruction gives the accounts.
+    fn decode_log(&self, ix: Option<&InstructionView>, log: &ProgramLog) -> Option<Action> {
+        let ProgramLog::Message(message) = log else {
+            return None;
+        };
+        let ix = ix?;
+        if !matches!(
+            ix.data.first(),
+            Some(&SWAP_BASE_IN_TAG | &SWAP_BASE_OUT_TAG)
+        ) {
+            return None;
+        }
+        let log = Self::parse_ray_log(message)?;
+
+        //17 accounts without amm target orders, 18 with it; user owner is always last
+        let len = ix.accounts.len();
+        let coin_vault_index = if len == 18 { 5 } else { 4 };
+        let vault_mint = |index: usize| {
+            ix.account(index)
+                .and_then(|vault| ix.context.token_mints.get(&vault).copied())
+        };
+
+        Some(Action::RaydiumSwap(RaydiumSwap {
+            amm: ix.account(AMM_INDEX)?,
+            user: ix.account(len.checked_sub(1)?)?,
+            coin_mint: vault_mint(coin_vault_index),
+            pc_mint: vault_mint(coin_vault_index + 1),
+            log,
+        }))
+    }
+}
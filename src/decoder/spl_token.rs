@@ -0,0 +1,58 @@
+use solana_sdk::pubkey::Pubkey;
+use spl_token::instruction::TokenInstruction;
+
+use super::{Action, Decoder, InstructionView};
+use crate::types::constant::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+
+/// Token-2022 shares the instruction layout for everything decoded here,
+/// extension instructions fail to unpack and are skipped.
+pub struct SplTokenDecoder;
+
+impl Decoder for SplTokenDecoder {
+    fn program_ids(&self) -> Vec<Pubkey> {
+        vec![TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID]
+    }
+
+    fn decode_instruction(&self, ix: &InstructionView) -> Option<Action> {
+        let action = match TokenInstruction::unpack(ix.data).ok()? {
+            TokenInstruction::Transfer { amount } => {
+                let source = ix.account(0)?;
+                let destination = ix.account(1)?;
+                Action::TokenTransfer {
+                    source,
+                    destination,
+                    authority: ix.account(2)?,
+                    mint: ix
+                        .context
+                        .token_mints
+                        .get(&source)
+                        .or_else(|| ix.context.token_mints.get(&destination))
+                        .copied(),
+                    amount,
+                }
+            }
+            TokenInstruction::TransferChecked { amount, .. } => Action::TokenTransfer {
+                source: ix.account(0)?,
+                destination: ix.account(2)?,
+                authority: ix.account(3)?,
+                mint: Some(ix.account(1)?),
+                amount,
+            },
+            TokenInstruction::MintTo { amount }
+            | TokenInstruction::MintToChecked { amount, .. } => Action::TokenMintTo {
+                mint: ix.account(0)?,
+                account: ix.account(1)?,
+                amount,
+            },
+            TokenInstruction::Burn { amount } | TokenInstruction::BurnChecked { amount, .. } => {
+                Action::TokenBurn {
+                    account: ix.account(0)?,
+                    mint: ix.account(1)?,
+                    amount,
+                }
+            }
+            _ => return None,
+        };
+        Some(action)
+    }
+}
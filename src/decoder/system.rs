@@ -0,0 +1,41 @@
+use solana_sdk::pubkey::Pubkey;
+
+use super::{Action, Decoder, InstructionView};
+use crate::types::constant::SYSTEM_PROGRAM_ID;
+
+//SystemInstruction is bincode encoded: u32 tag followed by the fields
+const CREATE_ACCOUNT_TAG: u32 = 0;
+const TRANSFER_TAG: u32 = 2;
+
+pub struct SystemDecoder;
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+impl Decoder for SystemDecoder {
+    fn program_ids(&self) -> Vec<Pubkey> {
+        vec![SYSTEM_PROGRAM_ID]
+    }
+
+    fn decode_instruction(&self, ix: &InstructionView) -> Option<Action> {
+        let tag = u32::from_le_bytes(ix.data.get(..4)?.try_into().ok()?);
+        match tag {
+            CREATE_ACCOUNT_TAG => Some(Action::SystemCreateAccount {
+                from: ix.account(0)?,
+                to: ix.account(1)?,
+                lamports: read_u64(ix.data, 4)?,
+                space: read_u64(ix.data, 12)?,
+                owner: Pubkey::try_from(ix.data.get(20..52)?).ok()?,
+            }),
+            TRANSFER_TAG => Some(Action::SystemTransfer {
+                from: ix.account(0)?,
+                to: ix.account(1)?,
+                lamports: read_u64(ix.data, 4)?,
+            }),
+            _ => None,
+        }
+    }
+}
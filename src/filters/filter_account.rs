@@ -1,22 +1,66 @@
+use solana_sdk::pubkey::Pubkey;
 use std::collections::HashMap;
 use yellowstone_grpc_proto::geyser::{
     CommitmentLevel, SubscribeRequest, SubscribeRequestFilterAccounts,
+    SubscribeRequestFilterAccountsFilter, SubscribeRequestFilterAccountsFilterMemcmp,
+    subscribe_request_filter_accounts_filter::Filter,
+    subscribe_request_filter_accounts_filter_memcmp::Data,
 };
 
+use crate::types::constant::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+
 pub fn new_filter_accounts(
     account: Option<Vec<String>>,
     owner: Option<Vec<String>>,
+    filters: Option<Vec<SubscribeRequestFilterAccountsFilter>>,
 ) -> SubscribeRequest {
     SubscribeRequest {
         accounts: HashMap::from([(
             "client".to_string(),
-            SubscribeRequestFilterAccounts {
-                account: account.unwrap_or_default(),
-                owner: owner.unwrap_or_default(),
-                ..Default::default()
-            },
+            accounts_filter(account, owner, filters),
         )]),
         commitment: Some(CommitmentLevel::Processed.into()),
         ..Default::default()
     }
 }
+
+/// Accounts in `account`, or owned by a program in `owner`, whose data matches every filter.
+pub fn accounts_filter(
+    account: Option<Vec<String>>,
+    owner: Option<Vec<String>>,
+    filters: Option<Vec<SubscribeRequestFilterAccountsFilter>>,
+) -> SubscribeRequestFilterAccounts {
+    SubscribeRequestFilterAccounts {
+        account: account.unwrap_or_default(),
+        owner: owner.unwrap_or_default(),
+        filters: filters.unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
+/// Data holding `bytes` at `offset`.
+pub fn memcmp(offset: u64, bytes: &[u8]) -> SubscribeRequestFilterAccountsFilter {
+    SubscribeRequestFilterAccountsFilter {
+        filter: Some(Filter::Memcmp(SubscribeRequestFilterAccountsFilterMemcmp {
+            offset,
+            data: Some(Data::Bytes(bytes.to_vec())),
+        })),
+    }
+}
+
+/// SPL Token / Token-2022 accounts of `mint`, which is the first 32 bytes of their data.
+pub fn token_accounts_filter(mint: &Pubkey) -> SubscribeRequestFilterAccounts {
+    accounts_filter(
+        None,
+        Some(vec![
+            TOKEN_PROGRAM_ID.to_string(),
+            TOKEN_2022_PROGRAM_ID.to_string(),
+        ]),
+        Some(vec![
+            memcmp(0, mint.as_ref()),
+            SubscribeRequestFilterAccountsFilter {
+                filter: Some(Filter::TokenAccountState(true)),
+            },
+        ]),
+    )
+}
@@ -16,7 +16,7 @@ pub fn new_filter_transactions(
                 vote: Some(false),
                 failed: Some(false),
                 signature: None,
-                account_include: account_include,
+                account_include,
                 account_exclude: account_exclude.unwrap_or_default(),
                 account_required: account_required.unwrap_or_default(),
             },
@@ -2,5 +2,5 @@ pub mod filter_account;
 pub mod filter_pump_fun;
 pub mod filter_transaction;
 
-pub use filter_account::new_filter_accounts;
+pub use filter_account::{accounts_filter, memcmp, new_filter_accounts, token_accounts_filter};
 pub use filter_transaction::new_filter_transactions;
@@ -0,0 +1,12 @@
+pub mod analytics;
+pub mod client;
+pub mod db;
+pub mod decoder;
+pub mod filters;
+pub mod notify;
+pub mod proxy;
+pub mod server;
+pub mod sinks;
+pub mod trading;
+pub mod types;
+pub mod utils;
@@ -1,31 +1,49 @@
-mod client;
-mod filters;
-mod types;
-mod utils;
-
 use anyhow::Context;
-use chrono::{Local, Utc};
+use chrono::Utc;
 use dotenvy::dotenv;
 use futures::{sink::SinkExt, stream::StreamExt};
-use log::info;
+use log::{debug, info, warn};
 use serde_json::json;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::env;
 use std::io::Write;
-use std::{collections::HashMap, env};
-use tokio::time::{Duration, interval};
+use std::sync::Arc;
+use tokio::sync::mpsc;
 
-use solana_sdk::pubkey::Pubkey;
 use yellowstone_grpc_proto::{
-    geyser::SlotStatus,
+    geyser::{CommitmentLevel, SlotStatus},
     prelude::{
-        CommitmentLevel, SubscribeRequest, SubscribeRequestFilterSlots, SubscribeRequestPing,
-        SubscribeUpdatePong, SubscribeUpdateSlot, subscribe_update::UpdateOneof,
+        SubscribeRequest, SubscribeRequestFilterSlots, SubscribeRequestPing, SubscribeUpdatePong,
+        subscribe_update::UpdateOneof,
     },
 };
 
-use client::connection::GrpcClient;
-use filters::{new_filter_accounts, new_filter_transactions};
-use types::pump_fun::{CreateEvent, EventTrait};
-use utils::format::{create_pretty_account, create_pretty_transaction};
+use yellowstone_grpc_demo::analytics::candles::{
+    self, CandleAggregator, DEFAULT_CANDLE_INTERVALS, DEFAULT_LATENESS_SECS,
+};
+use yellowstone_grpc_demo::analytics::compute_budget::PriorityFeeTracker;
+use yellowstone_grpc_demo::analytics::creators::{CreatorTracker, DEFAULT_CREATOR_DB};
+use yellowstone_grpc_demo::analytics::holders::{self, DEFAULT_HOLDER_THRESHOLDS, HolderTracker};
+use yellowstone_grpc_demo::analytics::jito::BundleTracker;
+use yellowstone_grpc_demo::analytics::lifecycle::{DEFAULT_MAX_IDLE_SLOTS, LifecycleTracker};
+use yellowstone_grpc_demo::analytics::metadata::{MetadataResolver, ResolverConfig};
+use yellowstone_grpc_demo::analytics::positions::{self, DEFAULT_POSITIONS_DB, PositionTracker};
+use yellowstone_grpc_demo::analytics::risk::{self, RiskTracker};
+use yellowstone_grpc_demo::analytics::snipers::{DEFAULT_SNIPER_WINDOW_SLOTS, SniperTracker};
+use yellowstone_grpc_demo::client::connection::GrpcClient;
+use yellowstone_grpc_demo::decoder::{Action, DecoderRegistry};
+use yellowstone_grpc_demo::filters::new_filter_transactions;
+use yellowstone_grpc_demo::proxy::{self, DEFAULT_PROXY_CLIENT_BUFFER};
+use yellowstone_grpc_demo::sinks::{DEFAULT_SINKS, EventKind, Record, SinkRouter};
+use yellowstone_grpc_demo::trading::copy::{
+    self, CopyConfig, CopyTrader, DEFAULT_COPY_SIZE, DEFAULT_SLIPPAGE_BPS,
+};
+use yellowstone_grpc_demo::trading::submit::{self, StatusWatcher, Submitter};
+use yellowstone_grpc_demo::types::constant::{PUMP_AMM_PROGRAM_ID, PUMP_FUN_PROGRAM_ID};
+use yellowstone_grpc_demo::utils::format::create_pretty_account;
+use yellowstone_grpc_demo::utils::summary::create_transaction_summary;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -48,14 +66,68 @@ async fn main() -> anyhow::Result<()> {
         })
         .init();
 
+    let positions_db = env::var("POSITIONS_DB").unwrap_or_else(|_| DEFAULT_POSITIONS_DB.into());
+    // command line query: positions [wallet]
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let mut send_sol = None;
+    match args.first().map(String::as_str) {
+        Some("positions") => {
+            return positions::print_positions(&positions_db, args.get(1).map(String::as_str));
+        }
+        // send SOL over the stream below, or by polling RPC with YELLOWSTONE_GRPC_URL empty,
+        // e.g. against solana-test-validator
+        Some("send-sol") => {
+            let (Some(to), Some(sol)) = (args.get(1), args.get(2)) else {
+                anyhow::bail!("usage: send-sol <to> <sol>");
+            };
+            let to: Pubkey = to.parse().context("invalid recipient")?;
+            let lamports = copy::parse_sol(sol)?;
+            if env::var("YELLOWSTONE_GRPC_URL")
+                .unwrap_or_default()
+                .is_empty()
+            {
+                let submitter = Submitter::from_env()?.context("KEYPAIR_PATH is not set")?;
+                let transfer = submit::transfer(&submitter.payer(), &to, lamports);
+                let submission = submitter.submit(&[transfer]).await?;
+                println!("{}", Record::submission(&submission).to_json());
+                return Ok(());
+            }
+            send_sol = Some((to, lamports));
+        }
+        Some(command) => anyhow::bail!(
+            "unknown command {command}, expected: positions [wallet] | send-sol <to> <sol>"
+        ),
+        None => {}
+    }
+
     let endpoint = env::var("YELLOWSTONE_GRPC_URL")?;
     let grpc = GrpcClient::new(endpoint, None);
     // let account_include = vec!["39H3DGBpHpffjTuwQDR9yv9AgbK4U4hesLdsVZ9yDDc9".to_string()];
-    let account_include = vec!["6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P".to_string()];
+    // pump AMM too, so candles and positions carry on after migration
+    let account_include = vec![
+        PUMP_FUN_PROGRAM_ID.to_string(),
+        PUMP_AMM_PROGRAM_ID.to_string(),
+    ];
     let mut client = grpc.build_client().await?;
 
-    let request = new_filter_transactions(account_include, None, None);
-    // let request = new_filter_accounts(Some(account_include), None);
+    // proxy mode: local clients share this one upstream subscription
+    if let Ok(addr) = env::var("PROXY_LISTEN_ADDR")
+        && send_sol.is_none()
+    {
+        let commitment = env::var("PROXY_COMMITMENT").unwrap_or_else(|_| "processed".into());
+        let commitment = CommitmentLevel::from_str_name(&commitment.to_uppercase())
+            .with_context(|| format!("invalid PROXY_COMMITMENT: {commitment}"))?;
+        return proxy::serve(
+            addr.parse()?,
+            client,
+            commitment,
+            DEFAULT_PROXY_CLIENT_BUFFER,
+        )
+        .await;
+    }
+
+    let mut request = new_filter_transactions(account_include, None, None);
+    // let request = new_filter_accounts(Some(account_include), None, None);
 
     //test slot filter
     // let request = SubscribeRequest {
@@ -70,70 +142,390 @@ async fn main() -> anyhow::Result<()> {
     //     ..Default::default()
     // };
 
-    let (mut subscribe_tx, mut stream) = client.subscribe_with_request(Some(request)).await?;
-
-    while let Some(message) = stream.next().await {
-        match message?.update_oneof.expect("invalid message") {
-            UpdateOneof::Account(msg) => {
-                let account = msg
-                    .account
-                    .ok_or(anyhow::anyhow!("no account in the message"))?;
-                let mut value = create_pretty_account(account)?;
-                value["isStartup"] = json!(msg.is_startup);
-                value["slot"] = json!(msg.slot);
-                info!(
-                    "Receive Account: {}",
-                    serde_json::to_string(&value).expect("json serialization failed")
-                );
+    let registry = DecoderRegistry::with_defaults();
+    let fee_window_slots = env::var("PRIORITY_FEE_WINDOW_SLOTS")
+        .ok()
+        .and_then(|slots| slots.parse().ok())
+        .unwrap_or(150);
+    let fee_tracker = PriorityFeeTracker::new(fee_window_slots, [PUMP_FUN_PROGRAM_ID]);
+    let mut bundle_tracker = BundleTracker::new(2);
+    let mut lifecycle = LifecycleTracker::new(DEFAULT_MAX_IDLE_SLOTS);
+    let mut candles = CandleAggregator::new(
+        candles::parse_intervals(
+            &env::var("CANDLE_INTERVALS").unwrap_or_else(|_| DEFAULT_CANDLE_INTERVALS.into()),
+        )?,
+        env::var("CANDLE_BUCKETING")
+            .unwrap_or_else(|_| "wall_clock".into())
+            .parse()?,
+        DEFAULT_LATENESS_SECS,
+    );
+    if let Ok(price) = env::var("SOL_USD_PRICE") {
+        candles.set_sol_usd(Some(price.parse().context("invalid SOL_USD_PRICE")?));
+    }
+    let mut creators = CreatorTracker::open(
+        env::var("CREATOR_DB").unwrap_or_else(|_| DEFAULT_CREATOR_DB.into()),
+        DEFAULT_MAX_IDLE_SLOTS,
+    )?;
+    let mut snipers = SniperTracker::new(
+        env::var("SNIPER_WINDOW_SLOTS")
+            .ok()
+            .and_then(|slots| slots.parse().ok())
+            .unwrap_or(DEFAULT_SNIPER_WINDOW_SLOTS),
+    );
+    let mut positions = match env::var("WATCH_WALLETS") {
+        Ok(wallets) => Some(PositionTracker::open(
+            &positions_db,
+            positions::parse_watchlist(&wallets)?,
+        )?),
+        Err(_) => None,
+    };
+    let mut copy_trader = match (env::var("COPY_WALLET"), env::var("WATCH_WALLETS")) {
+        (Ok(wallet), Ok(leaders)) => {
+            let config = CopyConfig {
+                wallet: wallet.parse().context("invalid COPY_WALLET")?,
+                size: env::var("COPY_SIZE")
+                    .unwrap_or_else(|_| DEFAULT_COPY_SIZE.into())
+                    .parse()?,
+                max_sol: match env::var("COPY_MAX_SOL") {
+                    Ok(sol) => Some(copy::parse_sol(&sol).context("invalid COPY_MAX_SOL")?),
+                    Err(_) => None,
+                },
+                slippage_bps: env::var("COPY_SLIPPAGE_BPS")
+                    .ok()
+                    .and_then(|bps| bps.parse().ok())
+                    .unwrap_or(DEFAULT_SLIPPAGE_BPS),
+            };
+            Some(CopyTrader::new(
+                config,
+                positions::parse_watchlist(&leaders)?,
+            ))
+        }
+        _ => None,
+    };
+    // token accounts of HOLDER_MINTS are subscribed by mint (offset 0), with RPC_URL the existing ones are loaded after subscribing
+    let mut holders = HolderTracker::new(holders::parse_thresholds(
+        &env::var("HOLDER_THRESHOLDS").unwrap_or_else(|_| DEFAULT_HOLDER_THRESHOLDS.into()),
+    )?);
+    if let Ok(mints) = env::var("HOLDER_MINTS") {
+        for mint in mints
+            .split(',')
+            .map(str::trim)
+            .filter(|mint| !mint.is_empty())
+        {
+            let mint = mint
+                .parse()
+                .with_context(|| format!("invalid holder mint {mint}"))?;
+            holders.track(mint);
+        }
+        request.accounts.extend(holders.filters());
+    }
+    // risk scores of new tokens, with RPC_URL the mint and metadata accounts are fetched in the background
+    let mut risk = RiskTracker::new(DEFAULT_MAX_IDLE_SLOTS);
+    let risk_rpc = env::var("RPC_URL").ok().map(|url| {
+        Arc::new(RpcClient::new_with_commitment(
+            url,
+            CommitmentConfig::processed(),
+        ))
+    });
+    // off-chain metadata is fetched in background tasks, results come back to the main loop over the resolved channel
+    let (resolved_tx, mut resolved) = mpsc::unbounded_channel();
+    let mut metadata = if env::var("RESOLVE_METADATA").is_ok_and(|resolve| resolve == "true") {
+        Some(MetadataResolver::new(
+            ResolverConfig::from_env()?,
+            resolved_tx,
+        )?)
+    } else {
+        None
+    };
+    let mut sinks = SinkRouter::from_spec(
+        &env::var("OUTPUT_SINKS").unwrap_or_else(|_| DEFAULT_SINKS.to_string()),
+    )?;
+    // every status of each slot, so the stored status advances to finalized
+    if sinks.stores_slots() {
+        request.slots.insert(
+            "client".to_string(),
+            SubscribeRequestFilterSlots {
+                filter_by_commitment: Some(false),
+                interslot_updates: Some(false),
+            },
+        );
+    }
+
+    // our transactions land through transactions_status filters added to this subscription,
+    // percentile priority fees come from the pump.fun transactions on it
+    let status_watcher = StatusWatcher::for_request(&request);
+    let submitter = match send_sol {
+        Some(_) => Some(Arc::new(
+            Submitter::from_env()?
+                .context("KEYPAIR_PATH is not set")?
+                .fee_tracker(fee_tracker.clone())
+                .status_watcher(status_watcher.clone()),
+        )),
+        None => None,
+    };
+    let (submitted_tx, mut submitted) = mpsc::unbounded_channel();
+
+    let (mut subscribe_tx, mut stream) =
+        client.subscribe_with_request(Some(request.clone())).await?;
+    let (fetched_tx, mut fetched) = mpsc::unbounded_channel::<Vec<(Pubkey, Vec<u8>, u64)>>();
+    // subscribe first, then load: updates during the load are buffered and applied over the snapshot, so nothing in between is missed
+    let (holders_tx, mut holders_loaded) = mpsc::unbounded_channel();
+    if let Ok(url) = env::var("RPC_URL")
+        && holders.mints().next().is_some()
+    {
+        holders.start_loading();
+        let mints = holders.mints().copied().collect::<Vec<_>>();
+        let rpc = RpcClient::new_with_commitment(url, CommitmentConfig::processed());
+        tokio::spawn(async move {
+            let mut snapshots = Vec::new();
+            for mint in mints {
+                snapshots.push((mint, holders::fetch_token_accounts(&rpc, &mint).await));
             }
-            UpdateOneof::Transaction(msg) => {
-                if let Some(meta) = msg.transaction.clone().and_then(|t| t.meta) {
-                    let logs = meta.log_messages;
-                    if let Some(create_event) = CreateEvent::parse_logs::<CreateEvent>(&logs) {
-                        println!("{:?}", create_event);
-                        // info!("detect creating token: {:?}", create_event);
-                    };
+            let _ = holders_tx.send(snapshots);
+        });
+    }
+
+    let shutdown = tokio::signal::ctrl_c();
+    tokio::pin!(shutdown);
+
+    // errors end the stream but still run the shutdown path below
+    let result = async {
+        loop {
+            let message = tokio::select! {
+                message = stream.next() => message,
+                Some(accounts) = fetched.recv() => {
+                    for (pubkey, data, slot) in accounts {
+                        if let Some(score) = risk.observe_account(&pubkey, &data, slot) {
+                            sinks.dispatch(Record::risk(&score));
+                        }
+                    }
+                    continue;
+                }
+                Some(snapshots) = holders_loaded.recv() => {
+                    for (mint, result) in snapshots {
+                        match result {
+                            Ok(accounts) => {
+                                info!("loaded {} token accounts of {mint}", accounts.len());
+                                holders.load(&mint, &accounts);
+                            }
+                            Err(error) => warn!("failed to load the token accounts of {mint}: {error}"),
+                        }
+                    }
+                    for alert in holders.finish_loading() {
+                        sinks.dispatch(Record::holder_concentration(&alert));
+                    }
+                    continue;
+                }
+                Some(resolved) = resolved.recv() => {
+                    sinks.dispatch(Record::metadata(&resolved));
+                    continue;
+                }
+                _ = status_watcher.changed() => {
+                    request.transactions_status = status_watcher.filters();
+                    subscribe_tx
+                        .send(request.clone())
+                        .await
+                        .context("failed to update the subscription")?;
+                    continue;
+                }
+                Some(submission) = submitted.recv() => {
+                    let submission = submission?;
+                    sinks.dispatch(Record::submission(&submission));
+                    println!("{}", Record::submission(&submission).to_json());
+                    break;
+                }
+                _ = &mut shutdown => {
+                    info!("shutting down");
+                    break;
                 }
+            };
+            let Some(message) = message else {
+                break;
+            };
+            match message?.update_oneof.expect("invalid message") {
+                UpdateOneof::Account(msg) => {
+                    let account = msg
+                        .account
+                        .ok_or(anyhow::anyhow!("no account in the message"))?;
+                    let pubkey = Pubkey::try_from(account.pubkey.as_slice())
+                        .map_err(|_| anyhow::anyhow!("invalid account pubkey"))?;
+                    if let Some(alert) =
+                        holders.observe_account(pubkey, &account.data, msg.slot, account.write_version)
+                    {
+                        sinks.dispatch(Record::holder_concentration(&alert));
+                    }
+                    if let Some(score) = risk.observe_account(&pubkey, &account.data, msg.slot) {
+                        sinks.dispatch(Record::risk(&score));
+                    }
+                    let mut value = create_pretty_account(account)?;
+                    value["isStartup"] = json!(msg.is_startup);
+                    value["slot"] = json!(msg.slot);
+                    sinks.dispatch(Record {
+                        kind: EventKind::Account,
+                        value,
+                    });
+                }
+                UpdateOneof::Transaction(msg) => {
+                    if let Some(tx) = &msg.transaction {
+                        match create_transaction_summary(tx, msg.slot) {
+                            Ok(summary) => {
+                                fee_tracker.record(&summary);
+                                debug!("{}", summary);
+                                sinks.dispatch(Record::transaction(&summary));
+                            }
+                            Err(error) => {
+                                warn!("failed to summarize transaction at slot {}: {error:#}", msg.slot);
+                            }
+                        }
+                    }
 
-                // let mut value = create_pretty_transaction(tx)?;
-                // value["slot"] = json!(msg.slot);
-                // info!(
-                //     "Receive transaction: {}",
-                //     serde_json::to_string(&value).expect("json serialization failed")
-                // );
-            }
-            UpdateOneof::Slot(msg) => {
-                let status =
-                    SlotStatus::try_from(msg.status).context("failed to decode commitment")?;
-                let value = json!({
-                    "slot": msg.slot,
-                    "parent": msg.parent,
-                    "status": status.as_str_name(),
-                    "deadError": msg.dead_error,
-                });
-                info!(
-                    "Receive Slot: {}",
-                    serde_json::to_string(&value).expect("json serialization failed")
-                );
-            }
-            UpdateOneof::Ping(_) => {
-                let _ = subscribe_tx
-                    .send(SubscribeRequest {
-                        ping: Some(SubscribeRequestPing { id: 1 }),
-                        ..Default::default()
-                    })
-                    .await;
-                // info!("service is ping: {:#?}", Local::now());
-                info!("service is ping");
-            }
-            UpdateOneof::Pong(SubscribeUpdatePong { id }) => {
-                info!("pong received id{id}")
+                    // send once the priority fee can be priced
+                    if let (Some((to, lamports)), Some(submitter)) = (send_sol, &submitter)
+                        && submitter.unit_price().is_ok()
+                    {
+                        send_sol = None;
+                        let (submitter, submitted_tx) = (submitter.clone(), submitted_tx.clone());
+                        tokio::spawn(async move {
+                            let transfer = submit::transfer(&submitter.payer(), &to, lamports);
+                            let _ = submitted_tx.send(submitter.submit(&[transfer]).await);
+                        });
+                    }
+
+                    let decoded = match registry.decode(&msg) {
+                        Ok(decoded) => decoded,
+                        Err(error) => {
+                            warn!("failed to decode transaction at slot {}: {error:#}", msg.slot);
+                            continue;
+                        }
+                    };
+                    holders.observe(&decoded);
+                    if let Some(metadata) = &mut metadata {
+                        for action in &decoded.actions {
+                            if let Action::PumpFunCreate(create) = &action.action {
+                                metadata.resolve(create.mint, &create.uri, decoded.slot);
+                            }
+                        }
+                    }
+                    for record in Record::actions(&decoded) {
+                        sinks.dispatch(record);
+                    }
+                    for launch in bundle_tracker.observe(&decoded) {
+                        sinks.dispatch(Record::launch(&launch));
+                    }
+                    for transition in lifecycle.observe(&decoded) {
+                        sinks.dispatch(Record::lifecycle(&transition));
+                    }
+                    for candle in candles.observe(&decoded) {
+                        sinks.dispatch(Record::candle(&candle));
+                    }
+                    for sell in creators.observe(&decoded) {
+                        sinks.dispatch(Record::dev_sell(&sell));
+                    }
+                    for report in snipers.observe(&decoded) {
+                        sinks.dispatch(Record::sniper_report(&report));
+                        if let Some(score) = risk.observe_sniper_report(&report) {
+                            sinks.dispatch(Record::risk(&score));
+                        }
+                    }
+                    for score in risk.observe(&decoded, &mut creators) {
+                        if score.previous_score.is_none()
+                            && let Some(rpc) = &risk_rpc
+                        {
+                            let (rpc, fetched_tx, mint) = (rpc.clone(), fetched_tx.clone(), score.mint);
+                            tokio::spawn(async move {
+                                match risk::fetch_accounts(&rpc, &mint).await {
+                                    Ok(accounts) => {
+                                        let _ = fetched_tx.send(accounts);
+                                    }
+                                    Err(error) => {
+                                        warn!("failed to fetch the accounts of {mint}: {error}")
+                                    }
+                                }
+                            });
+                        }
+                        sinks.dispatch(Record::risk(&score));
+                    }
+                    for update in positions
+                        .iter_mut()
+                        .flat_map(|tracker| tracker.observe(&decoded))
+                    {
+                        sinks.dispatch(Record::position(&update));
+                    }
+                    for signal in copy_trader
+                        .iter_mut()
+                        .flat_map(|trader| trader.observe(&decoded))
+                    {
+                        sinks.dispatch(Record::copy_signal(&signal));
+                    }
+
+                    // let mut value = create_pretty_transaction(tx)?;
+                    // value["slot"] = json!(msg.slot);
+                    // info!(
+                    //     "Receive transaction: {}",
+                    //     serde_json::to_string(&value).expect("json serialization failed")
+                    // );
+                }
+                UpdateOneof::TransactionStatus(msg) => {
+                    if let Err(error) = status_watcher.observe(&msg) {
+                        warn!("failed to read a transaction status at slot {}: {error:#}", msg.slot);
+                    }
+                }
+                UpdateOneof::Slot(msg) => {
+                    let status =
+                        SlotStatus::try_from(msg.status).context("failed to decode commitment")?;
+                    let value = json!({
+                        "slot": msg.slot,
+                        "parent": msg.parent,
+                        "status": status.as_str_name(),
+                        "deadError": msg.dead_error,
+                    });
+                    sinks.dispatch(Record {
+                        kind: EventKind::Slot,
+                        value,
+                    });
+                }
+                UpdateOneof::Ping(_) => {
+                    let _ = subscribe_tx
+                        .send(SubscribeRequest {
+                            ping: Some(SubscribeRequestPing { id: 1 }),
+                            ..Default::default()
+                        })
+                        .await;
+                    // info!("service is ping: {:#?}", Local::now());
+                    info!("service is ping");
+                    sinks.flush();
+                    if let Err(error) = creators.save() {
+                        warn!("failed to save the creator db: {error}");
+                    }
+                    if let Some(Err(error)) = positions.as_mut().map(PositionTracker::save) {
+                        warn!("failed to save the positions db: {error}");
+                    }
+                    if let Some(stats) = fee_tracker.stats(&PUMP_FUN_PROGRAM_ID) {
+                        info!("pump.fun priority fee: {:?}", stats);
+                    }
+                }
+                UpdateOneof::Pong(SubscribeUpdatePong { id }) => {
+                    info!("pong received id{id}")
+                }
+                msg => anyhow::bail!("receive unexpected message: {msg:?}"),
             }
-            msg => anyhow::bail!("receive unexpected message: {msg:?}"),
         }
+        Ok::<(), anyhow::Error>(())
+    }
+    .await;
+    for launch in bundle_tracker.flush() {
+        sinks.dispatch(Record::launch(&launch));
+    }
+    for candle in candles.flush() {
+        sinks.dispatch(Record::candle(&candle));
+    }
+    for report in snipers.flush() {
+        sinks.dispatch(Record::sniper_report(&report));
     }
+    // writes the remaining buffered rows and file footers
+    sinks.close();
 
-    Ok::<(), anyhow::Error>(())
+    result
 
     // let logs = vec![
     //     "Program ComputeBudget111111111111111111111111111111 invoke [1]".to_string(),
@@ -0,0 +1,749 @@
+use anyhow::Context;
+use log::warn;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::{
+    DEFAULT_MAX_ATTEMPTS, Delivery, DeliveryConfig, LAMPORTS_PER_SOL, Notifier, lamports, str_field,
+};
+use crate::sinks::{EventKind, Record, Sink};
+
+/// The events alerts are rendered for.
+pub const ALERTED: [EventKind; 5] = [
+    EventKind::Create,
+    EventKind::Complete,
+    EventKind::CreatePool,
+    EventKind::Trade,
+    EventKind::DevSell,
+];
+
+pub const DEFAULT_TELEGRAM_API_URL: &str = "https://api.telegram.org";
+/// Telegram allows about 30 messages a second per bot.
+pub const DEFAULT_ALERTS_PER_SECOND: f64 = 20.0;
+pub const DEFAULT_MAX_PER_MINUTE: usize = 20;
+/// Mints whose symbol is remembered for trade/complete alerts.
+const MAX_KNOWN_SYMBOLS: usize = 100_000;
+
+/// The alerts config file, e.g.
+/// `{"channels":[{"name":"launches","telegram":{"chatId":"-100123"},"events":["create","complete"]},
+///   {"name":"whales","discord":{"webhookUrl":"https://discord.com/api/webhooks/..."},"events":["trade"],"minSol":10}]}`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertConfig {
+    pub max_per_second: Option<f64>,
+    pub max_attempts: Option<u32>,
+    pub dead_letter: Option<PathBuf>,
+    pub channels: Vec<ChannelConfig>,
+}
+
+/// A channel takes the `events` it lists, trades only from `minSol` up, and at
+/// most `maxPerMinute` messages; alerts over the limit are dropped.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelConfig {
+    pub name: String,
+    pub telegram: Option<TelegramConfig>,
+    pub discord: Option<DiscordConfig>,
+    pub events: Vec<String>,
+    pub min_sol: Option<f64>,
+    pub max_per_minute: Option<usize>,
+}
+
+/// `botToken` falls back to `TELEGRAM_BOT_TOKEN`; `apiUrl` defaults to the Bot API.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TelegramConfig {
+    pub bot_token: Option<String>,
+    pub chat_id: String,
+    pub api_url: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscordConfig {
+    pub webhook_url: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Platform {
+    Telegram {
+        api_url: String,
+        bot_token: String,
+        chat_id: String,
+    },
+    Discord {
+        webhook_url: String,
+    },
+}
+
+/// A platform-neutral message; `telegram_text` and `discord_embed` render it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Alert {
+    pub title: String,
+    pub url: String,
+    /// Discord embed color.
+    pub color: u32,
+    /// `(name, value, link)`
+    pub fields: Vec<(&'static str, String, Option<String>)>,
+    /// `(label, url)`
+    pub links: Vec<(&'static str, String)>,
+}
+
+fn solscan_account(address: &str) -> String {
+    format!("https://solscan.io/account/{address}")
+}
+
+fn solscan_token(mint: &str) -> String {
+    format!("https://solscan.io/token/{mint}")
+}
+
+fn solscan_tx(signature: &str) -> String {
+    format!("https://solscan.io/tx/{signature}")
+}
+
+fn pump_fun(mint: &str) -> String {
+    format!("https://pump.fun/coin/{mint}")
+}
+
+fn sol(lamports: u64) -> String {
+    format!("{:.3} SOL", lamports as f64 / LAMPORTS_PER_SOL)
+}
+
+impl Alert {
+    /// `symbols` maps the mints seen created to their symbol, for the events
+    /// that don't carry it.
+    pub fn from_record(record: &Record, symbols: &HashMap<String, String>) -> Option<Self> {
+        let value = &record.value;
+        let mint = match record.kind {
+            EventKind::CreatePool => str_field(value, "baseMint"),
+            _ => str_field(value, "mint"),
+        };
+        let signature = str_field(value, "signature");
+        let token = symbols
+            .get(mint)
+            .map(|symbol| format!("${symbol}"))
+            .unwrap_or_else(|| mint.chars().take(8).collect());
+        let links = vec![
+            ("pump.fun", pump_fun(mint)),
+            ("Solscan", solscan_token(mint)),
+            ("Transaction", solscan_tx(signature)),
+        ];
+        let alert = match record.kind {
+            EventKind::Create => {
+                let creator = str_field(value, "creator");
+                Alert {
+                    title: format!(
+                        "New launch: {} (${})",
+                        str_field(value, "name"),
+                        str_field(value, "symbol")
+                    ),
+                    url: pump_fun(mint),
+                    color: 0x2ecc71,
+                    fields: vec![
+                        ("Mint", mint.to_string(), Some(solscan_token(mint))),
+                        (
+                            "Creator",
+                            creator.to_string(),
+                            Some(solscan_account(creator)),
+                        ),
+                        ("Metadata", str_field(value, "uri").to_string(), None),
+                    ],
+                    links,
+                }
+            }
+            EventKind::Complete => Alert {
+                title: format!("Bonding curve complete: {token}"),
+                url: pump_fun(mint),
+                color: 0xf1c40f,
+                fields: vec![
+                    ("Mint", mint.to_string(), Some(solscan_token(mint))),
+                    (
+                        "Bonding curve",
+                        str_field(value, "bondingCurve").to_string(),
+                        Some(solscan_account(str_field(value, "bondingCurve"))),
+                    ),
+                ],
+                links,
+            },
+            EventKind::CreatePool => {
+                let pool = str_field(value, "pool");
+                let mut links = links;
+                links.insert(2, ("Pool", solscan_account(pool)));
+                Alert {
+                    title: format!("Migrated to pump AMM: {token}"),
+                    url: solscan_account(pool),
+                    color: 0x9b59b6,
+                    fields: vec![
+                        ("Mint", mint.to_string(), Some(solscan_token(mint))),
+                        ("Pool", pool.to_string(), Some(solscan_account(pool))),
+                        (
+                            "Liquidity",
+                            sol(value
+                                .get("quoteAmountIn")
+                                .and_then(Value::as_u64)
+                                .unwrap_or_default()),
+                            None,
+                        ),
+                    ],
+                    links,
+                }
+            }
+            EventKind::Trade => {
+                let is_buy = value.get("isBuy").and_then(Value::as_bool)?;
+                let sol_amount = value.get("solAmount").and_then(Value::as_u64)?;
+                let user = str_field(value, "user");
+                Alert {
+                    title: format!(
+                        "{} {} of {token}",
+                        if is_buy { "Buy" } else { "Sell" },
+                        sol(sol_amount)
+                    ),
+                    url: pump_fun(mint),
+                    color: if is_buy { 0x3498db } else { 0xe74c3c },
+                    fields: vec![
+                        ("Mint", mint.to_string(), Some(solscan_token(mint))),
+                        ("Wallet", user.to_string(), Some(solscan_account(user))),
+                    ],
+                    links,
+                }
+            }
+            EventKind::DevSell => {
+                let sol_amount = value.get("solAmount").and_then(Value::as_u64)?;
+                let user = str_field(value, "user");
+                let count = |key: &str| value.get(key).and_then(Value::as_u64).unwrap_or_default();
+                Alert {
+                    title: format!("Dev sold {} of {token}", sol(sol_amount)),
+                    url: pump_fun(mint),
+                    color: 0xe67e22,
+                    fields: vec![
+                        ("Mint", mint.to_string(), Some(solscan_token(mint))),
+                        ("Dev", user.to_string(), Some(solscan_account(user))),
+                        (
+                            "Sold",
+                            format!(
+                                "{:.1}% of the dev's buys, {}s after launch",
+                                value
+                                    .get("soldShare")
+                                    .and_then(Value::as_f64)
+                                    .unwrap_or_default(),
+                                value
+                                    .get("secsSinceLaunch")
+                                    .and_then(Value::as_i64)
+                                    .unwrap_or_default()
+                            ),
+                            None,
+                        ),
+                        (
+                            "Creator record",
+                            format!(
+                                "{} launched, {} completed, sold into {}",
+                                count("creatorLaunched"),
+                                count("creatorCompleted"),
+                                count("creatorDevSold")
+                            ),
+                            None,
+                        ),
+                    ],
+                    links,
+                }
+            }
+            _ => return None,
+        };
+        Some(alert)
+    }
+
+    /// Bot API `sendMessage` text, `parse_mode` HTML.
+    pub fn telegram_text(&self) -> String {
+        let escape = |text: &str| {
+            text.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+        };
+        let mut text = format!(
+            "<b><a href=\"{}\">{}</a></b>\n",
+            escape(&self.url),
+            escape(&self.title)
+        );
+        for (name, value, link) in &self.fields {
+            let value = match link {
+                Some(link) => format!("<a href=\"{}\">{}</a>", escape(link), escape(value)),
+                None => format!("<code>{}</code>", escape(value)),
+            };
+            text.push_str(&format!("{name}: {value}\n"));
+        }
+        let links = self
+            .links
+            .iter()
+            .map(|(label, url)| format!("<a href=\"{}\">{label}</a>", escape(url)))
+            .collect::<Vec<_>>();
+        text.push_str(&links.join(" | "));
+        text
+    }
+
+    pub fn discord_embed(&self) -> Value {
+        let escape = |text: &str| {
+            text.chars()
+                .flat_map(|c| {
+                    let escaped = matches!(c, '*' | '_' | '~' | '`' | '|' | '[' | ']' | '\\');
+                    escaped.then_some('\\').into_iter().chain([c])
+                })
+                .collect::<String>()
+        };
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, value, link)| {
+                let value = match link {
+                    Some(link) => format!("[{}]({link})", escape(value)),
+                    None => escape(value),
+                };
+                json!({ "name": name, "value": value, "inline": false })
+            })
+            .collect::<Vec<_>>();
+        let links = self
+            .links
+            .iter()
+            .map(|(label, url)| format!("[{label}]({url})"))
+            .collect::<Vec<_>>();
+        json!({
+            "title": self.title,
+            "url": self.url,
+            "color": self.color,
+            "fields": fields,
+            "description": links.join(" | "),
+        })
+    }
+}
+
+pub struct Channel {
+    pub name: String,
+    pub platform: Platform,
+    pub events: HashSet<EventKind>,
+    pub min_lamports: u64,
+    pub max_per_minute: usize,
+    sent: VecDeque<Instant>,
+    throttled: u64,
+}
+
+impl TryFrom<ChannelConfig> for Channel {
+    type Error = anyhow::Error;
+
+    fn try_from(config: ChannelConfig) -> Result<Self, Self::Error> {
+        let platform = match (config.telegram, config.discord) {
+            (Some(telegram), None) => Platform::Telegram {
+                api_url: telegram
+                    .api_url
+                    .unwrap_or_else(|| DEFAULT_TELEGRAM_API_URL.to_string())
+                    .trim_end_matches('/')
+                    .to_string(),
+                bot_token: telegram
+                    .bot_token
+                    .or_else(|| env::var("TELEGRAM_BOT_TOKEN").ok())
+                    .with_context(|| format!("channel {}: no bot token", config.name))?,
+                chat_id: telegram.chat_id,
+            },
+            (None, Some(discord)) => Platform::Discord {
+                webhook_url: discord.webhook_url,
+            },
+            _ => anyhow::bail!(
+                "channel {}: set exactly one of telegram and discord",
+                config.name
+            ),
+        };
+        let events = config
+            .events
+            .iter()
+            .map(|event| event.parse())
+            .collect::<anyhow::Result<HashSet<EventKind>>>()?;
+        if let Some(event) = events.iter().find(|event| !ALERTED.contains(event)) {
+            anyhow::bail!("channel {}: no alerts for {}", config.name, event.as_str());
+        }
+        Ok(Self {
+            name: config.name,
+            platform,
+            events,
+            min_lamports: lamports(config.min_sol),
+            max_per_minute: config.max_per_minute.unwrap_or(DEFAULT_MAX_PER_MINUTE),
+            sent: VecDeque::new(),
+            throttled: 0,
+        })
+    }
+}
+
+impl Channel {
+    pub fn accepts(&self, record: &Record) -> bool {
+        self.events.contains(&record.kind)
+            && (!matches!(record.kind, EventKind::Trade | EventKind::DevSell)
+                || record
+                    .value
+                    .get("solAmount")
+                    .and_then(Value::as_u64)
+                    .unwrap_or_default()
+                    >= self.min_lamports)
+    }
+
+    /// Takes a slot in the last minute's budget, if one is free.
+    fn take_slot(&mut self, now: Instant) -> bool {
+        while self
+            .sent
+            .front()
+            .is_some_and(|sent| now.duration_since(*sent) >= Duration::from_secs(60))
+        {
+            self.sent.pop_front();
+        }
+        if self.sent.len() >= self.max_per_minute {
+            self.throttled += 1;
+            if self.throttled.is_power_of_two() {
+                warn!(
+                    "channel {} throttled, {} alerts dropped",
+                    self.name, self.throttled
+                );
+            }
+            return false;
+        }
+        self.sent.push_back(now);
+        true
+    }
+
+    fn delivery(&self, id: String, alert: &Alert) -> Delivery {
+        match &self.platform {
+            Platform::Telegram {
+                api_url,
+                bot_token,
+                chat_id,
+            } => Delivery {
+                url: format!("{api_url}/bot{bot_token}/sendMessage"),
+                target: format!("telegram:{chat_id}"),
+                id,
+                headers: vec![],
+                body: json!({
+                    "chat_id": chat_id,
+                    "text": alert.telegram_text(),
+                    "parse_mode": "HTML",
+                    "disable_web_page_preview": true,
+                })
+                .to_string(),
+            },
+            Platform::Discord { webhook_url } => Delivery {
+                url: webhook_url.clone(),
+                target: format!("discord:{}", self.name),
+                id,
+                headers: vec![],
+                body: json!({ "embeds": [alert.discord_embed()] }).to_string(),
+            },
+        }
+    }
+}
+
+/// Posts launch, curve-complete, migration and trade alerts to Telegram chats and Discord
+/// webhooks through the same delivery queue as the webhooks.
+pub struct AlertSink {
+    channels: Vec<Channel>,
+    notifier: Notifier,
+    symbols: HashMap<String, String>,
+}
+
+impl AlertSink {
+    pub fn new(channels: Vec<Channel>, delivery: DeliveryConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            channels,
+            notifier: Notifier::new(delivery)?,
+            symbols: HashMap::new(),
+        })
+    }
+
+    pub fn from_config(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let config: AlertConfig = serde_json::from_slice(
+            &std::fs::read(path)
+                .with_context(|| format!("failed to read alerts config {}", path.display()))?,
+        )
+        .with_context(|| format!("invalid alerts config {}", path.display()))?;
+        let channels = config
+            .channels
+            .into_iter()
+            .map(Channel::try_from)
+            .collect::<anyhow::Result<_>>()?;
+        let delivery = DeliveryConfig {
+            max_attempts: config.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS).max(1),
+            max_per_second: config.max_per_second.unwrap_or(DEFAULT_ALERTS_PER_SECOND),
+            dead_letter: config.dead_letter,
+        };
+        Self::new(channels, delivery)
+    }
+}
+
+impl Sink for AlertSink {
+    fn write(&mut self, record: &Record) -> anyhow::Result<()> {
+        if record.kind == EventKind::Create {
+            if self.symbols.len() >= MAX_KNOWN_SYMBOLS {
+                self.symbols.clear();
+            }
+            self.symbols.insert(
+                str_field(&record.value, "mint").to_string(),
+                str_field(&record.value, "symbol").to_string(),
+            );
+        }
+        let mut alert = None;
+        let now = Instant::now();
+        for channel in &mut self.channels {
+            if !channel.accepts(record) || !channel.take_slot(now) {
+                continue;
+            }
+            let Some(alert) = alert
+                .get_or_insert_with(|| Alert::from_record(record, &self.symbols))
+                .as_ref()
+            else {
+                return Ok(());
+            };
+            let id = format!(
+                "{}:{}:{}",
+                str_field(&record.value, "signature"),
+                record.value["eventIndex"],
+                channel.name
+            );
+            // one failing channel doesn't stop the others
+            if let Err(error) = self.notifier.send(channel.delivery(id, alert)) {
+                warn!(
+                    "failed to queue alert for channel {}: {error:#}",
+                    channel.name
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits for the alerts still being retried.
+    fn close(&mut self) -> anyhow::Result<()> {
+        self.notifier.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::extract::{Path as UrlPath, State};
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use std::sync::{Arc, Mutex};
+
+    fn channel(config: Value) -> Channel {
+        Channel::try_from(serde_json::from_value::<ChannelConfig>(config).unwrap()).unwrap()
+    }
+
+    fn trade(sol: u64) -> Record {
+        Record::from_json(json!({
+            "type": "trade",
+            "signature": "trade-sig",
+            "eventIndex": 20_004,
+            "mint": "Mint1111",
+            "user": "User1111",
+            "isBuy": false,
+            "solAmount": sol * 1_000_000_000,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn renders_escaped_telegram_and_discord_messages() {
+        let create = Record::from_json(json!({
+            "type": "create",
+            "signature": "create-sig",
+            "mint": "Mint1111",
+            "name": "<b>Dog</b> & *co*",
+            "symbol": "DOG",
+            "creator": "Creator1",
+            "uri": "https://example.com/0.json",
+        }))
+        .unwrap();
+        let alert = Alert::from_record(&create, &HashMap::new()).unwrap();
+        let text = alert.telegram_text();
+        assert!(text.starts_with(
+            "<b><a href=\"https://pump.fun/coin/Mint1111\">New launch: &lt;b&gt;Dog&lt;/b&gt; &amp; *co* ($DOG)</a></b>"
+        ));
+        assert!(text.contains("Metadata: <code>https://example.com/0.json</code>"));
+        let embed = alert.discord_embed();
+        assert_eq!(embed["title"], "New launch: <b>Dog</b> & *co* ($DOG)");
+        assert_eq!(
+            embed["fields"][1]["value"],
+            "[Creator1](https://solscan.io/account/Creator1)"
+        );
+
+        let symbols = HashMap::from([("Mint1111".to_string(), "DOG".to_string())]);
+        let alert = Alert::from_record(&trade(12), &symbols).unwrap();
+        assert_eq!(alert.title, "Sell 12.000 SOL of $DOG");
+
+        let create_pool = Record::from_json(json!({
+            "type": "create_pool",
+            "signature": "pool-sig",
+            "baseMint": "Mint1111",
+            "pool": "Pool1111",
+            "quoteAmountIn": 84_990_359_679u64,
+        }))
+        .unwrap();
+        let alert = Alert::from_record(&create_pool, &symbols).unwrap();
+        assert_eq!(alert.title, "Migrated to pump AMM: $DOG");
+        assert_eq!(alert.url, "https://solscan.io/account/Pool1111");
+        assert_eq!(
+            alert.fields[2],
+            ("Liquidity", "84.990 SOL".to_string(), None)
+        );
+        assert_eq!(
+            alert.links,
+            vec![
+                ("pump.fun", "https://pump.fun/coin/Mint1111".to_string()),
+                ("Solscan", "https://solscan.io/token/Mint1111".to_string()),
+                ("Pool", "https://solscan.io/account/Pool1111".to_string()),
+                ("Transaction", "https://solscan.io/tx/pool-sig".to_string()),
+            ]
+        );
+        assert!(
+            channel(json!({
+                "name": "migrations",
+                "discord": {"webhookUrl": "http://127.0.0.1/hook"},
+                "events": ["complete", "create_pool"],
+            }))
+            .accepts(&create_pool)
+        );
+        assert!(Alert::from_record(&trade(1), &HashMap::new()).is_some());
+    }
+
+    #[test]
+    fn channels_filter_and_throttle() {
+        let mut whales = channel(json!({
+            "name": "whales",
+            "discord": {"webhookUrl": "http://127.0.0.1/hook"},
+            "events": ["trade"],
+            "minSol": 10,
+            "maxPerMinute": 2,
+        }));
+        assert!(whales.accepts(&trade(10)));
+        assert!(!whales.accepts(&trade(9)));
+        let now = Instant::now();
+        assert!(whales.take_slot(now));
+        assert!(whales.take_slot(now));
+        assert!(!whales.take_slot(now));
+        assert!(whales.take_slot(now + Duration::from_secs(60)));
+
+        assert!(
+            Channel::try_from(
+                serde_json::from_value::<ChannelConfig>(json!({
+                    "name": "slots",
+                    "discord": {"webhookUrl": "http://127.0.0.1/hook"},
+                    "events": ["slot"],
+                }))
+                .unwrap()
+            )
+            .is_err()
+        );
+    }
+
+    #[derive(Clone, Default)]
+    struct Received {
+        telegram: Arc<Mutex<Vec<(String, Value)>>>,
+        discord: Arc<Mutex<Vec<Value>>>,
+    }
+
+    /// Rejects the first bot's messages, e.g. a revoked token.
+    async fn telegram(
+        State(received): State<Received>,
+        UrlPath(bot): UrlPath<String>,
+        body: String,
+    ) -> StatusCode {
+        received
+            .telegram
+            .lock()
+            .unwrap()
+            .push((bot.clone(), serde_json::from_str(&body).unwrap()));
+        if bot == "botrevoked" {
+            StatusCode::UNAUTHORIZED
+        } else {
+            StatusCode::OK
+        }
+    }
+
+    async fn discord(State(received): State<Received>, body: String) -> StatusCode {
+        received
+            .discord
+            .lock()
+            .unwrap()
+            .push(serde_json::from_str(&body).unwrap());
+        StatusCode::NO_CONTENT
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn delivers_to_every_channel_without_leaking_tokens() {
+        let received = Received::default();
+        let app = Router::new()
+            .route("/{bot}/sendMessage", post(telegram))
+            .route("/discord/hook", post(discord))
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let dead_letter =
+            env::temp_dir().join(format!("alerts-dead-{}.ndjson", std::process::id()));
+        let _ = std::fs::remove_file(&dead_letter);
+        let channels = vec![
+            channel(json!({
+                "name": "revoked",
+                "telegram": {"botToken": "revoked", "chatId": "-1", "apiUrl": base},
+                "events": ["trade"],
+            })),
+            channel(json!({
+                "name": "group",
+                "telegram": {"botToken": "123:abc", "chatId": "-100", "apiUrl": base},
+                "events": ["trade"],
+                "minSol": 5,
+            })),
+            channel(json!({
+                "name": "whales",
+                "discord": {"webhookUrl": format!("{base}/discord/hook")},
+                "events": ["trade"],
+                "minSol": 10,
+            })),
+        ];
+        let delivery = DeliveryConfig {
+            max_attempts: 2,
+            max_per_second: 100.0,
+            dead_letter: Some(dead_letter.clone()),
+        };
+        let mut sink = AlertSink::new(channels, delivery).unwrap();
+        sink.write(&trade(6)).unwrap();
+        sink.write(&trade(12)).unwrap();
+        tokio::task::spawn_blocking(move || sink.close())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let telegram = received.telegram.lock().unwrap();
+        let bots = |name: &str| telegram.iter().filter(|(bot, _)| bot == name).count();
+        assert_eq!(bots("botrevoked"), 2);
+        assert_eq!(bots("bot123:abc"), 2);
+        let (_, message) = telegram
+            .iter()
+            .find(|(bot, _)| bot == "bot123:abc")
+            .unwrap();
+        assert_eq!(message["chat_id"], "-100");
+        assert_eq!(message["parse_mode"], "HTML");
+        let discord = received.discord.lock().unwrap();
+        assert_eq!(discord.len(), 1);
+        assert_eq!(
+            discord[0]["embeds"][0]["title"],
+            "Sell 12.000 SOL of Mint1111"
+        );
+
+        let dead = std::fs::read_to_string(&dead_letter).unwrap();
+        let _ = std::fs::remove_file(&dead_letter);
+        assert_eq!(dead.lines().count(), 2);
+        assert!(dead.contains("\"target\":\"telegram:-1\""));
+        assert!(!dead.contains("botrevoked"));
+    }
+}
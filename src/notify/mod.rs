@@ -0,0 +1,296 @@
+pub mod alert;
+pub mod webhook;
+
+use log::{info, warn};
+use serde_json::json;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, Receiver, Sender, error::TrySendError};
+use tokio::sync::{Mutex, Semaphore};
+use tokio::task::JoinSet;
+
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+pub const DEFAULT_MAX_PER_SECOND: f64 = 5.0;
+const QUEUE_CAPACITY: usize = 10_000;
+const MAX_IN_FLIGHT: usize = 16;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+
+fn lamports(sol: Option<f64>) -> u64 {
+    (sol.unwrap_or_default().max(0.0) * LAMPORTS_PER_SOL) as u64
+}
+
+fn str_field<'a>(value: &'a serde_json::Value, key: &str) -> &'a str {
+    value
+        .get(key)
+        .and_then(serde_json::Value::as_str)
+        .unwrap_or_default()
+}
+
+/// `url` without credentials, query or fragment, for `Delivery::target`.
+fn redact(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut url) => {
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            url.set_query(None);
+            url.set_fragment(None);
+            url.into()
+        }
+        Err(_) => "<invalid url>".to_string(),
+    }
+}
+
+/// One HTTP POST to make.
+#[derive(Clone, Debug)]
+pub struct Delivery {
+    pub url: String,
+    /// Where the delivery goes, for logs; unlike `url` never holds credentials.
+    pub target: String,
+    /// Names the delivery in logs and the dead-letter file.
+    pub id: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct DeliveryConfig {
+    pub max_attempts: u32,
+    /// Requests per second across every URL, retries included.
+    pub max_per_second: f64,
+    /// Deliveries that failed every attempt are appended here as NDJSON.
+    pub dead_letter: Option<PathBuf>,
+}
+
+impl Default for DeliveryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            max_per_second: DEFAULT_MAX_PER_SECOND,
+            dead_letter: None,
+        }
+    }
+}
+
+/// Token bucket holding up to one second of requests.
+struct RateLimiter {
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl RateLimiter {
+    fn new(rate: f64) -> Self {
+        let rate = rate.max(0.01);
+        Self {
+            rate,
+            tokens: rate.max(1.0),
+            last: Instant::now(),
+        }
+    }
+
+    async fn acquire(&mut self) {
+        loop {
+            let now = Instant::now();
+            let refill = now.duration_since(self.last).as_secs_f64() * self.rate;
+            self.tokens = (self.tokens + refill).min(self.rate.max(1.0));
+            self.last = now;
+            if self.tokens >= 1.0 {
+                self.tokens -= 1.0;
+                return;
+            }
+            tokio::time::sleep(Duration::from_secs_f64((1.0 - self.tokens) / self.rate)).await;
+        }
+    }
+}
+
+enum Failure {
+    /// Worth another attempt, after at least the given delay.
+    Retry(String, Option<Duration>),
+    /// The receiver rejected the request; retrying won't help.
+    Rejected(String),
+}
+
+async fn post(client: &reqwest::Client, delivery: &Delivery) -> Result<(), Failure> {
+    let mut request = client
+        .post(&delivery.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(delivery.body.clone());
+    for (name, value) in &delivery.headers {
+        request = request.header(name, value);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|error| Failure::Retry(error.without_url().to_string(), None))?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let error = format!("HTTP {status}");
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+            .map(Duration::from_secs_f64);
+        Err(Failure::Retry(error, retry_after))
+    } else if status.is_server_error() || status == reqwest::StatusCode::REQUEST_TIMEOUT {
+        Err(Failure::Retry(error, None))
+    } else {
+        Err(Failure::Rejected(error))
+    }
+}
+
+struct Worker {
+    client: reqwest::Client,
+    config: DeliveryConfig,
+    limiter: Mutex<RateLimiter>,
+}
+
+impl Worker {
+    async fn deliver(&self, delivery: Delivery) {
+        let mut backoff = MIN_BACKOFF;
+        let mut attempts = 0;
+        let error = loop {
+            attempts += 1;
+            self.limiter.lock().await.acquire().await;
+            let delay = match post(&self.client, &delivery).await {
+                Ok(()) => return,
+                Err(Failure::Rejected(error)) => break error,
+                Err(Failure::Retry(error, _)) if attempts >= self.config.max_attempts => {
+                    break error;
+                }
+                Err(Failure::Retry(error, retry_after)) => {
+                    warn!(
+                        "delivery {} to {} failed ({error}), attempt {attempts}",
+                        delivery.id, delivery.target
+                    );
+                    retry_after.unwrap_or_default().max(backoff)
+                }
+            };
+            tokio::time::sleep(delay).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        };
+        warn!(
+            "delivery {} to {} dead after {attempts} attempts: {error}",
+            delivery.id, delivery.target
+        );
+        if let Err(write_error) = self.dead_letter(&delivery, attempts, &error) {
+            warn!("failed to write dead letter {}: {write_error}", delivery.id);
+        }
+    }
+
+    fn dead_letter(&self, delivery: &Delivery, attempts: u32, error: &str) -> anyhow::Result<()> {
+        let Some(path) = &self.config.dead_letter else {
+            return Ok(());
+        };
+        let line = json!({
+            "id": delivery.id,
+            "target": delivery.target,
+            "attempts": attempts,
+            "error": error,
+            "failedAt": chrono::Utc::now().to_rfc3339(),
+            "body": delivery.body,
+        });
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(format!("{line}\n").as_bytes())?;
+        Ok(())
+    }
+
+    async fn run(self, mut rx: Receiver<Delivery>) {
+        let worker = Arc::new(self);
+        let permits = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
+        let mut tasks = JoinSet::new();
+        while let Some(delivery) = rx.recv().await {
+            let Ok(permit) = permits.clone().acquire_owned().await else {
+                break;
+            };
+            let worker = worker.clone();
+            tasks.spawn(async move {
+                worker.deliver(delivery).await;
+                drop(permit);
+            });
+            while tasks.try_join_next().is_some() {}
+        }
+        if !tasks.is_empty() {
+            info!("waiting for {} pending deliveries", tasks.len());
+        }
+        while tasks.join_next().await.is_some() {}
+    }
+}
+
+/// Delivers on a background thread so a slow or unreachable receiver never
+/// blocks the stream loop.
+pub struct Notifier {
+    tx: Option<Sender<Delivery>>,
+    worker: Option<JoinHandle<()>>,
+    dropped: u64,
+}
+
+impl Notifier {
+    pub fn new(config: DeliveryConfig) -> anyhow::Result<Self> {
+        let worker = Worker {
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()?,
+            limiter: Mutex::new(RateLimiter::new(config.max_per_second)),
+            config,
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        let worker = thread::spawn(move || runtime.block_on(worker.run(rx)));
+        Ok(Self {
+            tx: Some(tx),
+            worker: Some(worker),
+            dropped: 0,
+        })
+    }
+
+    pub fn send(&mut self, delivery: Delivery) -> anyhow::Result<()> {
+        let Some(tx) = &self.tx else {
+            anyhow::bail!("notifier closed");
+        };
+        match tx.try_send(delivery) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                if self.dropped.is_power_of_two() {
+                    warn!("notifier queue full, {} deliveries dropped", self.dropped);
+                }
+                Ok(())
+            }
+            Err(TrySendError::Closed(_)) => anyhow::bail!("notifier stopped"),
+        }
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Waits for the queued deliveries, retries included.
+    pub fn close(&mut self) -> anyhow::Result<()> {
+        self.tx.take();
+        if let Some(worker) = self.worker.take() {
+            worker
+                .join()
+                .map_err(|_| anyhow::anyhow!("notifier panicked"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Notifier {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
@@ -0,0 +1,463 @@
+use anyhow::Context;
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sha2::Sha256;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::env;
+use std::path::{Path, PathBuf};
+
+use super::{
+    DEFAULT_MAX_ATTEMPTS, DEFAULT_MAX_PER_SECOND, Delivery, DeliveryConfig, Notifier, lamports,
+    redact, str_field,
+};
+use crate::sinks::{EventKind, Record, Sink};
+
+/// The events the webhook sink evaluates rules on.
+pub const WATCHED: [EventKind; 2] = [EventKind::Create, EventKind::Trade];
+
+/// `X-Webhook-Signature` is `sha256=<hex>`, the HMAC-SHA256 of
+/// `<X-Webhook-Timestamp>.<body>` keyed with the secret.
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+
+/// The webhook config file, e.g.
+/// `{"secret":"...","maxPerSecond":5,"maxAttempts":5,"deadLetter":"./webhook-dead.ndjson",
+///   "rules":[{"name":"whale","event":"trade","url":"http://127.0.0.1:9000/hook","minSol":10}]}`.
+/// `WEBHOOK_SECRET` overrides `secret`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookConfig {
+    pub secret: Option<String>,
+    pub max_per_second: Option<f64>,
+    pub max_attempts: Option<u32>,
+    pub dead_letter: Option<PathBuf>,
+    pub rules: Vec<RuleConfig>,
+}
+
+/// Every criterion that is set has to match. `creators`, `nameRegex`,
+/// `symbolRegex` and `minInitialBuySol` apply to `create`, `minSol` to `trade`.
+/// `trade` rules must set `minSol`, `0` to post every trade.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleConfig {
+    pub name: String,
+    pub url: String,
+    pub event: String,
+    #[serde(default)]
+    pub creators: Vec<String>,
+    pub name_regex: Option<String>,
+    pub symbol_regex: Option<String>,
+    pub min_initial_buy_sol: Option<f64>,
+    pub min_sol: Option<f64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Rule {
+    pub name: String,
+    pub url: String,
+    pub event: EventKind,
+    pub creators: HashSet<Pubkey>,
+    pub name_regex: Option<Regex>,
+    pub symbol_regex: Option<Regex>,
+    pub min_initial_buy: u64,
+    pub min_lamports: u64,
+}
+
+impl TryFrom<RuleConfig> for Rule {
+    type Error = anyhow::Error;
+
+    fn try_from(config: RuleConfig) -> Result<Self, Self::Error> {
+        let event: EventKind = config.event.parse()?;
+        if !WATCHED.contains(&event) {
+            anyhow::bail!(
+                "rule {}: only create and trade rules are supported",
+                config.name
+            );
+        }
+        if event == EventKind::Trade && config.min_sol.is_none() {
+            anyhow::bail!("rule {}: trade rules need minSol", config.name);
+        }
+        let regex = |pattern: &Option<String>| {
+            pattern
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .with_context(|| format!("rule {}: invalid regex", config.name))
+        };
+        Ok(Self {
+            creators: config
+                .creators
+                .iter()
+                .map(|creator| {
+                    creator
+                        .parse()
+                        .with_context(|| format!("rule {}: invalid creator {creator}", config.name))
+                })
+                .collect::<anyhow::Result<_>>()?,
+            name_regex: regex(&config.name_regex)?,
+            symbol_regex: regex(&config.symbol_regex)?,
+            min_initial_buy: lamports(config.min_initial_buy_sol),
+            min_lamports: lamports(config.min_sol),
+            name: config.name,
+            url: config.url,
+            event,
+        })
+    }
+}
+
+impl Rule {
+    /// `initial_buy` is what the creator bought in the launch transaction, lamports.
+    pub fn matches(&self, record: &Record, initial_buy: u64) -> bool {
+        if record.kind != self.event {
+            return false;
+        }
+        let value = &record.value;
+        match record.kind {
+            EventKind::Create => {
+                let creator = str_field(value, "creator").parse::<Pubkey>();
+                (self.creators.is_empty()
+                    || creator.is_ok_and(|creator| self.creators.contains(&creator)))
+                    && self
+                        .name_regex
+                        .as_ref()
+                        .is_none_or(|regex| regex.is_match(str_field(value, "name")))
+                    && self
+                        .symbol_regex
+                        .as_ref()
+                        .is_none_or(|regex| regex.is_match(str_field(value, "symbol")))
+                    && initial_buy >= self.min_initial_buy
+            }
+            EventKind::Trade => {
+                value
+                    .get("solAmount")
+                    .and_then(Value::as_u64)
+                    .unwrap_or_default()
+                    >= self.min_lamports
+            }
+            _ => false,
+        }
+    }
+}
+
+/// `sha256=<hex>` of `<timestamp>.<body>`.
+pub fn sign(secret: &[u8], timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("hmac takes any key size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// A launch waiting for the rest of its transaction, which may hold the
+/// creator's initial buy.
+struct PendingCreate {
+    record: Record,
+    initial_buy: u64,
+}
+
+/// POSTs the create/trade events matching a rule to the rule's URL.
+pub struct WebhookSink {
+    rules: Vec<Rule>,
+    secret: Vec<u8>,
+    notifier: Notifier,
+    pending: Option<PendingCreate>,
+}
+
+impl WebhookSink {
+    pub fn new(rules: Vec<Rule>, secret: &str, delivery: DeliveryConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            rules,
+            secret: secret.as_bytes().to_vec(),
+            notifier: Notifier::new(delivery)?,
+            pending: None,
+        })
+    }
+
+    pub fn from_config(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let config: WebhookConfig = serde_json::from_slice(
+            &std::fs::read(path)
+                .with_context(|| format!("failed to read webhook config {}", path.display()))?,
+        )
+        .with_context(|| format!("invalid webhook config {}", path.display()))?;
+        let secret = env::var("WEBHOOK_SECRET")
+            .ok()
+            .or(config.secret)
+            .context("webhook secret is not set (secret or WEBHOOK_SECRET)")?;
+        let rules = config
+            .rules
+            .into_iter()
+            .map(Rule::try_from)
+            .collect::<anyhow::Result<_>>()?;
+        let delivery = DeliveryConfig {
+            max_attempts: config.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS).max(1),
+            max_per_second: config.max_per_second.unwrap_or(DEFAULT_MAX_PER_SECOND),
+            dead_letter: config.dead_letter,
+        };
+        Self::new(rules, &secret, delivery)
+    }
+
+    fn notify(&mut self, record: &Record, initial_buy: Option<u64>) -> anyhow::Result<()> {
+        let id = format!(
+            "{}:{}",
+            str_field(&record.value, "signature"),
+            record.value["eventIndex"]
+        );
+        let timestamp = chrono::Utc::now().timestamp();
+        for rule in &self.rules {
+            if !rule.matches(record, initial_buy.unwrap_or_default()) {
+                continue;
+            }
+            let delivery_id = format!("{id}:{}", rule.name);
+            let mut body = json!({
+                "id": delivery_id,
+                "rule": rule.name,
+                "event": record.to_json(),
+            });
+            if let Some(initial_buy) = initial_buy {
+                body["initialBuyLamports"] = json!(initial_buy);
+            }
+            let body = body.to_string();
+            let signature = sign(&self.secret, timestamp, &body);
+            self.notifier.send(Delivery {
+                url: rule.url.clone(),
+                target: redact(&rule.url),
+                id: delivery_id,
+                headers: vec![
+                    (TIMESTAMP_HEADER.to_string(), timestamp.to_string()),
+                    (SIGNATURE_HEADER.to_string(), signature),
+                ],
+                body,
+            })?;
+        }
+        Ok(())
+    }
+
+    fn resolve_pending(&mut self) -> anyhow::Result<()> {
+        match self.pending.take() {
+            Some(pending) => self.notify(&pending.record, Some(pending.initial_buy)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Sink for WebhookSink {
+    fn write(&mut self, record: &Record) -> anyhow::Result<()> {
+        let signature = str_field(&record.value, "signature");
+        if self
+            .pending
+            .as_ref()
+            .is_some_and(|pending| str_field(&pending.record.value, "signature") != signature)
+        {
+            self.resolve_pending()?;
+        }
+        match record.kind {
+            EventKind::Create => {
+                self.resolve_pending()?;
+                self.pending = Some(PendingCreate {
+                    record: record.clone(),
+                    initial_buy: 0,
+                });
+                Ok(())
+            }
+            EventKind::Trade => {
+                let value = &record.value;
+                if let Some(pending) = &mut self.pending
+                    && value.get("isBuy").and_then(Value::as_bool) == Some(true)
+                    && str_field(value, "user") == str_field(&pending.record.value, "user")
+                    && str_field(value, "mint") == str_field(&pending.record.value, "mint")
+                {
+                    pending.initial_buy += value
+                        .get("solAmount")
+                        .and_then(Value::as_u64)
+                        .unwrap_or_default();
+                }
+                self.notify(record, None)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        self.resolve_pending()
+    }
+
+    /// Sends the last launch and waits for the deliveries still being retried.
+    fn close(&mut self) -> anyhow::Result<()> {
+        self.resolve_pending()?;
+        self.notifier.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use std::sync::{Arc, Mutex};
+
+    fn rule(config: Value) -> anyhow::Result<Rule> {
+        Rule::try_from(serde_json::from_value::<RuleConfig>(config)?)
+    }
+
+    fn create(signature: &str, creator: &Pubkey, name: &str) -> Record {
+        Record::from_json(json!({
+            "type": "create",
+            "signature": signature,
+            "eventIndex": 3,
+            "mint": Pubkey::new_unique().to_string(),
+            "name": name,
+            "symbol": "TST",
+            "creator": creator.to_string(),
+            "user": creator.to_string(),
+        }))
+        .unwrap()
+    }
+
+    fn trade(signature: &str, user: &Pubkey, mint: &str, sol: u64) -> Record {
+        Record::from_json(json!({
+            "type": "trade",
+            "signature": signature,
+            "eventIndex": 20_004,
+            "mint": mint,
+            "user": user.to_string(),
+            "isBuy": true,
+            "solAmount": sol * 1_000_000_000,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn rules_match_every_criterion() {
+        let creator = Pubkey::new_unique();
+        let launch = rule(json!({
+            "name": "launch", "url": "http://127.0.0.1/hook", "event": "create",
+            "creators": [creator.to_string()], "nameRegex": "(?i)^dog", "minInitialBuySol": 1,
+        }))
+        .unwrap();
+        let record = create("a", &creator, "Doge");
+        assert!(launch.matches(&record, 1_000_000_000));
+        assert!(!launch.matches(&record, 999_999_999));
+        assert!(!launch.matches(&create("a", &creator, "Cat"), 1_000_000_000));
+        assert!(!launch.matches(&create("a", &Pubkey::new_unique(), "Doge"), 1_000_000_000));
+
+        let whale = rule(json!({
+            "name": "whale", "url": "http://127.0.0.1/hook", "event": "trade", "minSol": 10,
+        }))
+        .unwrap();
+        let user = Pubkey::new_unique();
+        assert!(whale.matches(&trade("b", &user, "m", 10), 0));
+        assert!(!whale.matches(&trade("b", &user, "m", 9), 0));
+        assert!(!whale.matches(&record, 0));
+
+        assert!(rule(json!({"name": "all", "url": "u", "event": "trade"})).is_err());
+        assert!(rule(json!({"name": "all", "url": "u", "event": "trade", "minSol": 0})).is_ok());
+        assert!(rule(json!({"name": "slots", "url": "u", "event": "slot"})).is_err());
+    }
+
+    #[derive(Clone, Default)]
+    struct Received {
+        flaky: Arc<Mutex<Vec<(HeaderMap, String)>>>,
+        rejected: Arc<Mutex<u32>>,
+    }
+
+    /// 500 twice, then 200.
+    async fn flaky(
+        State(received): State<Received>,
+        headers: HeaderMap,
+        body: String,
+    ) -> StatusCode {
+        let mut flaky = received.flaky.lock().unwrap();
+        flaky.push((headers, body));
+        if flaky.len() < 3 {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::OK
+        }
+    }
+
+    async fn reject(State(received): State<Received>) -> StatusCode {
+        *received.rejected.lock().unwrap() += 1;
+        StatusCode::BAD_REQUEST
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn retries_then_dead_letters_rejections() {
+        let received = Received::default();
+        let app = Router::new()
+            .route("/flaky", post(flaky))
+            .route("/reject", post(reject))
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let dead_letter =
+            env::temp_dir().join(format!("webhook-dead-{}.ndjson", std::process::id()));
+        let _ = std::fs::remove_file(&dead_letter);
+        let creator = Pubkey::new_unique();
+        let rules = vec![
+            rule(json!({
+                "name": "launch", "url": format!("{base}/flaky"), "event": "create",
+                "creators": [creator.to_string()], "minInitialBuySol": 1,
+            }))
+            .unwrap(),
+            rule(json!({
+                "name": "whale", "url": format!("{base}/reject?token=secret"), "event": "trade",
+                "minSol": 5,
+            }))
+            .unwrap(),
+        ];
+        let delivery = DeliveryConfig {
+            max_attempts: 3,
+            max_per_second: 100.0,
+            dead_letter: Some(dead_letter.clone()),
+        };
+        let mut sink = WebhookSink::new(rules, "secret", delivery).unwrap();
+        let launch = create("launch-sig", &creator, "Test");
+        let mint = str_field(&launch.value, "mint").to_string();
+        sink.write(&launch).unwrap();
+        // the creator's initial buy, in the same transaction
+        sink.write(&trade("launch-sig", &creator, &mint, 2))
+            .unwrap();
+        sink.write(&trade("whale-sig", &Pubkey::new_unique(), &mint, 6))
+            .unwrap();
+        sink.write(&create("other-sig", &Pubkey::new_unique(), "Test"))
+            .unwrap();
+        tokio::task::spawn_blocking(move || sink.close())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let flaky = received.flaky.lock().unwrap();
+        assert_eq!(flaky.len(), 3);
+        let (headers, body) = &flaky[2];
+        let timestamp = headers[TIMESTAMP_HEADER].to_str().unwrap();
+        assert_eq!(
+            headers[SIGNATURE_HEADER].to_str().unwrap(),
+            sign(b"secret", timestamp.parse().unwrap(), body)
+        );
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["id"], "launch-sig:3:launch");
+        assert_eq!(body["initialBuyLamports"], 2_000_000_000u64);
+        assert_eq!(body["event"]["type"], "create");
+
+        // 4xx is not retried
+        assert_eq!(*received.rejected.lock().unwrap(), 1);
+        let dead = std::fs::read_to_string(&dead_letter).unwrap();
+        let _ = std::fs::remove_file(&dead_letter);
+        let lines = dead.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 1);
+        let line: Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(line["id"], "whale-sig:20004:whale");
+        assert_eq!(line["attempts"], 1);
+        assert_eq!(line["target"], format!("{base}/reject"));
+        assert!(line.get("url").is_none());
+        assert!(!dead.contains("secret"));
+    }
+}
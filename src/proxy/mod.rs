@@ -0,0 +1,447 @@
+pub mod union;
+
+use futures::channel::mpsc::unbounded;
+use futures::{SinkExt, StreamExt};
+use log::{info, warn};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc::{self, Sender, error::TrySendError};
+use tokio::sync::watch;
+use tokio_stream::wrappers::ReceiverStream;
+use yellowstone_grpc_client::{GeyserGrpcClient, Interceptor};
+use yellowstone_grpc_proto::geyser::geyser_client::GeyserClient;
+use yellowstone_grpc_proto::geyser::geyser_server::{Geyser, GeyserServer};
+use yellowstone_grpc_proto::prelude::*;
+use yellowstone_grpc_proto::prost_types::Timestamp;
+use yellowstone_grpc_proto::tonic::service::interceptor::InterceptedService;
+use yellowstone_grpc_proto::tonic::transport::{Channel, Server};
+use yellowstone_grpc_proto::tonic::{Request, Response, Status, Streaming};
+
+use union::FilterUnion;
+
+pub const DEFAULT_PROXY_CLIENT_BUFFER: usize = 10_000;
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+type Update = Result<SubscribeUpdate, Status>;
+
+struct Client {
+    request: SubscribeRequest,
+    tx: Sender<Update>,
+}
+
+struct State {
+    clients: BTreeMap<u64, Client>,
+    union: FilterUnion,
+}
+
+/// Downstream subscriptions. Every change recomputes the union and hands it to
+/// the upstream task through `upstream`.
+#[derive(Clone)]
+struct Subscribers {
+    state: Arc<Mutex<State>>,
+    upstream: Arc<watch::Sender<SubscribeRequest>>,
+    next_id: Arc<AtomicU64>,
+    commitment: i32,
+}
+
+impl Subscribers {
+    fn new(commitment: CommitmentLevel) -> Self {
+        let commitment = commitment as i32;
+        Self {
+            state: Arc::new(Mutex::new(State {
+                clients: BTreeMap::new(),
+                union: FilterUnion::default(),
+            })),
+            upstream: Arc::new(watch::Sender::new(SubscribeRequest {
+                commitment: Some(commitment),
+                ..Default::default()
+            })),
+            next_id: Arc::default(),
+            commitment,
+        }
+    }
+
+    fn changed(&self, state: &mut State) {
+        let request = state.union.rebuild(
+            self.commitment,
+            state
+                .clients
+                .iter()
+                .map(|(id, client)| (*id, &client.request)),
+        );
+        self.upstream.send_replace(request);
+    }
+
+    fn add(&self, tx: Sender<Update>) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut state = self.state.lock().expect("proxy lock poisoned");
+        state.clients.insert(
+            id,
+            Client {
+                request: SubscribeRequest::default(),
+                tx,
+            },
+        );
+        id
+    }
+
+    fn update(&self, id: u64, request: SubscribeRequest) {
+        let mut state = self.state.lock().expect("proxy lock poisoned");
+        if let Some(client) = state.clients.get_mut(&id) {
+            client.request = request;
+            self.changed(&mut state);
+        }
+    }
+
+    fn remove(&self, id: u64) {
+        let mut state = self.state.lock().expect("proxy lock poisoned");
+        if state.clients.remove(&id).is_some() {
+            self.changed(&mut state);
+        }
+    }
+
+    /// Forward an upstream update to the clients whose filters it matched,
+    /// under their own filter names and with their own account data slices.
+    fn dispatch(&self, update: SubscribeUpdate) {
+        let mut state = self.state.lock().expect("proxy lock poisoned");
+        let State { clients, union } = &mut *state;
+        let targets: Vec<(u64, SubscribeUpdate)> = match &update.update_oneof {
+            // keeps the downstream connections alive
+            Some(subscribe_update::UpdateOneof::Ping(_)) => {
+                clients.keys().map(|id| (*id, update.clone())).collect()
+            }
+            Some(subscribe_update::UpdateOneof::Pong(_)) | None => return,
+            Some(_) => union
+                .route(&update.filters)
+                .into_iter()
+                .filter_map(|(id, filters)| {
+                    let client = clients.get(&id)?;
+                    let mut update = SubscribeUpdate {
+                        filters,
+                        ..update.clone()
+                    };
+                    slice_account_data(&mut update, &client.request.accounts_data_slice);
+                    Some((id, update))
+                })
+                .collect(),
+        };
+
+        let mut dropped = false;
+        for (id, update) in targets {
+            let Some(client) = clients.get(&id) else {
+                continue;
+            };
+            match client.tx.try_send(Ok(update)) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    warn!("proxy client {id} is too slow, disconnecting");
+                    let tx = client.tx.clone();
+                    // delivered once the client has read what is queued
+                    tokio::spawn(async move {
+                        let _ = tx
+                            .send(Err(Status::resource_exhausted("client is too slow")))
+                            .await;
+                    });
+                    clients.remove(&id);
+                    dropped = true;
+                }
+                Err(TrySendError::Closed(_)) => {
+                    clients.remove(&id);
+                    dropped = true;
+                }
+            }
+        }
+        if dropped {
+            self.changed(&mut state);
+        }
+    }
+}
+
+/// Keep only the requested ranges of each account's data, concatenated.
+fn slice_account_data(update: &mut SubscribeUpdate, slices: &[SubscribeRequestAccountsDataSlice]) {
+    if slices.is_empty() {
+        return;
+    }
+    if let Some(subscribe_update::UpdateOneof::Account(msg)) = &mut update.update_oneof
+        && let Some(account) = &mut msg.account
+    {
+        let data = &account.data;
+        account.data = slices
+            .iter()
+            .flat_map(|slice| {
+                let start = (slice.offset as usize).min(data.len());
+                let end = start.saturating_add(slice.length as usize).min(data.len());
+                data[start..end].iter().copied()
+            })
+            .collect();
+    }
+}
+
+fn pong(id: i32) -> SubscribeUpdate {
+    SubscribeUpdate {
+        filters: vec![],
+        created_at: Some(Timestamp::from(SystemTime::now())),
+        update_oneof: Some(subscribe_update::UpdateOneof::Pong(SubscribeUpdatePong {
+            id,
+        })),
+    }
+}
+
+fn is_ping_only(request: &SubscribeRequest) -> bool {
+    request.ping.is_some()
+        && request.accounts.is_empty()
+        && request.slots.is_empty()
+        && request.transactions.is_empty()
+        && request.transactions_status.is_empty()
+        && request.blocks.is_empty()
+        && request.blocks_meta.is_empty()
+        && request.entry.is_empty()
+}
+
+/// Serves the Geyser service to local clients out of one upstream subscription.
+pub struct GeyserProxy<F> {
+    upstream: Arc<tokio::sync::Mutex<GeyserClient<InterceptedService<Channel, F>>>>,
+    subscribers: Subscribers,
+    buffer: usize,
+}
+
+/// Why the proxy can't serve `request`, if it can't.
+fn rejection(commitment: i32, request: &SubscribeRequest) -> Option<Status> {
+    if request.from_slot.is_some() {
+        return Some(Status::invalid_argument(
+            "from_slot is not supported by the proxy",
+        ));
+    }
+    if let Some(requested) = request.commitment
+        && requested != commitment
+    {
+        let proxy = CommitmentLevel::try_from(commitment)
+            .map(|level| level.as_str_name())
+            .unwrap_or_default();
+        return Some(Status::invalid_argument(format!(
+            "the proxy only serves commitment {proxy}"
+        )));
+    }
+    None
+}
+
+impl<F> GeyserProxy<F>
+where
+    F: Interceptor + Send + Sync + 'static,
+{
+    /// Keep one upstream subscription open, resubscribing with the current
+    /// union whenever it changes, and reconnecting with backoff when it fails.
+    async fn run_upstream(
+        upstream: Arc<tokio::sync::Mutex<GeyserClient<InterceptedService<Channel, F>>>>,
+        subscribers: Subscribers,
+    ) {
+        let mut changes = subscribers.upstream.subscribe();
+        let mut backoff = Duration::from_secs(1);
+        loop {
+            let (mut request_tx, request_rx) = unbounded();
+            let request = changes.borrow_and_update().clone();
+            let _ = request_tx.send(request).await;
+            let subscribed = upstream.lock().await.subscribe(request_rx).await;
+            match subscribed {
+                Ok(response) => {
+                    info!("proxy subscribed upstream");
+                    let mut stream = response.into_inner();
+                    loop {
+                        tokio::select! {
+                            changed = changes.changed() => {
+                                if changed.is_err() {
+                                    return;
+                                }
+                                let request = changes.borrow_and_update().clone();
+                                if request_tx.send(request).await.is_err() {
+                                    break;
+                                }
+                            }
+                            message = stream.next() => match message {
+                                Some(Ok(update)) => {
+                                    backoff = Duration::from_secs(1);
+                                    if let Some(subscribe_update::UpdateOneof::Ping(_)) =
+                                        update.update_oneof
+                                    {
+                                        let _ = request_tx
+                                            .send(SubscribeRequest {
+                                                ping: Some(SubscribeRequestPing { id: 1 }),
+                                                ..Default::default()
+                                            })
+                                            .await;
+                                    }
+                                    subscribers.dispatch(update);
+                                }
+                                Some(Err(status)) => {
+                                    warn!("proxy upstream failed: {status}");
+                                    break;
+                                }
+                                None => {
+                                    warn!("proxy upstream closed");
+                                    break;
+                                }
+                            },
+                        }
+                    }
+                }
+                Err(status) => warn!("proxy failed to subscribe upstream: {status}"),
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+}
+
+#[yellowstone_grpc_proto::tonic::async_trait]
+impl<F> Geyser for GeyserProxy<F>
+where
+    F: Interceptor + Send + Sync + 'static,
+{
+    type SubscribeStream = ReceiverStream<Update>;
+
+    /// Each request from the client replaces its filters, as with Yellowstone
+    /// itself; a request carrying only `ping` is answered here with a pong.
+    async fn subscribe(
+        &self,
+        request: Request<Streaming<SubscribeRequest>>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let mut requests = request.into_inner();
+        let (tx, rx) = mpsc::channel(self.buffer);
+        // the subscribers hold the only strong sender, so dropping the client
+        // there ends its stream
+        let weak = tx.downgrade();
+        let id = self.subscribers.add(tx);
+        let subscribers = self.subscribers.clone();
+        let commitment = self.subscribers.commitment;
+        tokio::spawn(async move {
+            while let Ok(Some(request)) = requests.message().await {
+                let Some(tx) = weak.upgrade() else {
+                    break;
+                };
+                if let Some(SubscribeRequestPing { id }) = request.ping {
+                    let _ = tx.send(Ok(pong(id))).await;
+                    if is_ping_only(&request) {
+                        continue;
+                    }
+                }
+                if let Some(status) = rejection(commitment, &request) {
+                    subscribers.remove(id);
+                    let _ = tx.send(Err(status)).await;
+                    break;
+                }
+                subscribers.update(id, request);
+            }
+            subscribers.remove(id);
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn subscribe_replay_info(
+        &self,
+        request: Request<SubscribeReplayInfoRequest>,
+    ) -> Result<Response<SubscribeReplayInfoResponse>, Status> {
+        self.upstream
+            .lock()
+            .await
+            .subscribe_replay_info(request.into_inner())
+            .await
+    }
+
+    async fn ping(&self, request: Request<PingRequest>) -> Result<Response<PongResponse>, Status> {
+        Ok(Response::new(PongResponse {
+            count: request.into_inner().count,
+        }))
+    }
+
+    async fn get_latest_blockhash(
+        &self,
+        request: Request<GetLatestBlockhashRequest>,
+    ) -> Result<Response<GetLatestBlockhashResponse>, Status> {
+        self.upstream
+            .lock()
+            .await
+            .get_latest_blockhash(request.into_inner())
+            .await
+    }
+
+    async fn get_block_height(
+        &self,
+        request: Request<GetBlockHeightRequest>,
+    ) -> Result<Response<GetBlockHeightResponse>, Status> {
+        self.upstream
+            .lock()
+            .await
+            .get_block_height(request.into_inner())
+            .await
+    }
+
+    async fn get_slot(
+        &self,
+        request: Request<GetSlotRequest>,
+    ) -> Result<Response<GetSlotResponse>, Status> {
+        self.upstream
+            .lock()
+            .await
+            .get_slot(request.into_inner())
+            .await
+    }
+
+    async fn is_blockhash_valid(
+        &self,
+        request: Request<IsBlockhashValidRequest>,
+    ) -> Result<Response<IsBlockhashValidResponse>, Status> {
+        self.upstream
+            .lock()
+            .await
+            .is_blockhash_valid(request.into_inner())
+            .await
+    }
+
+    async fn get_version(
+        &self,
+        request: Request<GetVersionRequest>,
+    ) -> Result<Response<GetVersionResponse>, Status> {
+        self.upstream
+            .lock()
+            .await
+            .get_version(request.into_inner())
+            .await
+    }
+}
+
+/// Serve the Geyser service on `addr` until ctrl-c, multiplexing every
+/// downstream `Subscribe` onto one subscription of `client` at `commitment`.
+/// Unary calls other than `Ping` are forwarded upstream.
+pub async fn serve<F>(
+    addr: SocketAddr,
+    client: GeyserGrpcClient<F>,
+    commitment: CommitmentLevel,
+    buffer: usize,
+) -> anyhow::Result<()>
+where
+    F: Interceptor + Send + Sync + 'static,
+{
+    let subscribers = Subscribers::new(commitment);
+    let upstream = Arc::new(tokio::sync::Mutex::new(client.geyser));
+    tokio::spawn(GeyserProxy::run_upstream(
+        upstream.clone(),
+        subscribers.clone(),
+    ));
+    let proxy = GeyserProxy {
+        upstream,
+        subscribers,
+        buffer: buffer.max(1),
+    };
+    info!("geyser proxy listening on {addr}");
+    Server::builder()
+        .add_service(GeyserServer::new(proxy))
+        .serve_with_shutdown(addr, async {
+            let _ = tokio::signal::ctrl_c().await;
+            info!("shutting down");
+        })
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use yellowstone_grpc_proto::geyser::SubscribeRequest;
+use yellowstone_grpc_proto::prost::Message;
+
+/// The upstream subscription covering every client's filters.
+///
+/// Identical filters of different clients share one upstream filter. Upstream
+/// names are never reused, so an update still in flight from before a change
+/// can't be routed by a name that now means another filter.
+#[derive(Default)]
+pub struct FilterUnion {
+    names: HashMap<(&'static str, Vec<u8>), String>,
+    next: u64,
+    /// upstream filter name -> (client id, the client's own filter name)
+    routes: HashMap<String, Vec<(u64, String)>>,
+}
+
+struct Builder<'a> {
+    union: &'a mut FilterUnion,
+    used: HashSet<(&'static str, Vec<u8>)>,
+    routes: HashMap<String, Vec<(u64, String)>>,
+}
+
+impl Builder<'_> {
+    fn merge<T: Message + Clone>(
+        &mut self,
+        kind: &'static str,
+        id: u64,
+        filters: &HashMap<String, T>,
+        upstream: &mut HashMap<String, T>,
+    ) {
+        for (name, filter) in filters {
+            let key = (kind, filter.encode_to_vec());
+            let union = &mut *self.union;
+            let upstream_name = union
+                .names
+                .entry(key.clone())
+                .or_insert_with(|| {
+                    union.next += 1;
+                    format!("{kind}-{}", union.next)
+                })
+                .clone();
+            upstream.insert(upstream_name.clone(), filter.clone());
+            self.routes
+                .entry(upstream_name)
+                .or_default()
+                .push((id, name.clone()));
+            self.used.insert(key);
+        }
+    }
+}
+
+impl FilterUnion {
+    /// Recompute the union of `clients` and return the request to send upstream.
+    pub fn rebuild<'a>(
+        &mut self,
+        commitment: i32,
+        clients: impl IntoIterator<Item = (u64, &'a SubscribeRequest)>,
+    ) -> SubscribeRequest {
+        let mut request = SubscribeRequest {
+            commitment: Some(commitment),
+            ..Default::default()
+        };
+        let mut builder = Builder {
+            union: self,
+            used: HashSet::new(),
+            routes: HashMap::new(),
+        };
+        for (id, client) in clients {
+            builder.merge("accounts", id, &client.accounts, &mut request.accounts);
+            builder.merge("slots", id, &client.slots, &mut request.slots);
+            builder.merge(
+                "transactions",
+                id,
+                &client.transactions,
+                &mut request.transactions,
+            );
+            builder.merge(
+                "transactions_status",
+                id,
+                &client.transactions_status,
+                &mut request.transactions_status,
+            );
+            builder.merge("blocks", id, &client.blocks, &mut request.blocks);
+            builder.merge(
+                "blocks_meta",
+                id,
+                &client.blocks_meta,
+                &mut request.blocks_meta,
+            );
+            builder.merge("entry", id, &client.entry, &mut request.entry);
+        }
+        let Builder { used, routes, .. } = builder;
+        self.names.retain(|key, _| used.contains(key));
+        self.routes = routes;
+        request
+    }
+
+    /// The clients an update matched, each with its own names of the filters
+    /// that matched.
+    pub fn route(&self, filters: &[String]) -> BTreeMap<u64, Vec<String>> {
+        let mut matched: BTreeMap<u64, Vec<String>> = BTreeMap::new();
+        for (id, name) in filters
+            .iter()
+            .filter_map(|name| self.routes.get(name))
+            .flatten()
+        {
+            let names = matched.entry(*id).or_default();
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+        matched
+    }
+}
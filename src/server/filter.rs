@@ -0,0 +1,109 @@
+use serde::Deserialize;
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+
+use crate::sinks::{EventKind, Record};
+
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+
+/// Fields holding the token mint(s) of an event.
+const MINT_FIELDS: &[&str] = &["mint", "baseMint", "coinMint", "pcMint"];
+/// Fields holding the wallet(s) acting in an event.
+const WALLET_FIELDS: &[&str] = &[
+    "user",
+    "creator",
+    "coinCreator",
+    "feePayer",
+    "authority",
+    "from",
+    "to",
+];
+/// Fields holding the SOL size (lamports) of an event.
+const SOL_FIELDS: &[&str] = &["solAmount", "quoteAmountIn", "quoteAmountOut", "bundledSol"];
+
+/// Subscription as sent by a client, as query parameters
+/// (`?types=create,trade&mints=<mint>,<mint>&wallets=<wallet>&minSol=0.5`)
+/// or as a JSON text message with the same keys.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilterParams {
+    pub types: Option<String>,
+    pub mints: Option<String>,
+    pub wallets: Option<String>,
+    pub min_sol: Option<f64>,
+}
+
+/// Empty sets match everything. `min_lamports` only applies to events that have
+/// a SOL size (trades, launches); the others always pass it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClientFilter {
+    pub kinds: HashSet<EventKind>,
+    pub mints: HashSet<String>,
+    pub wallets: HashSet<String>,
+    pub min_lamports: u64,
+}
+
+fn split(list: &Option<String>) -> impl Iterator<Item = &str> {
+    list.as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+impl TryFrom<FilterParams> for ClientFilter {
+    type Error = anyhow::Error;
+
+    fn try_from(params: FilterParams) -> Result<Self, Self::Error> {
+        let pubkeys = |list| {
+            split(list)
+                .map(|key| {
+                    key.parse::<Pubkey>()
+                        .map(|key| key.to_string())
+                        .map_err(|_| anyhow::anyhow!("invalid pubkey: {key}"))
+                })
+                .collect::<anyhow::Result<HashSet<_>>>()
+        };
+        Ok(Self {
+            kinds: split(&params.types)
+                .map(str::parse)
+                .collect::<anyhow::Result<_>>()?,
+            mints: pubkeys(&params.mints)?,
+            wallets: pubkeys(&params.wallets)?,
+            min_lamports: (params.min_sol.unwrap_or_default().max(0.0) * LAMPORTS_PER_SOL) as u64,
+        })
+    }
+}
+
+fn any_field(value: &Value, fields: &[&str], keys: &HashSet<String>) -> bool {
+    fields.iter().any(|field| {
+        value
+            .get(field)
+            .and_then(Value::as_str)
+            .is_some_and(|key| keys.contains(key))
+    })
+}
+
+impl ClientFilter {
+    pub fn matches(&self, record: &Record) -> bool {
+        if !self.kinds.is_empty() && !self.kinds.contains(&record.kind) {
+            return false;
+        }
+        let value = &record.value;
+        if !self.mints.is_empty() && !any_field(value, MINT_FIELDS, &self.mints) {
+            return false;
+        }
+        if !self.wallets.is_empty() && !any_field(value, WALLET_FIELDS, &self.wallets) {
+            return false;
+        }
+        if self.min_lamports > 0
+            && let Some(lamports) = SOL_FIELDS
+                .iter()
+                .find_map(|field| value.get(field).and_then(Value::as_u64))
+        {
+            return lamports >= self.min_lamports;
+        }
+        true
+    }
+}
@@ -0,0 +1,219 @@
+pub mod filter;
+
+use axum::extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade, close_code};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use futures::{SinkExt, StreamExt};
+use log::{info, warn};
+use serde_json::json;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{self, Receiver, Sender, error::TrySendError};
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::sinks::{EventKind, Record, Sink};
+use filter::{ClientFilter, FilterParams};
+
+pub const DEFAULT_CLIENT_BUFFER: usize = 1024;
+
+/// A JSON-encoded record, shared by every client it goes to.
+type Payload = (EventKind, Arc<str>);
+
+struct Client {
+    filter: ClientFilter,
+    tx: Sender<Payload>,
+}
+
+/// Connected clients. Each has a bounded queue; a client whose queue is full is
+/// disconnected so it can never stall the stream loop.
+#[derive(Clone)]
+pub struct Hub {
+    clients: Arc<Mutex<HashMap<u64, Client>>>,
+    next_id: Arc<AtomicU64>,
+    buffer: usize,
+}
+
+impl Hub {
+    pub fn new(buffer: usize) -> Self {
+        Self {
+            clients: Arc::default(),
+            next_id: Arc::default(),
+            buffer: buffer.max(1),
+        }
+    }
+
+    pub fn subscribe(&self, filter: ClientFilter) -> (u64, Receiver<Payload>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::channel(self.buffer);
+        self.clients
+            .lock()
+            .expect("hub lock poisoned")
+            .insert(id, Client { filter, tx });
+        (id, rx)
+    }
+
+    pub fn update(&self, id: u64, filter: ClientFilter) {
+        if let Some(client) = self.clients.lock().expect("hub lock poisoned").get_mut(&id) {
+            client.filter = filter;
+        }
+    }
+
+    pub fn unsubscribe(&self, id: u64) {
+        self.clients.lock().expect("hub lock poisoned").remove(&id);
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.lock().expect("hub lock poisoned").len()
+    }
+
+    pub fn publish(&self, record: &Record) {
+        let mut payload: Option<Arc<str>> = None;
+        self.clients
+            .lock()
+            .expect("hub lock poisoned")
+            .retain(|id, client| {
+                if !client.filter.matches(record) {
+                    return true;
+                }
+                let text = payload
+                    .get_or_insert_with(|| record.to_json().to_string().into())
+                    .clone();
+                match client.tx.try_send((record.kind, text)) {
+                    Ok(()) => true,
+                    Err(TrySendError::Full(_)) => {
+                        warn!("client {id} is too slow, disconnecting");
+                        false
+                    }
+                    Err(TrySendError::Closed(_)) => false,
+                }
+            });
+    }
+}
+
+/// The hub as an output: every record routed here goes to the matching clients.
+pub struct FanoutSink {
+    hub: Hub,
+}
+
+impl FanoutSink {
+    pub fn new(hub: Hub) -> Self {
+        Self { hub }
+    }
+}
+
+impl Sink for FanoutSink {
+    fn write(&mut self, record: &Record) -> anyhow::Result<()> {
+        self.hub.publish(record);
+        Ok(())
+    }
+}
+
+fn bad_request(error: anyhow::Error) -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!({ "error": format!("{error:#}") })),
+    )
+        .into_response()
+}
+
+async fn ws_handler(
+    ws: WebSocketUpgrade,
+    Query(params): Query<FilterParams>,
+    State(hub): State<Hub>,
+) -> Response {
+    match ClientFilter::try_from(params) {
+        Ok(filter) => ws.on_upgrade(move |socket| ws_client(socket, hub, filter)),
+        Err(error) => bad_request(error),
+    }
+}
+
+/// Text messages from the client replace its filter, e.g. `{"types":"trade","minSol":1}`.
+async fn ws_client(socket: WebSocket, hub: Hub, filter: ClientFilter) {
+    let (id, mut rx) = hub.subscribe(filter);
+    let (mut sender, mut receiver) = socket.split();
+    loop {
+        tokio::select! {
+            payload = rx.recv() => {
+                let Some((_, text)) = payload else {
+                    let _ = sender
+                        .send(Message::Close(Some(CloseFrame {
+                            code: close_code::POLICY,
+                            reason: "too slow".into(),
+                        })))
+                        .await;
+                    break;
+                };
+                if sender.send(Message::Text(text.as_ref().into())).await.is_err() {
+                    break;
+                }
+            }
+            message = receiver.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let reply = serde_json::from_str::<FilterParams>(&text)
+                        .map_err(anyhow::Error::from)
+                        .and_then(ClientFilter::try_from);
+                    let reply = match reply {
+                        Ok(filter) => {
+                            hub.update(id, filter);
+                            json!({ "subscribed": true })
+                        }
+                        Err(error) => json!({ "error": format!("{error:#}") }),
+                    };
+                    if sender.send(Message::Text(reply.to_string().into())).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    hub.unsubscribe(id);
+}
+
+async fn sse_handler(Query(params): Query<FilterParams>, State(hub): State<Hub>) -> Response {
+    let filter = match ClientFilter::try_from(params) {
+        Ok(filter) => filter,
+        Err(error) => return bad_request(error),
+    };
+    // the hub drops the client on its first send after the stream is gone
+    let (_, rx) = hub.subscribe(filter);
+    let events = ReceiverStream::new(rx).map(|(kind, text)| {
+        Ok::<_, Infallible>(Event::default().event(kind.as_str()).data(text.as_ref()))
+    });
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+async fn health(State(hub): State<Hub>) -> Json<serde_json::Value> {
+    Json(json!({ "clients": hub.client_count() }))
+}
+
+/// Serve `/ws` (WebSocket), `/sse` (Server-Sent Events) and `/health` on `addr`.
+/// Binds before returning so address errors surface at startup; must be called
+/// inside the tokio runtime.
+pub fn serve(addr: SocketAddr, hub: Hub) -> anyhow::Result<()> {
+    let listener = std::net::TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    let listener = tokio::net::TcpListener::from_std(listener)?;
+    let app = Router::new()
+        .route("/ws", get(ws_handler))
+        .route("/sse", get(sse_handler))
+        .route("/health", get(health))
+        .with_state(hub);
+    info!("event server listening on {addr}");
+    tokio::spawn(async move {
+        if let Err(error) = axum::serve(listener, app).await {
+            warn!("event server stopped: {error}");
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use log::warn;
+use tokio::sync::mpsc::{self, Receiver, Sender, error::TrySendError};
+
+use super::{Record, Sink};
+
+/// Hands records to another task. Never blocks the stream: when the receiver
+/// falls behind, records are dropped and counted.
+pub struct ChannelSink {
+    tx: Sender<Record>,
+    dropped: u64,
+}
+
+impl ChannelSink {
+    pub fn new(capacity: usize) -> (Self, Receiver<Record>) {
+        let (tx, rx) = mpsc::channel(capacity);
+        (Self { tx, dropped: 0 }, rx)
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+impl Sink for ChannelSink {
+    fn write(&mut self, record: &Record) -> anyhow::Result<()> {
+        match self.tx.try_send(record.clone()) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                if self.dropped.is_power_of_two() {
+                    warn!("channel sink full, {} records dropped", self.dropped);
+                }
+                Ok(())
+            }
+            Err(TrySendError::Closed(_)) => anyhow::bail!("channel sink receiver dropped"),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::path::Path;
+
+use super::rotating::{RotatingWriter, RotationConfig};
+use super::{Record, Sink};
+
+/// NDJSON files in `dir`, named `<prefix>-<utc time>-<sequence>.ndjson`,
+/// rotated by size (100MB by default) and optionally every hour.
+pub struct FileSink {
+    writer: RotatingWriter,
+}
+
+impl FileSink {
+    pub fn new(dir: impl AsRef<Path>, prefix: &str) -> anyhow::Result<Self> {
+        Self::with_rotation(dir, prefix, RotationConfig::default())
+    }
+
+    pub fn with_rotation(
+        dir: impl AsRef<Path>,
+        prefix: &str,
+        rotation: RotationConfig,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            writer: RotatingWriter::new(dir, prefix, "ndjson", rotation)?,
+        })
+    }
+}
+
+impl Sink for FileSink {
+    fn write(&mut self, record: &Record) -> anyhow::Result<()> {
+        self.writer
+            .write_line(&serde_json::to_vec(&record.to_json())?)
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        self.writer.flush()
+    }
+
+    fn close(&mut self) -> anyhow::Result<()> {
+        self.writer.close()
+    }
+}
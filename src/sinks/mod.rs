@@ -0,0 +1,387 @@
+pub mod channel;
+pub mod file;
+pub mod parquet;
+pub mod postgres;
+pub mod record;
+pub mod recorder;
+pub mod rotating;
+pub mod rows;
+pub mod sqlite;
+pub mod stdout;
+
+use anyhow::Context;
+use log::warn;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::env;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::notify::alert::{self, AlertSink};
+use crate::notify::webhook::{self, WebhookSink};
+use crate::server::{self, DEFAULT_CLIENT_BUFFER, FanoutSink, Hub};
+use rotating::RotationConfig;
+
+pub use channel::ChannelSink;
+pub use file::FileSink;
+pub use parquet::{ParquetArchive, ParquetSink};
+pub use postgres::PostgresSink;
+pub use recorder::{RecordFormat, RecorderSink};
+pub use sqlite::{SqliteSink, SqliteStore};
+pub use stdout::StdoutSink;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Create,
+    Trade,
+    Complete,
+    Buy,
+    Sell,
+    CreatePool,
+    RaydiumSwap,
+    TokenTransfer,
+    TokenMintTo,
+    TokenBurn,
+    SystemTransfer,
+    SystemCreateAccount,
+    Account,
+    Slot,
+    Transaction,
+    Launch,
+    Lifecycle,
+    Candle,
+    DevSell,
+    SniperReport,
+    Position,
+    CopySignal,
+    Submission,
+    HolderConcentration,
+    Risk,
+    Metadata,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 26] = [
+        EventKind::Create,
+        EventKind::Trade,
+        EventKind::Complete,
+        EventKind::Buy,
+        EventKind::Sell,
+        EventKind::CreatePool,
+        EventKind::RaydiumSwap,
+        EventKind::TokenTransfer,
+        EventKind::TokenMintTo,
+        EventKind::TokenBurn,
+        EventKind::SystemTransfer,
+        EventKind::SystemCreateAccount,
+        EventKind::Account,
+        EventKind::Slot,
+        EventKind::Transaction,
+        EventKind::Launch,
+        EventKind::Lifecycle,
+        EventKind::Candle,
+        EventKind::DevSell,
+        EventKind::SniperReport,
+        EventKind::Position,
+        EventKind::CopySignal,
+        EventKind::Submission,
+        EventKind::HolderConcentration,
+        EventKind::Risk,
+        EventKind::Metadata,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Create => "create",
+            EventKind::Trade => "trade",
+            EventKind::Complete => "complete",
+            EventKind::Buy => "buy",
+            EventKind::Sell => "sell",
+            EventKind::CreatePool => "create_pool",
+            EventKind::RaydiumSwap => "raydium_swap",
+            EventKind::TokenTransfer => "token_transfer",
+            EventKind::TokenMintTo => "token_mint_to",
+            EventKind::TokenBurn => "token_burn",
+            EventKind::SystemTransfer => "system_transfer",
+            EventKind::SystemCreateAccount => "system_create_account",
+            EventKind::Account => "account",
+            EventKind::Slot => "slot",
+            EventKind::Transaction => "transaction",
+            EventKind::Launch => "launch",
+            EventKind::Lifecycle => "lifecycle",
+            EventKind::Candle => "candle",
+            EventKind::DevSell => "dev_sell",
+            EventKind::SniperReport => "sniper_report",
+            EventKind::Position => "position",
+            EventKind::CopySignal => "copy_signal",
+            EventKind::Submission => "submission",
+            EventKind::HolderConcentration => "holder_concentration",
+            EventKind::Risk => "risk",
+            EventKind::Metadata => "metadata",
+        }
+    }
+}
+
+impl FromStr for EventKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EventKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| anyhow::anyhow!("unknown event type: {s}"))
+    }
+}
+
+/// One output event. `value` is a JSON object, pubkeys/signatures as base58.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub kind: EventKind,
+    pub value: Value,
+}
+
+impl Record {
+    /// The flat NDJSON shape: `{"type": <kind>, ...value}`.
+    pub fn to_json(&self) -> Value {
+        let mut value = self.value.clone();
+        if let Value::Object(map) = &mut value {
+            map.insert("type".to_string(), Value::from(self.kind.as_str()));
+        }
+        value
+    }
+
+    /// The inverse of `to_json`.
+    pub fn from_json(mut value: Value) -> anyhow::Result<Self> {
+        let kind = value
+            .as_object_mut()
+            .and_then(|map| map.remove("type"))
+            .and_then(|kind| kind.as_str().map(str::parse))
+            .context("record without a type")??;
+        Ok(Self { kind, value })
+    }
+}
+
+pub trait Sink: Send {
+    fn write(&mut self, record: &Record) -> anyhow::Result<()>;
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called once on shutdown, after the last write.
+    fn close(&mut self) -> anyhow::Result<()> {
+        self.flush()
+    }
+}
+
+struct Route {
+    kinds: HashSet<EventKind>,
+    sink: Box<dyn Sink>,
+}
+
+/// Sends each record to the sinks configured for its event type.
+#[derive(Default)]
+pub struct SinkRouter {
+    routes: Vec<Route>,
+    stores_slots: bool,
+}
+
+/// Everything but the token/system transfers, which are mostly noise.
+pub const DEFAULT_SINKS: &str = "stdout=create,trade,complete,buy,sell,create_pool,raydium_swap,account,slot,launch,lifecycle,candle,dev_sell,sniper_report,position,copy_signal,submission,holder_concentration,risk,metadata";
+
+pub const DEFAULT_POSTGRES_SPOOL: &str = "./postgres-spool.ndjson";
+
+impl SinkRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add<S: Sink + 'static>(
+        &mut self,
+        kinds: impl IntoIterator<Item = EventKind>,
+        sink: S,
+    ) -> &mut Self {
+        self.routes.push(Route {
+            kinds: kinds.into_iter().collect(),
+            sink: Box::new(sink),
+        });
+        self
+    }
+
+    /// Parse `sink[:arg]=events;...`, e.g. `stdout=create,trade;file:./output=*`.
+    ///
+    /// `file`, `csv` and `ndjson` take `dir[,hourly][,gzip][,<n>mb]`, e.g.
+    /// `csv:./data,hourly,gzip=trade`. `csv`/`ndjson` only record the pump events.
+    ///
+    /// `parquet` takes `dir[,<n>rows][,<n>s]` (row group size, flush interval) and
+    /// only archives `trade`, `buy` and `sell`. `sqlite` takes the database path.
+    /// `postgres[:spool file]` connects to `POSTGRES_URL`. `server:<addr>` serves the
+    /// events to WebSocket/SSE clients, e.g. `server:127.0.0.1:8080=*`.
+    /// `webhook:<config.json>` POSTs the `create`/`trade` events matching its rules,
+    /// `alerts:<config.json>` posts `create`/`complete`/`create_pool`/`trade`/`dev_sell` alerts to Telegram/Discord.
+    pub fn from_spec(spec: &str) -> anyhow::Result<Self> {
+        let mut router = Self::new();
+        for route in spec
+            .split(';')
+            .map(str::trim)
+            .filter(|route| !route.is_empty())
+        {
+            let (target, events) = route
+                .split_once('=')
+                .with_context(|| format!("invalid sink route: {route}"))?;
+            let kinds = if events.trim() == "*" {
+                EventKind::ALL.to_vec()
+            } else {
+                events
+                    .split(',')
+                    .map(|kind| kind.trim().parse())
+                    .collect::<anyhow::Result<Vec<_>>>()?
+            };
+            let (name, arg) = match target.split_once(':') {
+                Some((name, arg)) => (name, Some(arg)),
+                None => (target, None),
+            };
+            match (name, arg) {
+                ("stdout", _) => router.add(kinds, StdoutSink::new()),
+                ("file", Some(arg)) => {
+                    let (dir, rotation) = parse_file_arg(arg)?;
+                    router.add(kinds, FileSink::with_rotation(dir, "events", rotation)?)
+                }
+                ("csv" | "ndjson", Some(arg)) => {
+                    let (dir, rotation) = parse_file_arg(arg)?;
+                    let format = if name == "csv" {
+                        RecordFormat::Csv
+                    } else {
+                        RecordFormat::Ndjson
+                    };
+                    let kinds = kinds
+                        .into_iter()
+                        .filter(|kind| recorder::RECORDED.contains(kind))
+                        .collect::<Vec<_>>();
+                    router.add(kinds, RecorderSink::new(dir, format, rotation)?)
+                }
+                ("parquet", Some(arg)) => {
+                    let kinds = kinds
+                        .into_iter()
+                        .filter(|kind| parquet::ARCHIVED.contains(kind))
+                        .collect::<Vec<_>>();
+                    router.add(kinds, parse_parquet_arg(arg)?)
+                }
+                ("sqlite", Some(path)) => {
+                    let kinds = kinds
+                        .into_iter()
+                        .filter(|kind| rows::STORED.contains(kind))
+                        .collect::<Vec<_>>();
+                    router.stores_slots |= kinds.contains(&EventKind::Slot);
+                    router.add(kinds, SqliteSink::open(path)?)
+                }
+                ("postgres", spool) => {
+                    let url = env::var("POSTGRES_URL").context("POSTGRES_URL is not set")?;
+                    let kinds = kinds
+                        .into_iter()
+                        .filter(|kind| rows::STORED.contains(kind))
+                        .collect::<Vec<_>>();
+                    router.stores_slots |= kinds.contains(&EventKind::Slot);
+                    router.add(
+                        kinds,
+                        PostgresSink::new(&url, spool.unwrap_or(DEFAULT_POSTGRES_SPOOL))?,
+                    )
+                }
+                ("server", Some(addr)) => {
+                    let hub = Hub::new(DEFAULT_CLIENT_BUFFER);
+                    server::serve(addr.parse().context("invalid server address")?, hub.clone())?;
+                    router.add(kinds, FanoutSink::new(hub))
+                }
+                ("webhook", Some(path)) => {
+                    let kinds = kinds
+                        .into_iter()
+                        .filter(|kind| webhook::WATCHED.contains(kind))
+                        .collect::<Vec<_>>();
+                    router.add(kinds, WebhookSink::from_config(path)?)
+                }
+                ("alerts", Some(path)) => {
+                    let kinds = kinds
+                        .into_iter()
+                        .filter(|kind| alert::ALERTED.contains(kind))
+                        .collect::<Vec<_>>();
+                    router.add(kinds, AlertSink::from_config(path)?)
+                }
+                _ => anyhow::bail!("unknown sink: {target}"),
+            };
+        }
+        Ok(router)
+    }
+
+    /// Whether a `sqlite` or `postgres` sink stores `slot` events, which only
+    /// arrive with a slots filter in the subscription.
+    pub fn stores_slots(&self) -> bool {
+        self.stores_slots
+    }
+
+    /// A failing sink is logged and skipped so it can't stop the stream.
+    pub fn dispatch(&mut self, record: Record) {
+        for route in self
+            .routes
+            .iter_mut()
+            .filter(|route| route.kinds.contains(&record.kind))
+        {
+            if let Err(error) = route.sink.write(&record) {
+                warn!("sink failed to write {}: {error:#}", record.kind.as_str());
+            }
+        }
+    }
+
+    pub fn flush(&mut self) {
+        for route in &mut self.routes {
+            if let Err(error) = route.sink.flush() {
+                warn!("sink failed to flush: {error:#}");
+            }
+        }
+    }
+
+    pub fn close(&mut self) {
+        for route in &mut self.routes {
+            if let Err(error) = route.sink.close() {
+                warn!("sink failed to close: {error:#}");
+            }
+        }
+    }
+}
+
+fn parse_file_arg(arg: &str) -> anyhow::Result<(&str, RotationConfig)> {
+    let mut parts = arg.split(',').map(str::trim);
+    let dir = parts.next().unwrap_or_default();
+    let mut rotation = RotationConfig::default();
+    for option in parts {
+        match option {
+            "hourly" => rotation.hourly = true,
+            "gzip" => rotation.gzip = true,
+            size => {
+                let mb: u64 = size
+                    .strip_suffix("mb")
+                    .and_then(|mb| mb.parse().ok())
+                    .with_context(|| format!("invalid file sink option: {size}"))?;
+                rotation.max_bytes = mb * 1024 * 1024;
+            }
+        }
+    }
+    Ok((dir, rotation))
+}
+
+fn parse_parquet_arg(arg: &str) -> anyhow::Result<ParquetSink> {
+    let mut parts = arg.split(',').map(str::trim);
+    let mut archive = ParquetArchive::new(parts.next().unwrap_or_default())?;
+    for option in parts {
+        if let Some(rows) = option
+            .strip_suffix("rows")
+            .and_then(|rows| rows.parse().ok())
+        {
+            archive = archive.row_group_size(rows);
+        } else if let Some(secs) = option.strip_suffix('s').and_then(|secs| secs.parse().ok()) {
+            archive = archive.flush_interval(Duration::from_secs(secs));
+        } else {
+            anyhow::bail!("invalid parquet sink option: {option}");
+        }
+    }
+    Ok(ParquetSink::spawn(archive))
+}
@@ -0,0 +1,459 @@
+use arrow_array::{
+    ArrayRef, BooleanArray, Int64Array, RecordBatch, StringArray, TimestampMillisecondArray,
+    UInt64Array,
+};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use chrono::{DateTime, NaiveDate, Utc};
+use log::{error, info, warn};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::recorder::{Column, ColumnType, Decimals, record_key, schema};
+use super::{EventKind, Record, Sink};
+
+pub const DEFAULT_ROW_GROUP_SIZE: usize = 100_000;
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(300);
+const PARQUET_QUEUE_CAPACITY: usize = 100_000;
+
+/// The trade events the archive keeps.
+pub const ARCHIVED: [EventKind; 3] = [EventKind::Trade, EventKind::Buy, EventKind::Sell];
+
+fn arrow_schema(columns: &[Column]) -> Schema {
+    Schema::new(
+        columns
+            .iter()
+            .map(|column| {
+                let data_type = match column.ty {
+                    ColumnType::U64 => DataType::UInt64,
+                    ColumnType::I64 => DataType::Int64,
+                    ColumnType::Bool => DataType::Boolean,
+                    ColumnType::Pubkey | ColumnType::Signature | ColumnType::Text => DataType::Utf8,
+                    ColumnType::Time => {
+                        DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()))
+                    }
+                };
+                let mut metadata =
+                    HashMap::from([("type".to_string(), column.ty.as_str().to_string())]);
+                match column.decimals {
+                    Decimals::None => {}
+                    Decimals::Fixed(decimals) => {
+                        metadata.insert("decimals".to_string(), decimals.to_string());
+                    }
+                    Decimals::Column(name) => {
+                        metadata.insert("decimals_column".to_string(), name.to_string());
+                    }
+                }
+                Field::new(column.name, data_type, column.nullable()).with_metadata(metadata)
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn to_batch(
+    schema: &Arc<Schema>,
+    columns: &[Column],
+    rows: &[Vec<Value>],
+) -> anyhow::Result<RecordBatch> {
+    let arrays = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            let values = rows.iter().map(|row| &row[i]);
+            let array: ArrayRef = match column.ty {
+                ColumnType::U64 => Arc::new(values.map(Value::as_u64).collect::<UInt64Array>()),
+                ColumnType::I64 => Arc::new(values.map(Value::as_i64).collect::<Int64Array>()),
+                ColumnType::Bool => Arc::new(values.map(Value::as_bool).collect::<BooleanArray>()),
+                ColumnType::Pubkey | ColumnType::Signature | ColumnType::Text => {
+                    Arc::new(values.map(Value::as_str).collect::<StringArray>())
+                }
+                ColumnType::Time => Arc::new(
+                    values
+                        .map(|value| {
+                            value
+                                .as_str()
+                                .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+                                .map(|time| time.timestamp_millis())
+                        })
+                        .collect::<TimestampMillisecondArray>()
+                        .with_timezone("UTC"),
+                ),
+            };
+            array
+        })
+        .collect();
+    Ok(RecordBatch::try_new(schema.clone(), arrays)?)
+}
+
+/// One open file of a `date=<day>/type=<event>` partition. Written as
+/// `.parquet.inprogress` and renamed once the footer is written, so a
+/// `*.parquet` file is always complete.
+struct Partition {
+    columns: Vec<Column>,
+    schema: Arc<Schema>,
+    rows: Vec<Vec<Value>>,
+    writer: Option<ArrowWriter<File>>,
+    path: PathBuf,
+    /// Rows lost to row groups that failed to encode or write.
+    dropped: u64,
+}
+
+impl Partition {
+    fn tmp_path(&self) -> PathBuf {
+        self.path.with_extension("parquet.inprogress")
+    }
+
+    fn write_row_group(&mut self, properties: &WriterProperties) -> anyhow::Result<()> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        if self.writer.is_none() {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            self.writer = Some(ArrowWriter::try_new(
+                File::create(self.tmp_path())?,
+                self.schema.clone(),
+                Some(properties.clone()),
+            )?);
+        }
+        // a failed row group is dropped, otherwise the buffer grows without
+        // bound and every later write fails on the same row
+        let rows = std::mem::take(&mut self.rows);
+        let writer = self.writer.as_mut().expect("writer opened above");
+        to_batch(&self.schema, &self.columns, &rows)
+            .and_then(|batch| {
+                writer.write(&batch)?;
+                writer.flush()?;
+                Ok(())
+            })
+            .map_err(|error| {
+                self.dropped += rows.len() as u64;
+                error.context(format!(
+                    "dropped {} rows of {}",
+                    rows.len(),
+                    self.path.display()
+                ))
+            })
+    }
+
+    /// Writes the footer even when the last rows are dropped, so the row groups
+    /// already written stay readable.
+    fn close(&mut self, properties: &WriterProperties) -> anyhow::Result<()> {
+        let written = self.write_row_group(properties);
+        if let Some(writer) = self.writer.take() {
+            writer.close()?;
+            fs::rename(self.tmp_path(), &self.path)?;
+            info!("closed {}", self.path.display());
+        }
+        written
+    }
+}
+
+/// Trades (`trade`, `buy`, `sell`) archived as Parquet under
+/// `<dir>/date=<YYYY-MM-DD>/type=<event>/part-<utc time>-<sequence>.parquet`,
+/// partitioned by the event's block time. Columns match the CSV recorder, amounts
+/// stay raw integers with their decimals in the field metadata.
+///
+/// Rows are buffered and written as a row group once `row_group_size` rows are
+/// buffered or `flush_interval` has passed. Call `close` on shutdown to write the
+/// remaining rows and the file footers. A row group that fails to encode or write
+/// is dropped, `close` reports how many rows were lost. Writes on the calling
+/// thread, see [`ParquetSink`] for the background one.
+pub struct ParquetArchive {
+    dir: PathBuf,
+    row_group_size: usize,
+    flush_interval: Duration,
+    properties: WriterProperties,
+    partitions: HashMap<(NaiveDate, EventKind), Partition>,
+    latest_date: Option<NaiveDate>,
+    last_flush: Instant,
+    sequence: u64,
+    /// Dropped rows of the partitions already closed.
+    dropped: u64,
+}
+
+impl ParquetArchive {
+    pub fn new(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        let mut sink = Self {
+            dir: dir.as_ref().to_path_buf(),
+            row_group_size: DEFAULT_ROW_GROUP_SIZE,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            properties: WriterProperties::default(),
+            partitions: HashMap::new(),
+            latest_date: None,
+            last_flush: Instant::now(),
+            sequence: 0,
+            dropped: 0,
+        };
+        sink.update_properties();
+        Ok(sink)
+    }
+
+    pub fn row_group_size(mut self, rows: usize) -> Self {
+        self.row_group_size = rows.max(1);
+        self.update_properties();
+        self
+    }
+
+    pub fn flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    fn update_properties(&mut self) {
+        self.properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_max_row_group_size(self.row_group_size)
+            .build();
+    }
+
+    fn partition(&mut self, date: NaiveDate, kind: EventKind) -> anyhow::Result<&mut Partition> {
+        if !self.partitions.contains_key(&(date, kind)) {
+            let columns = schema(kind)
+                .filter(|_| ARCHIVED.contains(&kind))
+                .ok_or_else(|| anyhow::anyhow!("no parquet schema for {}", kind.as_str()))?;
+            let path = self
+                .dir
+                .join(format!("date={date}"))
+                .join(format!("type={}", kind.as_str()))
+                .join(format!(
+                    "part-{}-{}.parquet",
+                    Utc::now().format("%Y%m%d-%H%M%S%.3f"),
+                    self.sequence
+                ));
+            self.sequence += 1;
+            self.partitions.insert(
+                (date, kind),
+                Partition {
+                    schema: Arc::new(arrow_schema(&columns)),
+                    columns,
+                    rows: Vec::new(),
+                    writer: None,
+                    path,
+                    dropped: 0,
+                },
+            );
+        }
+        Ok(self
+            .partitions
+            .get_mut(&(date, kind))
+            .expect("inserted above"))
+    }
+
+    /// Write every buffered row, then close the partitions of past days.
+    fn write_buffered(&mut self) -> anyhow::Result<()> {
+        self.last_flush = Instant::now();
+        for partition in self.partitions.values_mut() {
+            partition.write_row_group(&self.properties)?;
+        }
+        if let Some(latest) = self.latest_date {
+            let past = self
+                .partitions
+                .keys()
+                .filter(|(date, _)| *date < latest)
+                .copied()
+                .collect::<Vec<_>>();
+            for key in past {
+                if let Some(mut partition) = self.partitions.remove(&key) {
+                    let closed = partition.close(&self.properties);
+                    self.dropped += partition.dropped;
+                    closed?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Rows dropped so far because their row group failed to encode or write.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+            + self
+                .partitions
+                .values()
+                .map(|partition| partition.dropped)
+                .sum::<u64>()
+    }
+}
+
+impl Sink for ParquetArchive {
+    fn write(&mut self, record: &Record) -> anyhow::Result<()> {
+        let date = record
+            .value
+            .get("timestamp")
+            .and_then(Value::as_i64)
+            .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+            .unwrap_or_else(Utc::now)
+            .date_naive();
+        self.latest_date = self.latest_date.max(Some(date));
+
+        let row_group_size = self.row_group_size;
+        let properties = self.properties.clone();
+        let partition = self.partition(date, record.kind)?;
+        let row = partition
+            .columns
+            .iter()
+            .map(|column| {
+                record
+                    .value
+                    .get(record_key(column.name))
+                    .cloned()
+                    .unwrap_or(Value::Null)
+            })
+            .collect();
+        partition.rows.push(row);
+        if partition.rows.len() >= row_group_size {
+            partition.write_row_group(&properties)?;
+        }
+
+        if self.last_flush.elapsed() >= self.flush_interval {
+            self.write_buffered()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        if self.last_flush.elapsed() >= self.flush_interval {
+            self.write_buffered()?;
+        }
+        Ok(())
+    }
+
+    /// Closes every partition even if one fails, returning the last error or
+    /// else the number of rows dropped since the archive was opened.
+    fn close(&mut self) -> anyhow::Result<()> {
+        let mut result = Ok(());
+        for (_, mut partition) in self.partitions.drain() {
+            if let Err(error) = partition.close(&self.properties) {
+                result = Err(error);
+            }
+            self.dropped += partition.dropped;
+        }
+        match std::mem::take(&mut self.dropped) {
+            0 => result,
+            dropped => Err(result
+                .err()
+                .unwrap_or_else(|| anyhow::anyhow!("failed to write some row groups"))
+                .context(format!("{dropped} parquet rows dropped"))),
+        }
+    }
+}
+
+impl Drop for ParquetArchive {
+    fn drop(&mut self) {
+        if let Err(error) = self.close() {
+            warn!("failed to close parquet files: {error:#}");
+        }
+    }
+}
+
+/// A [`ParquetArchive`] on its own thread, so encoding and file writes never
+/// block the stream loop. When the queue is full `write` waits for the writer
+/// instead of dropping trades.
+pub struct ParquetSink {
+    tx: Option<SyncSender<Record>>,
+    writer: Option<JoinHandle<anyhow::Result<()>>>,
+    full: u64,
+}
+
+impl ParquetSink {
+    pub fn spawn(mut archive: ParquetArchive) -> Self {
+        let (tx, rx) = mpsc::sync_channel::<Record>(PARQUET_QUEUE_CAPACITY);
+        let writer = thread::spawn(move || {
+            loop {
+                let result = match rx.recv_timeout(Duration::from_secs(1)) {
+                    Ok(record) => archive.write(&record),
+                    Err(RecvTimeoutError::Timeout) => archive.flush(),
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                if let Err(error) = result {
+                    error!("failed to write parquet: {error:#}");
+                }
+            }
+            archive.close()
+        });
+        Self {
+            tx: Some(tx),
+            writer: Some(writer),
+            full: 0,
+        }
+    }
+}
+
+impl Sink for ParquetSink {
+    fn write(&mut self, record: &Record) -> anyhow::Result<()> {
+        let Some(tx) = &self.tx else {
+            anyhow::bail!("parquet sink closed");
+        };
+        match tx.try_send(record.clone()) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(record)) => {
+                self.full += 1;
+                if self.full.is_power_of_two() {
+                    warn!(
+                        "parquet sink full {} times, waiting for the writer",
+                        self.full
+                    );
+                }
+                tx.send(record)
+                    .map_err(|_| anyhow::anyhow!("parquet writer stopped"))
+            }
+            Err(TrySendError::Disconnected(_)) => anyhow::bail!("parquet writer stopped"),
+        }
+    }
+
+    /// Writes what is queued plus the file footers and waits for the writer
+    /// thread, returning the archive's close error with the dropped row count.
+    fn close(&mut self) -> anyhow::Result<()> {
+        self.tx.take();
+        match self.writer.take() {
+            Some(writer) => writer
+                .join()
+                .map_err(|_| anyhow::anyhow!("parquet writer panicked"))?,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ParquetSink {
+    fn drop(&mut self) {
+        if let Err(error) = self.close() {
+            warn!("failed to close parquet files: {error:#}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn close_reports_dropped_rows() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("parquet-dropped-{}", std::process::id()));
+        let mut sink = ParquetSink::spawn(ParquetArchive::new(&dir)?.row_group_size(2));
+        // the required columns are missing, so the row group fails to encode
+        for _ in 0..3 {
+            sink.write(&Record {
+                kind: EventKind::Trade,
+                value: json!({ "timestamp": 1_700_000_000 }),
+            })?;
+        }
+        let error = sink.close().expect_err("rows were dropped");
+        fs::remove_dir_all(&dir)?;
+        assert!(
+            format!("{error:#}").starts_with("3 parquet rows dropped"),
+            "{error:#}"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,732 @@
+use futures::pin_mut;
+use log::{error, info, warn};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::{self, Receiver, Sender, error::TrySendError};
+use tokio_postgres::binary_copy::BinaryCopyInWriter;
+use tokio_postgres::types::{ToSql, Type};
+use tokio_postgres::{Client, NoTls, Transaction};
+
+use super::rows::{MigrationRow, SlotRow, StoreRow, TokenRow, TradeRow};
+use super::{Record, Sink};
+
+/// `(version, sql)`, applied in order and recorded in `schema_migrations`.
+const MIGRATIONS: &[(i32, &str)] = &[(1, include_str!("../../migrations/postgres/0001_init.sql"))];
+
+pub const POSTGRES_BATCH_SIZE: usize = 5000;
+pub const POSTGRES_BATCH_INTERVAL: Duration = Duration::from_secs(1);
+const POSTGRES_QUEUE_CAPACITY: usize = 100_000;
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+// COPY can't skip conflicting rows, so each batch is copied into session-local
+// staging tables and moved over with INSERT ... ON CONFLICT.
+const STAGING: &str = "
+    CREATE TEMP TABLE tokens_stage (LIKE tokens) ON COMMIT DELETE ROWS;
+    CREATE TEMP TABLE trades_stage (LIKE trades) ON COMMIT DELETE ROWS;
+    CREATE TEMP TABLE migrations_stage (LIKE migrations) ON COMMIT DELETE ROWS;
+    CREATE TEMP TABLE slots_stage (LIKE slots) ON COMMIT DELETE ROWS;
+";
+
+fn time(block_time: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(block_time.max(0) as u64)
+}
+
+async fn copy_rows(
+    tx: &Transaction<'_>,
+    statement: &str,
+    types: &[Type],
+    rows: &[Vec<&(dyn ToSql + Sync)>],
+) -> anyhow::Result<()> {
+    if rows.is_empty() {
+        return Ok(());
+    }
+    let writer = BinaryCopyInWriter::new(tx.copy_in(statement).await?, types);
+    pin_mut!(writer);
+    for row in rows {
+        writer.as_mut().write(row).await?;
+    }
+    writer.finish().await?;
+    Ok(())
+}
+
+/// Data exceptions (class 22, e.g. a NUL byte in a text) and integrity
+/// constraint violations (class 23) fail the same way on every retry.
+fn is_rejected(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<tokio_postgres::Error>()
+        .and_then(tokio_postgres::Error::code)
+        .is_some_and(|code| matches!(&code.code()[..2], "22" | "23"))
+}
+
+fn append_ndjson(path: &Path, records: &[Record]) -> anyhow::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    for record in records {
+        let mut line = serde_json::to_vec(&record.to_json())?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// Owns the connection and the spool, runs on the sink's writer thread.
+struct PostgresWriter {
+    url: String,
+    spool: PathBuf,
+    rejects: PathBuf,
+    client: Option<Client>,
+    backoff: Duration,
+    retry_at: Option<Instant>,
+}
+
+impl PostgresWriter {
+    async fn connect(&self) -> anyhow::Result<Client> {
+        let (mut client, connection) = tokio_postgres::connect(&self.url, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(error) = connection.await {
+                warn!("postgres connection closed: {error}");
+            }
+        });
+
+        client
+            .batch_execute(
+                "CREATE TABLE IF NOT EXISTS schema_migrations (
+                    version INTEGER PRIMARY KEY,
+                    applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+                )",
+            )
+            .await?;
+        for (version, sql) in MIGRATIONS {
+            let tx = client.transaction().await?;
+            // serializes concurrent writers migrating the same database
+            tx.batch_execute("LOCK TABLE schema_migrations IN EXCLUSIVE MODE")
+                .await?;
+            let applied = tx
+                .query_opt(
+                    "SELECT 1 FROM schema_migrations WHERE version = $1",
+                    &[version],
+                )
+                .await?
+                .is_some();
+            if !applied {
+                tx.batch_execute(sql).await?;
+                tx.execute(
+                    "INSERT INTO schema_migrations (version) VALUES ($1)",
+                    &[version],
+                )
+                .await?;
+                info!("postgres schema migrated to version {version}");
+            }
+            tx.commit().await?;
+        }
+        client.batch_execute(STAGING).await?;
+        Ok(client)
+    }
+
+    async fn insert(&mut self, records: &[Record]) -> anyhow::Result<()> {
+        if self.client.is_none() {
+            self.client = Some(self.connect().await?);
+        }
+        let client = self.client.as_mut().expect("connected above");
+
+        let mut tokens = Vec::new();
+        let mut trades = Vec::new();
+        let mut migrations = Vec::new();
+        // one row per slot, the latest status wins
+        let mut slots = BTreeMap::new();
+        for row in records.iter().filter_map(StoreRow::from_record) {
+            match row {
+                StoreRow::Token(token) => tokens.push(token),
+                StoreRow::Trade(trade) => trades.push(trade),
+                StoreRow::Migration(migration) => migrations.push(migration),
+                StoreRow::Slot(slot) => {
+                    slots.insert(slot.slot, slot);
+                }
+            }
+        }
+
+        let tx = client.transaction().await?;
+        copy_tokens(&tx, &tokens).await?;
+        copy_trades(&tx, &trades).await?;
+        copy_migrations(&tx, &migrations).await?;
+        copy_slots(&tx, &slots.into_values().collect::<Vec<_>>()).await?;
+        tx.batch_execute(
+            "INSERT INTO tokens SELECT * FROM tokens_stage ON CONFLICT DO NOTHING;
+             INSERT INTO trades SELECT * FROM trades_stage ON CONFLICT DO NOTHING;
+             INSERT INTO migrations SELECT * FROM migrations_stage ON CONFLICT DO NOTHING;
+             INSERT INTO slots SELECT * FROM slots_stage
+                 ON CONFLICT (slot) DO UPDATE SET parent = excluded.parent, status = excluded.status;",
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Inserts the batch, or if the database rejects it, each record on its own
+    /// with the rejected ones moved to the reject file.
+    async fn insert_or_reject(&mut self, records: &[Record]) -> anyhow::Result<()> {
+        match self.insert(records).await {
+            Err(error) if is_rejected(&error) => {
+                warn!(
+                    "postgres rejected a batch of {}, inserting one by one: {error:#}",
+                    records.len()
+                );
+                for record in records {
+                    match self.insert(std::slice::from_ref(record)).await {
+                        Err(error) if is_rejected(&error) => {
+                            error!(
+                                "postgres rejected a {} record, moved to {}: {error:#}",
+                                record.kind.as_str(),
+                                self.rejects.display()
+                            );
+                            append_ndjson(&self.rejects, std::slice::from_ref(record))?;
+                        }
+                        result => result?,
+                    }
+                }
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Write the spooled records; the spool is removed only once all of them are in.
+    /// Replaying twice is harmless, conflicting rows are skipped.
+    async fn replay_spool(&mut self) -> anyhow::Result<()> {
+        if !self.spool.exists() {
+            return Ok(());
+        }
+        let reader = BufReader::new(fs::File::open(&self.spool)?);
+        let mut batch = Vec::with_capacity(POSTGRES_BATCH_SIZE);
+        let mut replayed = 0;
+        for line in reader.lines() {
+            let line = line?;
+            match serde_json::from_str(&line)
+                .map_err(anyhow::Error::from)
+                .and_then(Record::from_json)
+            {
+                Ok(record) => batch.push(record),
+                Err(error) => warn!("skipping bad spool line: {error:#}"),
+            }
+            if batch.len() >= POSTGRES_BATCH_SIZE {
+                self.insert_or_reject(&batch).await?;
+                replayed += batch.len();
+                batch.clear();
+            }
+        }
+        self.insert_or_reject(&batch).await?;
+        replayed += batch.len();
+        fs::remove_file(&self.spool)?;
+        info!("replayed {replayed} spooled records into postgres");
+        Ok(())
+    }
+
+    /// Never fails: while the database is unreachable, batches go to the spool file
+    /// and the next attempt is delayed with exponential backoff. Records the
+    /// database rejects go to the reject file instead of blocking the ones after.
+    async fn write(&mut self, records: &[Record]) {
+        if records.is_empty() && !self.spool.exists() {
+            return;
+        }
+        let result = if self.retry_at.is_some_and(|at| Instant::now() < at) {
+            Err(None)
+        } else {
+            match self.replay_spool().await {
+                Ok(()) => self.insert_or_reject(records).await.map_err(Some),
+                Err(error) => Err(Some(error)),
+            }
+        };
+        match result {
+            Ok(()) => {
+                self.backoff = MIN_BACKOFF;
+                self.retry_at = None;
+            }
+            Err(error) => {
+                if let Some(error) = error {
+                    self.client = None;
+                    warn!(
+                        "postgres write failed, retrying in {:?}: {error:#}",
+                        self.backoff
+                    );
+                    self.retry_at = Some(Instant::now() + self.backoff);
+                    self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+                }
+                if let Err(error) = append_ndjson(&self.spool, records) {
+                    error!(
+                        "failed to spool {} records, dropped: {error:#}",
+                        records.len()
+                    );
+                }
+            }
+        }
+    }
+
+    async fn run(mut self, mut rx: Receiver<Record>) {
+        let mut batch = Vec::with_capacity(POSTGRES_BATCH_SIZE);
+        let mut deadline = Instant::now() + POSTGRES_BATCH_INTERVAL;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let closed = match tokio::time::timeout(remaining, rx.recv()).await {
+                Ok(Some(record)) => {
+                    batch.push(record);
+                    false
+                }
+                Ok(None) => true,
+                Err(_) => false,
+            };
+            if closed || batch.len() >= POSTGRES_BATCH_SIZE || Instant::now() >= deadline {
+                self.write(&batch).await;
+                batch.clear();
+                deadline = Instant::now() + POSTGRES_BATCH_INTERVAL;
+            }
+            if closed {
+                break;
+            }
+        }
+    }
+}
+
+async fn copy_tokens(tx: &Transaction<'_>, tokens: &[TokenRow]) -> anyhow::Result<()> {
+    let values = tokens
+        .iter()
+        .map(|token| {
+            (
+                token.mint.to_string(),
+                token.creator.to_string(),
+                token.user.to_string(),
+                token.bonding_curve.to_string(),
+                token.total_supply as i64,
+                token.slot as i64,
+                time(token.block_time),
+            )
+        })
+        .collect::<Vec<_>>();
+    let rows = tokens
+        .iter()
+        .zip(&values)
+        .map(|(token, value)| -> Vec<&(dyn ToSql + Sync)> {
+            vec![
+                &value.0,
+                &token.name,
+                &token.symbol,
+                &token.uri,
+                &value.1,
+                &value.2,
+                &value.3,
+                &value.4,
+                &value.5,
+                &token.signature,
+                &value.6,
+            ]
+        })
+        .collect::<Vec<_>>();
+    copy_rows(
+        tx,
+        "COPY tokens_stage (mint, name, symbol, uri, creator, wallet, bonding_curve, total_supply, slot, signature, time) FROM STDIN BINARY",
+        &[
+            Type::TEXT,
+            Type::TEXT,
+            Type::TEXT,
+            Type::TEXT,
+            Type::TEXT,
+            Type::TEXT,
+            Type::TEXT,
+            Type::INT8,
+            Type::INT8,
+            Type::TEXT,
+            Type::TIMESTAMPTZ,
+        ],
+        &rows,
+    )
+    .await
+}
+
+async fn copy_trades(tx: &Transaction<'_>, trades: &[TradeRow]) -> anyhow::Result<()> {
+    let values = trades
+        .iter()
+        .map(|trade| {
+            (
+                time(trade.block_time),
+                trade.event_index as i32,
+                trade.slot as i64,
+                trade.mint.to_string(),
+                trade.wallet.to_string(),
+                trade.pool.to_string(),
+                [
+                    trade.sol_amount as i64,
+                    trade.token_amount as i64,
+                    trade.fee as i64,
+                ],
+            )
+        })
+        .collect::<Vec<_>>();
+    let rows = trades
+        .iter()
+        .zip(&values)
+        .map(|(trade, value)| -> Vec<&(dyn ToSql + Sync)> {
+            vec![
+                &value.0,
+                &trade.signature,
+                &value.1,
+                &value.2,
+                &trade.venue,
+                &value.3,
+                &value.4,
+                &value.5,
+                &trade.is_buy,
+                &value.6[0],
+                &value.6[1],
+                &value.6[2],
+            ]
+        })
+        .collect::<Vec<_>>();
+    copy_rows(
+        tx,
+        "COPY trades_stage (time, signature, event_index, slot, venue, mint, wallet, pool, is_buy, sol_amount, token_amount, fee) FROM STDIN BINARY",
+        &[
+            Type::TIMESTAMPTZ,
+            Type::TEXT,
+            Type::INT4,
+            Type::INT8,
+            Type::TEXT,
+            Type::TEXT,
+            Type::TEXT,
+            Type::TEXT,
+            Type::BOOL,
+            Type::INT8,
+            Type::INT8,
+            Type::INT8,
+        ],
+        &rows,
+    )
+    .await
+}
+
+async fn copy_migrations(tx: &Transaction<'_>, migrations: &[MigrationRow]) -> anyhow::Result<()> {
+    let values = migrations
+        .iter()
+        .map(|migration| {
+            (
+                time(migration.block_time),
+                migration.event_index as i32,
+                migration.slot as i64,
+                migration.mint.to_string(),
+                migration.user.to_string(),
+                migration.bonding_curve.map(|curve| curve.to_string()),
+                migration.pool.map(|pool| pool.to_string()),
+                migration.base_amount.map(|amount| amount as i64),
+                migration.quote_amount.map(|amount| amount as i64),
+            )
+        })
+        .collect::<Vec<_>>();
+    let rows = migrations
+        .iter()
+        .zip(&values)
+        .map(|(migration, value)| -> Vec<&(dyn ToSql + Sync)> {
+            vec![
+                &value.0,
+                &migration.signature,
+                &value.1,
+                &value.2,
+                &migration.kind,
+                &value.3,
+                &value.4,
+                &value.5,
+                &value.6,
+                &value.7,
+                &value.8,
+            ]
+        })
+        .collect::<Vec<_>>();
+    copy_rows(
+        tx,
+        "COPY migrations_stage (time, signature, event_index, slot, kind, mint, wallet, bonding_curve, pool, base_amount, quote_amount) FROM STDIN BINARY",
+        &[
+            Type::TIMESTAMPTZ,
+            Type::TEXT,
+            Type::INT4,
+            Type::INT8,
+            Type::TEXT,
+            Type::TEXT,
+            Type::TEXT,
+            Type::TEXT,
+            Type::TEXT,
+            Type::INT8,
+            Type::INT8,
+        ],
+        &rows,
+    )
+    .await
+}
+
+async fn copy_slots(tx: &Transaction<'_>, slots: &[SlotRow]) -> anyhow::Result<()> {
+    let values = slots
+        .iter()
+        .map(|slot| (slot.slot as i64, slot.parent.map(|parent| parent as i64)))
+        .collect::<Vec<_>>();
+    let rows = slots
+        .iter()
+        .zip(&values)
+        .map(|(slot, value)| -> Vec<&(dyn ToSql + Sync)> { vec![&value.0, &value.1, &slot.status] })
+        .collect::<Vec<_>>();
+    copy_rows(
+        tx,
+        "COPY slots_stage (slot, parent, status) FROM STDIN BINARY",
+        &[Type::INT8, Type::INT8, Type::TEXT],
+        &rows,
+    )
+    .await
+}
+
+/// Writes the same rows as `SqliteSink` to PostgreSQL (TimescaleDB optional) with
+/// binary COPY, on its own thread, in batches of up to `POSTGRES_BATCH_SIZE` records
+/// or every `POSTGRES_BATCH_INTERVAL`. Replayed events are skipped on their
+/// (signature, event index) key.
+///
+/// While the database is down batches are appended to the `spool` NDJSON file and
+/// replayed, oldest first, once a write succeeds again. Records the database
+/// rejects as invalid (SQLSTATE class 22 or 23) are appended to
+/// `<spool>.rejected.ndjson` and skipped.
+pub struct PostgresSink {
+    tx: Option<Sender<Record>>,
+    writer: Option<JoinHandle<()>>,
+    dropped: u64,
+}
+
+impl PostgresSink {
+    pub fn new(url: &str, spool: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let writer = PostgresWriter {
+            url: url.to_string(),
+            spool: spool.as_ref().to_path_buf(),
+            rejects: spool.as_ref().with_extension("rejected.ndjson"),
+            client: None,
+            backoff: MIN_BACKOFF,
+            retry_at: None,
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let (tx, rx) = mpsc::channel(POSTGRES_QUEUE_CAPACITY);
+        let writer = thread::spawn(move || runtime.block_on(writer.run(rx)));
+        Ok(Self {
+            tx: Some(tx),
+            writer: Some(writer),
+            dropped: 0,
+        })
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+impl Sink for PostgresSink {
+    fn write(&mut self, record: &Record) -> anyhow::Result<()> {
+        let Some(tx) = &self.tx else {
+            anyhow::bail!("postgres sink closed");
+        };
+        match tx.try_send(record.clone()) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                if self.dropped.is_power_of_two() {
+                    warn!("postgres sink full, {} records dropped", self.dropped);
+                }
+                Ok(())
+            }
+            Err(TrySendError::Closed(_)) => anyhow::bail!("postgres writer stopped"),
+        }
+    }
+
+    /// Writes (or spools) what is queued and waits for the writer thread.
+    fn close(&mut self) -> anyhow::Result<()> {
+        self.tx.take();
+        if let Some(writer) = self.writer.take() {
+            writer
+                .join()
+                .map_err(|_| anyhow::anyhow!("postgres writer panicked"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PostgresSink {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use solana_sdk::pubkey::Pubkey;
+    use std::env;
+
+    fn record(value: serde_json::Value) -> Record {
+        Record::from_json(value).unwrap()
+    }
+
+    fn records(mint: &Pubkey, user: &Pubkey) -> Vec<Record> {
+        let trade = |signature: &str, event_index: u64| {
+            record(json!({
+                "type": "trade",
+                "signature": signature,
+                "eventIndex": event_index,
+                "slot": 11,
+                "timestamp": 1_700_000_001,
+                "mint": mint.to_string(),
+                "user": user.to_string(),
+                "isBuy": true,
+                "solAmount": 1_000_000_000u64,
+                "tokenAmount": 35_000_000_000_000u64,
+                "fee": 9_500_000,
+                "creatorFee": 500_000,
+            }))
+        };
+        vec![
+            record(json!({
+                "type": "create",
+                "signature": "create-sig",
+                "eventIndex": 3,
+                "slot": 10,
+                "timestamp": 1_700_000_000,
+                "mint": mint.to_string(),
+                "name": "Test",
+                "symbol": "TST",
+                "uri": "https://example.com/0.json",
+                "creator": user.to_string(),
+                "user": user.to_string(),
+                "bondingCurve": Pubkey::new_unique().to_string(),
+                "tokenTotalSupply": 1_000_000_000_000_000u64,
+            })),
+            trade("trade-sig", 4),
+            trade("trade-sig", 20_004),
+            record(json!({"type": "slot", "slot": 11, "parent": 10, "status": "processed"})),
+        ]
+    }
+
+    async fn count(client: &Client, table: &str) -> i64 {
+        client
+            .query_one(&format!("SELECT count(*) FROM {table}"), &[])
+            .await
+            .unwrap()
+            .get(0)
+    }
+
+    /// Needs a disposable database, e.g.
+    /// `POSTGRES_TEST_URL="host=localhost user=postgres dbname=test" cargo test -- --ignored --test-threads=1`.
+    /// Its pump tables are dropped first.
+    #[tokio::test]
+    #[ignore = "needs POSTGRES_TEST_URL"]
+    async fn spools_while_down_then_replays_and_upserts() {
+        let url = env::var("POSTGRES_TEST_URL").expect("POSTGRES_TEST_URL");
+        let (client, connection) = tokio_postgres::connect(&url, NoTls).await.unwrap();
+        tokio::spawn(connection);
+        client
+            .batch_execute(
+                "DROP TABLE IF EXISTS tokens, trades, migrations, slots, schema_migrations CASCADE",
+            )
+            .await
+            .unwrap();
+
+        let spool = env::temp_dir().join(format!("postgres-spool-{}.ndjson", std::process::id()));
+        let _ = fs::remove_file(&spool);
+        let (mint, user) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let batch = records(&mint, &user);
+
+        // unreachable: everything goes to the spool
+        let mut sink = PostgresSink::new("host=127.0.0.1 port=1 user=postgres", &spool).unwrap();
+        for record in &batch {
+            sink.write(record).unwrap();
+        }
+        // blocks in the sink's own runtime
+        tokio::task::spawn_blocking(move || sink.close())
+            .await
+            .unwrap()
+            .unwrap();
+        let spooled = fs::read_to_string(&spool).unwrap();
+        assert_eq!(spooled.lines().count(), batch.len());
+
+        // the spool is replayed, then the same records again plus a newer slot status
+        let mut sink = PostgresSink::new(&url, &spool).unwrap();
+        for record in &batch {
+            sink.write(record).unwrap();
+        }
+        sink.write(&record(
+            json!({"type": "slot", "slot": 11, "parent": 10, "status": "confirmed"}),
+        ))
+        .unwrap();
+        tokio::task::spawn_blocking(move || sink.close())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(!spool.exists());
+        assert_eq!(count(&client, "tokens").await, 1);
+        assert_eq!(count(&client, "trades").await, 2);
+        assert_eq!(count(&client, "slots").await, 1);
+        let status: String = client
+            .query_one("SELECT status FROM slots WHERE slot = 11", &[])
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(status, "confirmed");
+        let row = client
+            .query_one(
+                "SELECT wallet, sol_amount, fee FROM trades WHERE event_index = 4",
+                &[],
+            )
+            .await
+            .unwrap();
+        assert_eq!(row.get::<_, String>(0), user.to_string());
+        assert_eq!(row.get::<_, i64>(1), 1_000_000_000);
+        assert_eq!(row.get::<_, i64>(2), 10_000_000);
+    }
+
+    /// Needs a disposable database like the test above.
+    #[tokio::test]
+    #[ignore = "needs POSTGRES_TEST_URL"]
+    async fn moves_rejected_records_aside() {
+        let url = env::var("POSTGRES_TEST_URL").expect("POSTGRES_TEST_URL");
+        let (client, connection) = tokio_postgres::connect(&url, NoTls).await.unwrap();
+        tokio::spawn(connection);
+        client
+            .batch_execute(
+                "DROP TABLE IF EXISTS tokens, trades, migrations, slots, schema_migrations CASCADE",
+            )
+            .await
+            .unwrap();
+
+        let spool = env::temp_dir().join(format!("postgres-reject-{}.ndjson", std::process::id()));
+        let rejects = spool.with_extension("rejected.ndjson");
+        let _ = fs::remove_file(&rejects);
+        let (mint, user) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut batch = records(&mint, &user);
+        // postgres text can't hold NUL, so the create is rejected on every retry
+        batch[0].value["name"] = json!("Te\0st");
+
+        let mut sink = PostgresSink::new(&url, &spool).unwrap();
+        for record in &batch {
+            sink.write(record).unwrap();
+        }
+        tokio::task::spawn_blocking(move || sink.close())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(!spool.exists());
+        assert_eq!(count(&client, "tokens").await, 0);
+        assert_eq!(count(&client, "trades").await, 2);
+        assert_eq!(count(&client, "slots").await, 1);
+        let rejected = fs::read_to_string(&rejects).unwrap();
+        fs::remove_file(&rejects).unwrap();
+        assert_eq!(rejected.lines().count(), 1);
+        assert!(rejected.contains(&mint.to_string()));
+    }
+}
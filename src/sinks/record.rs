@@ -0,0 +1,599 @@
+use base64::{Engine, engine::general_purpose};
+use chrono::SecondsFormat;
+use serde_json::{Value, json};
+use solana_sdk::instruction::Instruction;
+
+use super::{EventKind, Record};
+use crate::analytics::candles::Candle;
+use crate::analytics::creators::DevSell;
+use crate::analytics::holders::HolderAlert;
+use crate::analytics::jito::LaunchReport;
+use crate::analytics::lifecycle::LifecycleTransition;
+use crate::analytics::metadata::{OffchainMetadata, ResolvedMetadata};
+use crate::analytics::positions::PositionUpdate;
+use crate::analytics::risk::RiskScore;
+use crate::analytics::snipers::SniperReport;
+use crate::decoder::{Action, DecodedAction, DecodedTransaction};
+use crate::trading::copy::CopySignal;
+use crate::trading::submit::Submission;
+use crate::types::pump_fun::{BuyEvent, CreatePoolEvent, SellEvent};
+use crate::types::raydium::RaydiumSwapLog;
+use crate::types::swap::SwapSide;
+use crate::utils::summary::TransactionSummary;
+
+impl Action {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Action::PumpFunCreate(_) => EventKind::Create,
+            Action::PumpFunTrade(_) => EventKind::Trade,
+            Action::PumpFunComplete(_) => EventKind::Complete,
+            Action::PumpAmmBuy { .. } => EventKind::Buy,
+            Action::PumpAmmSell { .. } => EventKind::Sell,
+            Action::PumpAmmCreatePool(_) => EventKind::CreatePool,
+            Action::RaydiumSwap(_) => EventKind::RaydiumSwap,
+            Action::TokenTransfer { .. } => EventKind::TokenTransfer,
+            Action::TokenMintTo { .. } => EventKind::TokenMintTo,
+            Action::TokenBurn { .. } => EventKind::TokenBurn,
+            Action::SystemTransfer { .. } => EventKind::SystemTransfer,
+            Action::SystemCreateAccount { .. } => EventKind::SystemCreateAccount,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        match self {
+            Action::PumpFunCreate(event) => json!({
+                "name": event.name,
+                "symbol": event.symbol,
+                "uri": event.uri,
+                "mint": event.mint.to_string(),
+                "bondingCurve": event.bonding_curve.to_string(),
+                "user": event.user.to_string(),
+                "creator": event.creator.to_string(),
+                "timestamp": event.timestamp,
+                "virtualTokenReserves": event.virtual_token_reserves,
+                "virtualSolReserves": event.virtual_sol_reserves,
+                "realTokenReserves": event.real_token_reserves,
+                "tokenTotalSupply": event.token_total_supply,
+            }),
+            Action::PumpFunTrade(event) => json!({
+                "mint": event.mint.to_string(),
+                "solAmount": event.sol_amount,
+                "tokenAmount": event.token_amount,
+                "isBuy": event.is_buy,
+                "user": event.user.to_string(),
+                "timestamp": event.timestamp,
+                "virtualSolReserves": event.virtual_sol_reserves,
+                "virtualTokenReserves": event.virtual_token_reserves,
+                "realSolReserves": event.real_sol_reserves,
+                "realTokenReserves": event.real_token_reserves,
+                "feeRecipient": event.fee_recipient.to_string(),
+                "feeBasisPoints": event.fee_basis_points,
+                "fee": event.fee,
+                "creator": event.creator.to_string(),
+                "creatorFeeBasisPoints": event.creator_fee_basis_points,
+                "creatorFee": event.creator_fee,
+            }),
+            Action::PumpFunComplete(event) => json!({
+                "user": event.user.to_string(),
+                "mint": event.mint.to_string(),
+                "bondingCurve": event.bonding_curve.to_string(),
+                "timestamp": event.timestamp,
+            }),
+            Action::PumpAmmBuy {
+                event,
+                base_mint,
+                quote_mint,
+            } => {
+                let mut value = buy_json(event);
+                value["baseMint"] = json!(base_mint.to_string());
+                value["quoteMint"] = json!(quote_mint.to_string());
+                value
+            }
+            Action::PumpAmmSell {
+                event,
+                base_mint,
+                quote_mint,
+            } => {
+                let mut value = sell_json(event);
+                value["baseMint"] = json!(base_mint.to_string());
+                value["quoteMint"] = json!(quote_mint.to_string());
+                value
+            }
+            Action::PumpAmmCreatePool(event) => create_pool_json(event),
+            Action::RaydiumSwap(swap) => {
+                let (amount_in, amount_out) = swap.amounts();
+                let (pool_coin, pool_pc) = swap.pool_amounts();
+                json!({
+                    "amm": swap.amm.to_string(),
+                    "user": swap.user.to_string(),
+                    "coinMint": swap.coin_mint.map(|mint| mint.to_string()),
+                    "pcMint": swap.pc_mint.map(|mint| mint.to_string()),
+                    "baseIn": matches!(swap.log, RaydiumSwapLog::BaseIn(_)),
+                    "direction": swap.direction(),
+                    "amountIn": amount_in,
+                    "amountOut": amount_out,
+                    "poolCoin": pool_coin,
+                    "poolPc": pool_pc,
+                })
+            }
+            Action::TokenTransfer {
+                source,
+                destination,
+                authority,
+                mint,
+                amount,
+            } => json!({
+                "source": source.to_string(),
+                "destination": destination.to_string(),
+                "authority": authority.to_string(),
+                "mint": mint.map(|mint| mint.to_string()),
+                "amount": amount,
+            }),
+            Action::TokenMintTo {
+                mint,
+                account,
+                amount,
+            }
+            | Action::TokenBurn {
+                mint,
+                account,
+                amount,
+            } => json!({
+                "mint": mint.to_string(),
+                "account": account.to_string(),
+                "amount": amount,
+            }),
+            Action::SystemTransfer { from, to, lamports } => json!({
+                "from": from.to_string(),
+                "to": to.to_string(),
+                "lamports": lamports,
+            }),
+            Action::SystemCreateAccount {
+                from,
+                to,
+                lamports,
+                space,
+                owner,
+            } => json!({
+                "from": from.to_string(),
+                "to": to.to_string(),
+                "lamports": lamports,
+                "space": space,
+                "owner": owner.to_string(),
+            }),
+        }
+    }
+}
+
+fn buy_json(event: &BuyEvent) -> Value {
+    json!({
+        "timestamp": event.timestamp,
+        "baseAmountOut": event.base_amount_out,
+        "maxQuoteAmountIn": event.max_quote_amount_in,
+        "userBaseTokenReserves": event.user_base_token_reserves,
+        "userQuoteTokenReserves": event.user_quote_token_reserves,
+        "poolBaseTokenReserves": event.pool_base_token_reserves,
+        "poolQuoteTokenReserves": event.pool_quote_token_reserves,
+        "quoteAmountIn": event.quote_amount_in,
+        "lpFeeBasisPoints": event.lp_fee_basis_points,
+        "lpFee": event.lp_fee,
+        "protocolFeeBasisPoints": event.protocol_fee_basis_points,
+        "protocolFee": event.protocol_fee,
+        "quoteAmountInWithLpFee": event.quote_amount_in_with_lp_fee,
+        "userQuoteAmountIn": event.user_quote_amount_in,
+        "pool": event.pool.to_string(),
+        "user": event.user.to_string(),
+        "userBaseTokenAccount": event.user_base_token_account.to_string(),
+        "userQuoteTokenAccount": event.user_quote_token_account.to_string(),
+        "protocolFeeRecipient": event.protocol_fee_recipient.to_string(),
+        "protocolFeeRecipientTokenAccount": event.protocol_fee_recipient_token_account.to_string(),
+        "coinCreator": event.coin_creator.to_string(),
+        "coinCreatorFeeBasisPoints": event.coin_creator_fee_basis_points,
+        "coinCreatorFee": event.coin_creator_fee,
+    })
+}
+
+fn sell_json(event: &SellEvent) -> Value {
+    json!({
+        "timestamp": event.timestamp,
+        "baseAmountIn": event.base_amount_in,
+        "minQuoteAmountOut": event.min_quote_amount_out,
+        "userBaseTokenReserves": event.user_base_token_reserves,
+        "userQuoteTokenReserves": event.user_quote_token_reserves,
+        "poolBaseTokenReserves": event.pool_base_token_reserves,
+        "poolQuoteTokenReserves": event.pool_quote_token_reserves,
+        "quoteAmountOut": event.quote_amount_out,
+        "lpFeeBasisPoints": event.lp_fee_basis_points,
+        "lpFee": event.lp_fee,
+        "protocolFeeBasisPoints": event.protocol_fee_basis_points,
+        "protocolFee": event.protocol_fee,
+        "quoteAmountOutWithoutLpFee": event.quote_amount_out_without_lp_fee,
+        "userQuoteAmountOut": event.user_quote_amount_out,
+        "pool": event.pool.to_string(),
+        "user": event.user.to_string(),
+        "userBaseTokenAccount": event.user_base_token_account.to_string(),
+        "userQuoteTokenAccount": event.user_quote_token_account.to_string(),
+        "protocolFeeRecipient": event.protocol_fee_recipient.to_string(),
+        "protocolFeeRecipientTokenAccount": event.protocol_fee_recipient_token_account.to_string(),
+        "coinCreator": event.coin_creator.to_string(),
+        "coinCreatorFeeBasisPoints": event.coin_creator_fee_basis_points,
+        "coinCreatorFee": event.coin_creator_fee,
+    })
+}
+
+fn create_pool_json(event: &CreatePoolEvent) -> Value {
+    json!({
+        "timestamp": event.timestamp,
+        "index": event.index,
+        "creator": event.creator.to_string(),
+        "baseMint": event.base_mint.to_string(),
+        "quoteMint": event.quote_mint.to_string(),
+        "baseMintDecimals": event.base_mint_decimals,
+        "quoteMintDecimals": event.quote_mint_decimals,
+        "baseAmountIn": event.base_amount_in,
+        "quoteAmountIn": event.quote_amount_in,
+        "poolBaseAmount": event.pool_base_amount,
+        "poolQuoteAmount": event.pool_quote_amount,
+        "minimumLiquidity": event.minimum_liquidity,
+        "initialLiquidity": event.initial_liquidity,
+        "lpTokenAmountOut": event.lp_token_amount_out,
+        "poolBump": event.pool_bump,
+        "pool": event.pool.to_string(),
+        "lpMint": event.lp_mint.to_string(),
+        "userBaseTokenAccount": event.user_base_token_account.to_string(),
+        "userQuoteTokenAccount": event.user_quote_token_account.to_string(),
+        "coinCreator": event.coin_creator.to_string(),
+    })
+}
+
+impl Record {
+    /// A decoded action with the transaction it came from.
+    pub fn action(tx: &DecodedTransaction, decoded: &DecodedAction) -> Self {
+        let mut value = decoded.action.to_json();
+        value["slot"] = json!(tx.slot);
+        value["signature"] = json!(tx.signature.to_string());
+        value["instructionIndex"] = json!(decoded.instruction_index);
+        value["innerIndex"] = json!(decoded.inner_index);
+        value["programId"] = json!(decoded.program_id.to_string());
+        value["receivedAt"] = json!(tx.received_at.to_rfc3339_opts(SecondsFormat::Millis, true));
+        Self {
+            kind: decoded.action.kind(),
+            value,
+        }
+    }
+
+    /// Every action of a decoded transaction, in order, with its `eventIndex`.
+    pub fn actions(tx: &DecodedTransaction) -> impl Iterator<Item = Record> + '_ {
+        tx.actions.iter().map(|decoded| {
+            let mut record = Self::action(tx, decoded);
+            record.value["eventIndex"] = json!(event_index(decoded));
+            record
+        })
+    }
+
+    pub fn transaction(summary: &TransactionSummary) -> Self {
+        Self {
+            kind: EventKind::Transaction,
+            value: summary.to_json(),
+        }
+    }
+
+    pub fn launch(report: &LaunchReport) -> Self {
+        Self {
+            kind: EventKind::Launch,
+            value: json!({
+                "mint": report.mint.to_string(),
+                "name": report.name,
+                "symbol": report.symbol,
+                "creator": report.creator.to_string(),
+                "slot": report.slot,
+                "signature": report.signature.to_string(),
+                "bundle": report.bundle.iter().map(|sig| sig.to_string()).collect::<Vec<_>>(),
+                "tipLamports": report.tip_lamports,
+                "bundledBuys": report.bundled_buys.iter().map(|buy| json!({
+                    "signature": buy.signature.to_string(),
+                    "user": buy.user.to_string(),
+                    "solAmount": buy.sol_amount,
+                    "tokenAmount": buy.token_amount,
+                })).collect::<Vec<_>>(),
+                "bundledSol": report.bundled_sol(),
+            }),
+        }
+    }
+
+    pub fn lifecycle(transition: &LifecycleTransition) -> Self {
+        let token = &transition.token;
+        Self {
+            kind: EventKind::Lifecycle,
+            value: json!({
+                "stage": transition.stage.as_str(),
+                "slot": transition.slot,
+                "signature": transition.signature.to_string(),
+                "mint": token.mint.to_string(),
+                "name": token.name,
+                "symbol": token.symbol,
+                "creator": token.creator.to_string(),
+                "bondingCurve": token.bonding_curve.to_string(),
+                "createdSlot": token.created_slot,
+                "createdAt": token.created_at,
+                "virtualSolReserves": token.virtual_sol_reserves,
+                "virtualTokenReserves": token.virtual_token_reserves,
+                "realSolReserves": token.real_sol_reserves,
+                "realTokenReserves": token.real_token_reserves,
+                "progress": token.progress(),
+                "marketCapLamports": token.market_cap(),
+                "holders": token.holders(),
+                "volumeLamports": token.volume,
+                "buys": token.buys,
+                "sells": token.sells,
+                "completeSlot": token.complete_slot,
+                "timeToCompleteSecs": token.time_to_complete(),
+                "slotsToComplete": token.complete_slot.map(|slot| slot - token.created_slot),
+                "pool": token.pool.map(|pool| pool.to_string()),
+                "migratedSlot": token.migrated_slot,
+            }),
+        }
+    }
+
+    pub fn candle(candle: &Candle) -> Self {
+        let mut value = json!({
+            "mint": candle.mint.to_string(),
+            "interval": candle.interval.to_string(),
+            "bucketing": candle.bucketing.as_str(),
+            "start": candle.start,
+            "end": candle.end,
+            "open": candle.open,
+            "high": candle.high,
+            "low": candle.low,
+            "close": candle.close,
+            "volumeLamports": candle.volume,
+            "buyVolumeLamports": candle.buy_volume,
+            "sellVolumeLamports": candle.sell_volume,
+            "tokenVolume": candle.token_volume,
+            "buys": candle.buys,
+            "sells": candle.sells,
+            "venue": candle.venue.as_str(),
+            "firstSlot": candle.first_slot,
+            "lastSlot": candle.last_slot,
+        });
+        if let Some(sol_usd) = candle.sol_usd {
+            value["solUsd"] = json!(sol_usd);
+            value["openUsd"] = json!(candle.open * sol_usd);
+            value["highUsd"] = json!(candle.high * sol_usd);
+            value["lowUsd"] = json!(candle.low * sol_usd);
+            value["closeUsd"] = json!(candle.close * sol_usd);
+        }
+        Self {
+            kind: EventKind::Candle,
+            value,
+        }
+    }
+
+    pub fn dev_sell(sell: &DevSell) -> Self {
+        let token = &sell.token;
+        let profile = &sell.profile;
+        Self {
+            kind: EventKind::DevSell,
+            value: json!({
+                "slot": sell.slot,
+                "signature": sell.signature.to_string(),
+                "mint": token.mint.to_string(),
+                "name": token.name,
+                "symbol": token.symbol,
+                "creator": token.creator.to_string(),
+                "user": sell.wallet.to_string(),
+                "solAmount": sell.sol_amount,
+                "tokenAmount": sell.token_amount,
+                "firstSell": sell.first,
+                "soldShare": token.sold_share(),
+                "secsSinceLaunch": sell.timestamp - token.created_at,
+                "realizedLamports": token.realized(),
+                "creatorLaunched": profile.launched,
+                "creatorCompleted": profile.completed,
+                "creatorDevSold": profile.dev_sold,
+                "creatorAvgSecsToSell": profile.avg_secs_to_sell,
+                "creatorRealizedLamports": profile.realized,
+            }),
+        }
+    }
+
+    pub fn sniper_report(report: &SniperReport) -> Self {
+        let pubkeys = |keys: &[_]| keys.iter().map(ToString::to_string).collect::<Vec<_>>();
+        Self {
+            kind: EventKind::SniperReport,
+            value: json!({
+                "mint": report.mint.to_string(),
+                "name": report.name,
+                "symbol": report.symbol,
+                "creator": report.creator.to_string(),
+                "slot": report.slot,
+                "signature": report.signature.to_string(),
+                "windowSlots": report.window_slots,
+                "buyers": report.buyers.iter().map(|buyer| json!({
+                    "wallet": buyer.wallet.to_string(),
+                    "slot": buyer.slot,
+                    "slotOffset": buyer.slot_offset,
+                    "signature": buyer.first_signature.to_string(),
+                    "solAmount": buyer.sol_amount,
+                    "tokensHeld": buyer.tokens_held,
+                    "funders": pubkeys(&buyer.funders),
+                    "dev": buyer.dev,
+                    "creatorFunded": buyer.creator_funded,
+                    "insider": buyer.insider,
+                    "cluster": buyer.cluster,
+                })).collect::<Vec<_>>(),
+                "clusters": report.clusters.iter().map(|cluster| json!({
+                    "wallets": pubkeys(&cluster.wallets),
+                    "funders": pubkeys(&cluster.funders),
+                })).collect::<Vec<_>>(),
+                "sameSlotBuyers": report.same_slot_buyers(),
+                "sniperShare": report.sniper_share(),
+                "insiderShare": report.insider_share(),
+            }),
+        }
+    }
+
+    pub fn position(update: &PositionUpdate) -> Self {
+        let position = &update.position;
+        let mark = update.mark.as_ref();
+        Self {
+            kind: EventKind::Position,
+            value: json!({
+                "slot": update.slot,
+                "signature": update.signature.to_string(),
+                "wallet": position.wallet.to_string(),
+                "mint": position.mint.to_string(),
+                "venue": update.venue.as_str(),
+                "isBuy": update.side == SwapSide::Buy,
+                "tokenAmount": update.token_amount,
+                "solAmount": update.sol_amount,
+                "fees": update.fees,
+                "tokens": position.tokens,
+                "costLamports": position.cost,
+                "avgCost": position.avg_cost(),
+                "markPrice": mark.map(|mark| mark.price()),
+                "realizedLamports": position.realized,
+                "unrealizedLamports": position.unrealized(mark),
+                "feesLamports": position.fees,
+                "buys": position.buys,
+                "sells": position.sells,
+            }),
+        }
+    }
+
+    pub fn copy_signal(signal: &CopySignal) -> Self {
+        Self {
+            kind: EventKind::CopySignal,
+            value: json!({
+                "slot": signal.slot,
+                "leader": signal.leader.to_string(),
+                "leaderSignature": signal.leader_signature.to_string(),
+                "mint": signal.mint.to_string(),
+                "isBuy": signal.side == SwapSide::Buy,
+                "leaderSolAmount": signal.leader_sol,
+                "leaderTokenAmount": signal.leader_tokens,
+                "wallet": signal.wallet.to_string(),
+                "solAmount": signal.sol,
+                "tokenAmount": signal.tokens,
+                "solLimit": signal.sol_limit,
+                "slippageBps": signal.slippage_bps,
+                "instructions": signal.instructions.iter().map(instruction).collect::<Vec<_>>(),
+            }),
+        }
+    }
+
+    /// A sent transaction.
+    pub fn submission(submission: &Submission) -> Self {
+        let landed = submission.landed.as_ref();
+        Self {
+            kind: EventKind::Submission,
+            value: json!({
+                "signature": submission.signature.to_string(),
+                "landed": landed.is_some(),
+                "slot": landed.map(|landed| landed.slot),
+                "commitment": landed.map(|landed| landed.commitment.to_string()),
+                "error": landed.and_then(|landed| landed.error.clone()),
+                "attempts": submission.attempts,
+                "unitPrice": submission.unit_price,
+                "elapsedMs": submission.elapsed.as_millis() as u64,
+            }),
+        }
+    }
+
+    pub fn holder_concentration(alert: &HolderAlert) -> Self {
+        Self {
+            kind: EventKind::HolderConcentration,
+            value: json!({
+                "mint": alert.mint.to_string(),
+                "slot": alert.slot,
+                "threshold": alert.threshold,
+                "rising": alert.rising,
+                "topShare": alert.top_share,
+                "previousShare": alert.previous_share,
+                "holders": alert.holders,
+                "supply": alert.supply,
+                "top": alert.top.iter().map(|holder| json!({
+                    "owner": holder.owner.to_string(),
+                    "amount": holder.amount,
+                    "share": if alert.supply == 0 {
+                        0.0
+                    } else {
+                        holder.amount as f64 * 100.0 / alert.supply as f64
+                    },
+                })).collect::<Vec<_>>(),
+            }),
+        }
+    }
+
+    pub fn risk(score: &RiskScore) -> Self {
+        Self {
+            kind: EventKind::Risk,
+            value: json!({
+                "mint": score.mint.to_string(),
+                "name": score.name,
+                "symbol": score.symbol,
+                "creator": score.creator.to_string(),
+                "slot": score.slot,
+                "score": score.score,
+                "previousScore": score.previous_score,
+                "level": score.level(),
+                "mintAccountSeen": score.mint_account_seen,
+                "reasons": score.reasons.iter().map(|reason| json!({
+                    "code": reason.code,
+                    "points": reason.points,
+                    "detail": reason.detail,
+                })).collect::<Vec<_>>(),
+            }),
+        }
+    }
+
+    pub fn metadata(resolved: &ResolvedMetadata) -> Self {
+        let metadata = resolved.metadata.as_ref().ok();
+        let field =
+            |get: fn(&OffchainMetadata) -> &Option<String>| metadata.and_then(|m| get(m).clone());
+        Self {
+            kind: EventKind::Metadata,
+            value: json!({
+                "mint": resolved.mint.to_string(),
+                "uri": resolved.uri,
+                "url": resolved.url,
+                "slot": resolved.slot,
+                "cached": resolved.cached,
+                "elapsedMs": resolved.elapsed.as_millis() as u64,
+                "error": resolved.metadata.as_ref().err(),
+                "name": field(|m| &m.name),
+                "symbol": field(|m| &m.symbol),
+                "description": field(|m| &m.description),
+                "image": field(|m| &m.image),
+                "twitter": field(|m| &m.twitter),
+                "telegram": field(|m| &m.telegram),
+                "website": field(|m| &m.website),
+            }),
+        }
+    }
+}
+
+/// Unique per signature and independent of which decoders are registered:
+/// `instructionIndex * 10000` plus the log line the event was read from within
+/// that instruction, or plus 5000 + its inner position for instruction data.
+/// Log messages are capped at 10KB, so a line index stays well below 5000.
+fn event_index(decoded: &DecodedAction) -> u64 {
+    let ordinal = match decoded.log_index {
+        Some(line) => line,
+        None => 5_000 + decoded.inner_index.map_or(0, |position| position + 1),
+    };
+    (decoded.instruction_index * 10_000 + ordinal) as u64
+}
+
+/// The shape web3.js takes for `TransactionInstruction`, data in base64.
+fn instruction(instruction: &Instruction) -> Value {
+    json!({
+        "programId": instruction.program_id.to_string(),
+        "accounts": instruction.accounts.iter().map(|account| json!({
+            "pubkey": account.pubkey.to_string(),
+            "isSigner": account.is_signer,
+            "isWritable": account.is_writable,
+        })).collect::<Vec<_>>(),
+        "data": general_purpose::STANDARD.encode(&instruction.data),
+    })
+}
@@ -0,0 +1,431 @@
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::rotating::{RotatingWriter, RotationConfig};
+use super::{EventKind, Record, Sink};
+use ColumnType::{Bool, I64, Pubkey, Signature, Text, Time, U64};
+
+/// Bumped whenever a column is added, removed or reordered.
+pub const SCHEMA_VERSION: u32 = 1;
+
+pub const SOL_DECIMALS: u8 = 9;
+/// Every pump.fun mint has 6 decimals.
+pub const PUMP_TOKEN_DECIMALS: u8 = 6;
+
+/// The pump.fun / pump AMM events the recorder has a schema for.
+pub const RECORDED: [EventKind; 6] = [
+    EventKind::Create,
+    EventKind::Trade,
+    EventKind::Complete,
+    EventKind::Buy,
+    EventKind::Sell,
+    EventKind::CreatePool,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordFormat {
+    Ndjson,
+    Csv,
+}
+
+impl RecordFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RecordFormat::Ndjson => "ndjson",
+            RecordFormat::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum ColumnType {
+    U64,
+    I64,
+    Bool,
+    Pubkey,
+    Signature,
+    Text,
+    /// RFC 3339, UTC, millisecond precision.
+    Time,
+}
+
+impl ColumnType {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            ColumnType::U64 => "u64",
+            ColumnType::I64 => "i64",
+            ColumnType::Bool => "bool",
+            ColumnType::Pubkey => "pubkey",
+            ColumnType::Signature => "signature",
+            ColumnType::Text => "string",
+            ColumnType::Time => "timestamp",
+        }
+    }
+}
+
+/// Raw integer amounts carry their decimals here instead of being scaled.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Decimals {
+    None,
+    Fixed(u8),
+    /// The decimals are in another column of the same row.
+    Column(&'static str),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Column {
+    pub(crate) name: &'static str,
+    pub(crate) ty: ColumnType,
+    pub(crate) decimals: Decimals,
+}
+
+impl Column {
+    pub(crate) fn nullable(&self) -> bool {
+        self.name == "inner_index"
+    }
+}
+
+const fn col(name: &'static str, ty: ColumnType) -> Column {
+    Column {
+        name,
+        ty,
+        decimals: Decimals::None,
+    }
+}
+
+const fn sol(name: &'static str) -> Column {
+    Column {
+        name,
+        ty: ColumnType::U64,
+        decimals: Decimals::Fixed(SOL_DECIMALS),
+    }
+}
+
+const fn token(name: &'static str) -> Column {
+    Column {
+        name,
+        ty: ColumnType::U64,
+        decimals: Decimals::Fixed(PUMP_TOKEN_DECIMALS),
+    }
+}
+
+const fn amount(name: &'static str, decimals_column: &'static str) -> Column {
+    Column {
+        name,
+        ty: ColumnType::U64,
+        decimals: Decimals::Column(decimals_column),
+    }
+}
+
+/// Leading columns of every file. `block_time` is the event's on-chain clock timestamp.
+const COMMON: [Column; 7] = [
+    col("slot", U64),
+    col("signature", Signature),
+    col("instruction_index", U64),
+    col("inner_index", U64),
+    col("program_id", Pubkey),
+    col("block_time", I64),
+    col("received_at", Time),
+];
+
+const CREATE: [Column; 11] = [
+    col("mint", Pubkey),
+    col("name", Text),
+    col("symbol", Text),
+    col("uri", Text),
+    col("bonding_curve", Pubkey),
+    col("user", Pubkey),
+    col("creator", Pubkey),
+    token("virtual_token_reserves"),
+    sol("virtual_sol_reserves"),
+    token("real_token_reserves"),
+    token("token_total_supply"),
+];
+
+const TRADE: [Column; 15] = [
+    col("mint", Pubkey),
+    col("is_buy", Bool),
+    sol("sol_amount"),
+    token("token_amount"),
+    col("user", Pubkey),
+    sol("virtual_sol_reserves"),
+    token("virtual_token_reserves"),
+    sol("real_sol_reserves"),
+    token("real_token_reserves"),
+    col("fee_recipient", Pubkey),
+    col("fee_basis_points", U64),
+    sol("fee"),
+    col("creator", Pubkey),
+    col("creator_fee_basis_points", U64),
+    sol("creator_fee"),
+];
+
+const COMPLETE: [Column; 3] = [
+    col("mint", Pubkey),
+    col("user", Pubkey),
+    col("bonding_curve", Pubkey),
+];
+
+// pump amm pools are quoted in WSOL, so quote amounts use SOL decimals
+const BUY: [Column; 24] = [
+    col("pool", Pubkey),
+    col("user", Pubkey),
+    col("base_mint", Pubkey),
+    col("quote_mint", Pubkey),
+    token("base_amount_out"),
+    sol("max_quote_amount_in"),
+    sol("quote_amount_in"),
+    sol("quote_amount_in_with_lp_fee"),
+    sol("user_quote_amount_in"),
+    token("user_base_token_reserves"),
+    sol("user_quote_token_reserves"),
+    token("pool_base_token_reserves"),
+    sol("pool_quote_token_reserves"),
+    col("lp_fee_basis_points", U64),
+    sol("lp_fee"),
+    col("protocol_fee_basis_points", U64),
+    sol("protocol_fee"),
+    col("coin_creator_fee_basis_points", U64),
+    sol("coin_creator_fee"),
+    col("user_base_token_account", Pubkey),
+    col("user_quote_token_account", Pubkey),
+    col("protocol_fee_recipient", Pubkey),
+    col("protocol_fee_recipient_token_account", Pubkey),
+    col("coin_creator", Pubkey),
+];
+
+const SELL: [Column; 24] = [
+    col("pool", Pubkey),
+    col("user", Pubkey),
+    col("base_mint", Pubkey),
+    col("quote_mint", Pubkey),
+    token("base_amount_in"),
+    sol("min_quote_amount_out"),
+    sol("quote_amount_out"),
+    sol("quote_amount_out_without_lp_fee"),
+    sol("user_quote_amount_out"),
+    token("user_base_token_reserves"),
+    sol("user_quote_token_reserves"),
+    token("pool_base_token_reserves"),
+    sol("pool_quote_token_reserves"),
+    col("lp_fee_basis_points", U64),
+    sol("lp_fee"),
+    col("protocol_fee_basis_points", U64),
+    sol("protocol_fee"),
+    col("coin_creator_fee_basis_points", U64),
+    sol("coin_creator_fee"),
+    col("user_base_token_account", Pubkey),
+    col("user_quote_token_account", Pubkey),
+    col("protocol_fee_recipient", Pubkey),
+    col("protocol_fee_recipient_token_account", Pubkey),
+    col("coin_creator", Pubkey),
+];
+
+const CREATE_POOL: [Column; 19] = [
+    col("pool", Pubkey),
+    col("index", U64),
+    col("creator", Pubkey),
+    col("base_mint", Pubkey),
+    col("quote_mint", Pubkey),
+    col("base_mint_decimals", U64),
+    col("quote_mint_decimals", U64),
+    amount("base_amount_in", "base_mint_decimals"),
+    amount("quote_amount_in", "quote_mint_decimals"),
+    amount("pool_base_amount", "base_mint_decimals"),
+    amount("pool_quote_amount", "quote_mint_decimals"),
+    col("minimum_liquidity", U64),
+    col("initial_liquidity", U64),
+    col("lp_token_amount_out", U64),
+    col("pool_bump", U64),
+    col("lp_mint", Pubkey),
+    col("user_base_token_account", Pubkey),
+    col("user_quote_token_account", Pubkey),
+    col("coin_creator", Pubkey),
+];
+
+pub(crate) fn schema(kind: EventKind) -> Option<Vec<Column>> {
+    let columns: &[Column] = match kind {
+        EventKind::Create => &CREATE,
+        EventKind::Trade => &TRADE,
+        EventKind::Complete => &COMPLETE,
+        EventKind::Buy => &BUY,
+        EventKind::Sell => &SELL,
+        EventKind::CreatePool => &CREATE_POOL,
+        _ => return None,
+    };
+    Some(COMMON.iter().chain(columns).copied().collect())
+}
+
+/// Column name to the camelCase key of `Record::action`.
+pub(crate) fn record_key(column: &str) -> String {
+    if column == "block_time" {
+        return "timestamp".to_string();
+    }
+    let mut key = String::with_capacity(column.len());
+    let mut upper = false;
+    for c in column.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            key.push(c.to_ascii_uppercase());
+            upper = false;
+        } else {
+            key.push(c);
+        }
+    }
+    key
+}
+
+fn schema_json(kind: EventKind, format: RecordFormat, columns: &[Column]) -> Value {
+    json!({
+        "event": kind.as_str(),
+        "version": SCHEMA_VERSION,
+        "format": format.extension(),
+        "columns": columns.iter().map(|column| {
+            let mut value = json!({
+                "name": column.name,
+                "type": column.ty.as_str(),
+                "nullable": column.nullable(),
+            });
+            match column.decimals {
+                Decimals::None => {}
+                Decimals::Fixed(decimals) => value["decimals"] = json!(decimals),
+                Decimals::Column(name) => value["decimalsColumn"] = json!(name),
+            }
+            value
+        }).collect::<Vec<_>>(),
+    })
+}
+
+fn csv_field(value: &Value) -> String {
+    let text = match value {
+        Value::Null => return String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+/// Pump.fun and pump AMM events with a fixed column set per event type,
+/// one file series per type: `<dir>/<type>-<utc time>-<sequence>.<csv|ndjson>`.
+/// `<type>.schema.json` next to them describes the columns, including the
+/// decimals of each raw integer amount.
+pub struct RecorderSink {
+    dir: PathBuf,
+    format: RecordFormat,
+    rotation: RotationConfig,
+    writers: HashMap<EventKind, (Vec<Column>, RotatingWriter)>,
+}
+
+impl RecorderSink {
+    pub fn new(
+        dir: impl AsRef<Path>,
+        format: RecordFormat,
+        rotation: RotationConfig,
+    ) -> anyhow::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        for kind in RECORDED {
+            let columns = schema(kind).expect("recorded kinds have a schema");
+            fs::write(
+                dir.join(format!("{}.schema.json", kind.as_str())),
+                serde_json::to_vec_pretty(&schema_json(kind, format, &columns))?,
+            )?;
+        }
+        Ok(Self {
+            dir,
+            format,
+            rotation,
+            writers: HashMap::new(),
+        })
+    }
+
+    fn writer(&mut self, kind: EventKind) -> anyhow::Result<&mut (Vec<Column>, RotatingWriter)> {
+        if !self.writers.contains_key(&kind) {
+            let columns = schema(kind)
+                .ok_or_else(|| anyhow::anyhow!("no recorder schema for {}", kind.as_str()))?;
+            let mut writer = RotatingWriter::new(
+                &self.dir,
+                kind.as_str(),
+                self.format.extension(),
+                self.rotation.clone(),
+            )?;
+            if self.format == RecordFormat::Csv {
+                let header = columns
+                    .iter()
+                    .map(|column| column.name)
+                    .collect::<Vec<_>>()
+                    .join(",");
+                writer = writer.header(header);
+            }
+            self.writers.insert(kind, (columns, writer));
+        }
+        Ok(self.writers.get_mut(&kind).expect("inserted above"))
+    }
+}
+
+impl Sink for RecorderSink {
+    fn write(&mut self, record: &Record) -> anyhow::Result<()> {
+        let format = self.format;
+        let (columns, writer) = self.writer(record.kind)?;
+        let values = columns
+            .iter()
+            .map(|column| {
+                record
+                    .value
+                    .get(record_key(column.name))
+                    .cloned()
+                    .unwrap_or(Value::Null)
+            })
+            .collect::<Vec<_>>();
+
+        let line = match format {
+            RecordFormat::Csv => values.iter().map(csv_field).collect::<Vec<_>>().join(","),
+            // written by hand to keep the keys in column order
+            RecordFormat::Ndjson => {
+                let fields = columns
+                    .iter()
+                    .zip(&values)
+                    .map(|(column, value)| format!("\"{}\":{}", column.name, value))
+                    .collect::<Vec<_>>();
+                format!("{{{}}}", fields.join(","))
+            }
+        };
+        writer.write_line(line.as_bytes())
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        for (_, writer) in self.writers.values_mut() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) -> anyhow::Result<()> {
+        // one failing writer must not leave the others unrotated
+        let errors = self
+            .writers
+            .iter_mut()
+            .filter_map(|(kind, (_, writer))| {
+                writer
+                    .close()
+                    .err()
+                    .map(|error| format!("{}: {error:#}", kind.as_str()))
+            })
+            .collect::<Vec<_>>();
+        if !errors.is_empty() {
+            anyhow::bail!(
+                "failed to close {} writers: {}",
+                errors.len(),
+                errors.join("; ")
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+use chrono::{DateTime, Timelike, Utc};
+use flate2::{Compression, write::GzEncoder};
+use log::{error, info};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+
+pub const DEFAULT_MAX_FILE_BYTES: u64 = 100 * 1024 * 1024;
+
+#[derive(Clone, Debug)]
+pub struct RotationConfig {
+    /// Start a new file once the current one reaches this size.
+    pub max_bytes: u64,
+    /// Start a new file at every UTC hour boundary.
+    pub hourly: bool,
+    /// Gzip closed files (`<name>.gz`), in a background thread joined on close.
+    pub gzip: bool,
+}
+
+impl Default for RotationConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_FILE_BYTES,
+            hourly: false,
+            gzip: false,
+        }
+    }
+}
+
+struct OpenFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    written: u64,
+    hour: DateTime<Utc>,
+}
+
+/// Files named `<prefix>-<utc time>-<sequence>.<extension>` in `dir`.
+/// `header` (e.g. a CSV header line) is written at the top of every file.
+pub struct RotatingWriter {
+    dir: PathBuf,
+    prefix: String,
+    extension: String,
+    header: Option<String>,
+    config: RotationConfig,
+    current: Option<OpenFile>,
+    sequence: u64,
+    compressing: Vec<JoinHandle<()>>,
+}
+
+impl RotatingWriter {
+    pub fn new(
+        dir: impl AsRef<Path>,
+        prefix: &str,
+        extension: &str,
+        config: RotationConfig,
+    ) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+            prefix: prefix.to_string(),
+            extension: extension.to_string(),
+            header: None,
+            config,
+            current: None,
+            sequence: 0,
+            compressing: Vec::new(),
+        })
+    }
+
+    pub fn header(mut self, header: String) -> Self {
+        self.header = Some(header);
+        self
+    }
+
+    /// Write one line, `line` without the trailing newline.
+    pub fn write_line(&mut self, line: &[u8]) -> anyhow::Result<()> {
+        let now = Utc::now();
+        let hour = now
+            .with_minute(0)
+            .and_then(|time| time.with_second(0))
+            .and_then(|time| time.with_nanosecond(0))
+            .unwrap_or(now);
+        let rotate = match &self.current {
+            None => true,
+            Some(file) => {
+                file.written >= self.config.max_bytes || (self.config.hourly && file.hour != hour)
+            }
+        };
+        if rotate {
+            self.close_file()?;
+            self.open(now, hour)?;
+        }
+
+        let file = self.current.as_mut().expect("file opened above");
+        file.writer.write_all(line)?;
+        file.writer.write_all(b"\n")?;
+        file.written += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn open(&mut self, now: DateTime<Utc>, hour: DateTime<Utc>) -> anyhow::Result<()> {
+        let path = self.dir.join(format!(
+            "{}-{}-{}.{}",
+            self.prefix,
+            now.format("%Y%m%d-%H%M%S%.3f"),
+            self.sequence,
+            self.extension
+        ));
+        self.sequence += 1;
+        let mut writer = BufWriter::new(File::create(&path)?);
+        let mut written = 0;
+        if let Some(header) = &self.header {
+            writer.write_all(header.as_bytes())?;
+            writer.write_all(b"\n")?;
+            written = header.len() as u64 + 1;
+        }
+        self.current = Some(OpenFile {
+            path,
+            writer,
+            written,
+            hour,
+        });
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        if let Some(file) = &mut self.current {
+            file.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Close the current file, compressing it in the background if configured.
+    /// The next write opens a new one.
+    fn close_file(&mut self) -> anyhow::Result<()> {
+        self.compressing.retain(|handle| !handle.is_finished());
+        let Some(mut file) = self.current.take() else {
+            return Ok(());
+        };
+        file.writer.flush()?;
+        drop(file.writer);
+
+        if self.config.gzip {
+            let path = file.path;
+            self.compressing
+                .push(thread::spawn(move || match gzip_file(&path) {
+                    Ok(gz) => info!("compressed {}", gz.display()),
+                    Err(err) => error!("failed to compress {}: {err:#}", path.display()),
+                }));
+        }
+        Ok(())
+    }
+
+    /// Close the current file and wait for every pending compression, e.g. on
+    /// shutdown, so no half-written `.gz` is left behind.
+    pub fn close(&mut self) -> anyhow::Result<()> {
+        let closed = self.close_file();
+        for handle in self.compressing.drain(..) {
+            if handle.join().is_err() {
+                error!("compression thread panicked");
+            }
+        }
+        closed
+    }
+}
+
+impl Drop for RotatingWriter {
+    fn drop(&mut self) {
+        if let Some(file) = &mut self.current {
+            let _ = file.writer.flush();
+        }
+        for handle in self.compressing.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Compress `path` into `path.gz` and remove the original.
+pub fn gzip_file(path: &Path) -> anyhow::Result<PathBuf> {
+    let mut gz_path = path.as_os_str().to_owned();
+    gz_path.push(".gz");
+    let gz_path = PathBuf::from(gz_path);
+
+    let mut input = File::open(path)?;
+    let mut encoder = GzEncoder::new(File::create(&gz_path)?, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    fs::remove_file(path)?;
+    Ok(gz_path)
+}
@@ -0,0 +1,206 @@
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+
+use super::{EventKind, Record};
+use crate::types::swap::{Venue, bonding_curve_address};
+
+/// The events the database sinks keep.
+pub const STORED: [EventKind; 7] = [
+    EventKind::Create,
+    EventKind::Trade,
+    EventKind::Complete,
+    EventKind::Buy,
+    EventKind::Sell,
+    EventKind::CreatePool,
+    EventKind::Slot,
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenRow {
+    pub mint: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub creator: Pubkey,
+    pub user: Pubkey,
+    pub bonding_curve: Pubkey,
+    pub total_supply: u64,
+    pub slot: u64,
+    pub signature: String,
+    pub block_time: i64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TradeRow {
+    pub signature: String,
+    pub event_index: u64,
+    pub slot: u64,
+    pub block_time: i64,
+    pub venue: String,
+    pub mint: Pubkey,
+    pub wallet: Pubkey,
+    pub pool: Pubkey,
+    pub is_buy: bool,
+    /// Lamports.
+    pub sol_amount: u64,
+    pub token_amount: u64,
+    /// Every fee of the trade, lamports.
+    pub fee: u64,
+}
+
+/// A curve completing (`complete`) or its pump AMM pool being created (`create_pool`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct MigrationRow {
+    pub signature: String,
+    pub event_index: u64,
+    pub slot: u64,
+    pub block_time: i64,
+    pub kind: String,
+    pub mint: Pubkey,
+    pub user: Pubkey,
+    pub bonding_curve: Option<Pubkey>,
+    pub pool: Option<Pubkey>,
+    pub base_amount: Option<u64>,
+    pub quote_amount: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SlotRow {
+    pub slot: u64,
+    pub parent: Option<u64>,
+    pub status: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StoreRow {
+    Token(TokenRow),
+    Trade(TradeRow),
+    Migration(MigrationRow),
+    Slot(SlotRow),
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(Value::as_str).unwrap_or_default()
+}
+
+fn u64_field(value: &Value, key: &str) -> u64 {
+    value.get(key).and_then(Value::as_u64).unwrap_or_default()
+}
+
+fn pubkey_field(value: &Value, key: &str) -> Option<Pubkey> {
+    str_field(value, key).parse().ok()
+}
+
+impl StoreRow {
+    /// The row a record is stored as, `None` for the kinds not in `STORED`
+    /// and for records with a malformed pubkey.
+    pub fn from_record(record: &Record) -> Option<Self> {
+        let value = &record.value;
+        let signature = str_field(value, "signature").to_string();
+        let event_index = u64_field(value, "eventIndex");
+        let slot = u64_field(value, "slot");
+        let block_time = value
+            .get("timestamp")
+            .and_then(Value::as_i64)
+            .unwrap_or_default();
+
+        let row = match record.kind {
+            EventKind::Create => StoreRow::Token(TokenRow {
+                mint: pubkey_field(value, "mint")?,
+                name: str_field(value, "name").to_string(),
+                symbol: str_field(value, "symbol").to_string(),
+                uri: str_field(value, "uri").to_string(),
+                creator: pubkey_field(value, "creator")?,
+                user: pubkey_field(value, "user")?,
+                bonding_curve: pubkey_field(value, "bondingCurve")?,
+                total_supply: u64_field(value, "tokenTotalSupply"),
+                slot,
+                signature,
+                block_time,
+            }),
+            EventKind::Trade => {
+                let mint = pubkey_field(value, "mint")?;
+                StoreRow::Trade(TradeRow {
+                    signature,
+                    event_index,
+                    slot,
+                    block_time,
+                    venue: Venue::PumpFun.as_str().to_string(),
+                    mint,
+                    wallet: pubkey_field(value, "user")?,
+                    pool: bonding_curve_address(&mint),
+                    is_buy: value
+                        .get("isBuy")
+                        .and_then(Value::as_bool)
+                        .unwrap_or_default(),
+                    sol_amount: u64_field(value, "solAmount"),
+                    token_amount: u64_field(value, "tokenAmount"),
+                    fee: u64_field(value, "fee") + u64_field(value, "creatorFee"),
+                })
+            }
+            EventKind::Buy | EventKind::Sell => {
+                let is_buy = record.kind == EventKind::Buy;
+                let (sol_amount, token_amount) = if is_buy {
+                    (
+                        u64_field(value, "quoteAmountIn"),
+                        u64_field(value, "baseAmountOut"),
+                    )
+                } else {
+                    (
+                        u64_field(value, "quoteAmountOut"),
+                        u64_field(value, "baseAmountIn"),
+                    )
+                };
+                StoreRow::Trade(TradeRow {
+                    signature,
+                    event_index,
+                    slot,
+                    block_time,
+                    venue: Venue::PumpAmm.as_str().to_string(),
+                    mint: pubkey_field(value, "baseMint")?,
+                    wallet: pubkey_field(value, "user")?,
+                    pool: pubkey_field(value, "pool")?,
+                    is_buy,
+                    sol_amount,
+                    token_amount,
+                    fee: u64_field(value, "lpFee")
+                        + u64_field(value, "protocolFee")
+                        + u64_field(value, "coinCreatorFee"),
+                })
+            }
+            EventKind::Complete => StoreRow::Migration(MigrationRow {
+                signature,
+                event_index,
+                slot,
+                block_time,
+                kind: record.kind.as_str().to_string(),
+                mint: pubkey_field(value, "mint")?,
+                user: pubkey_field(value, "user")?,
+                bonding_curve: pubkey_field(value, "bondingCurve"),
+                pool: None,
+                base_amount: None,
+                quote_amount: None,
+            }),
+            EventKind::CreatePool => StoreRow::Migration(MigrationRow {
+                signature,
+                event_index,
+                slot,
+                block_time,
+                kind: record.kind.as_str().to_string(),
+                mint: pubkey_field(value, "baseMint")?,
+                user: pubkey_field(value, "creator")?,
+                bonding_curve: None,
+                pool: pubkey_field(value, "pool"),
+                base_amount: Some(u64_field(value, "baseAmountIn")),
+                quote_amount: Some(u64_field(value, "quoteAmountIn")),
+            }),
+            EventKind::Slot => StoreRow::Slot(SlotRow {
+                slot,
+                parent: value.get("parent").and_then(Value::as_u64),
+                status: str_field(value, "status").to_string(),
+            }),
+            _ => return None,
+        };
+        Some(row)
+    }
+}
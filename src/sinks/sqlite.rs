@@ -0,0 +1,388 @@
+use log::{error, warn};
+use rusqlite::{Connection, ErrorCode, OptionalExtension, params};
+use solana_sdk::pubkey::Pubkey;
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::rows::{StoreRow, TokenRow, TradeRow};
+use super::{Record, Sink};
+use crate::db::{self, pubkey};
+
+/// Schema changes, see [`db::open`].
+const MIGRATIONS: &[&str] = &[
+    // 1: initial schema
+    "CREATE TABLE tokens (
+        mint TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        symbol TEXT NOT NULL,
+        uri TEXT NOT NULL,
+        creator TEXT NOT NULL,
+        user TEXT NOT NULL,
+        bonding_curve TEXT NOT NULL,
+        total_supply INTEGER NOT NULL,
+        slot INTEGER NOT NULL,
+        signature TEXT NOT NULL,
+        block_time INTEGER NOT NULL
+    );
+    CREATE INDEX tokens_creator ON tokens (creator);
+
+    CREATE TABLE trades (
+        signature TEXT NOT NULL,
+        event_index INTEGER NOT NULL,
+        slot INTEGER NOT NULL,
+        block_time INTEGER NOT NULL,
+        venue TEXT NOT NULL,
+        mint TEXT NOT NULL,
+        wallet TEXT NOT NULL,
+        pool TEXT NOT NULL,
+        is_buy INTEGER NOT NULL,
+        sol_amount INTEGER NOT NULL,
+        token_amount INTEGER NOT NULL,
+        fee INTEGER NOT NULL,
+        PRIMARY KEY (signature, event_index)
+    );
+    CREATE INDEX trades_mint ON trades (mint, slot);
+    CREATE INDEX trades_wallet ON trades (wallet, slot);
+
+    CREATE TABLE migrations (
+        signature TEXT NOT NULL,
+        event_index INTEGER NOT NULL,
+        slot INTEGER NOT NULL,
+        block_time INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        mint TEXT NOT NULL,
+        user TEXT NOT NULL,
+        bonding_curve TEXT,
+        pool TEXT,
+        base_amount INTEGER,
+        quote_amount INTEGER,
+        PRIMARY KEY (signature, event_index)
+    );
+    CREATE INDEX migrations_mint ON migrations (mint);
+
+    CREATE TABLE slots (
+        slot INTEGER PRIMARY KEY,
+        parent INTEGER,
+        status TEXT NOT NULL
+    );",
+];
+
+/// Tokens, trades, migrations and slots in one SQLite file.
+/// Pubkeys and signatures are stored as base58 text.
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+impl SqliteStore {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Ok(Self {
+            conn: db::open(path, "sqlite", MIGRATIONS)?,
+        })
+    }
+
+    /// Insert in one transaction. Rows already stored (same signature and event
+    /// index) are ignored, so replaying a stream is safe.
+    pub fn insert(&mut self, records: &[Record]) -> anyhow::Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut insert_token = tx.prepare_cached(
+                "INSERT OR IGNORE INTO tokens (mint, name, symbol, uri, creator, user, bonding_curve, total_supply, slot, signature, block_time)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?;
+            let mut insert_trade = tx.prepare_cached(
+                "INSERT OR IGNORE INTO trades (signature, event_index, slot, block_time, venue, mint, wallet, pool, is_buy, sol_amount, token_amount, fee)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            )?;
+            let mut insert_migration = tx.prepare_cached(
+                "INSERT OR IGNORE INTO migrations (signature, event_index, slot, block_time, kind, mint, user, bonding_curve, pool, base_amount, quote_amount)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?;
+            let mut upsert_slot = tx.prepare_cached(
+                "INSERT INTO slots (slot, parent, status) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (slot) DO UPDATE SET parent = excluded.parent, status = excluded.status",
+            )?;
+
+            for row in records.iter().filter_map(StoreRow::from_record) {
+                match row {
+                    StoreRow::Token(token) => {
+                        insert_token.execute(params![
+                            token.mint.to_string(),
+                            token.name,
+                            token.symbol,
+                            token.uri,
+                            token.creator.to_string(),
+                            token.user.to_string(),
+                            token.bonding_curve.to_string(),
+                            token.total_supply as i64,
+                            token.slot as i64,
+                            token.signature,
+                            token.block_time,
+                        ])?;
+                    }
+                    StoreRow::Trade(trade) => {
+                        insert_trade.execute(params![
+                            trade.signature,
+                            trade.event_index as i64,
+                            trade.slot as i64,
+                            trade.block_time,
+                            trade.venue,
+                            trade.mint.to_string(),
+                            trade.wallet.to_string(),
+                            trade.pool.to_string(),
+                            trade.is_buy,
+                            trade.sol_amount as i64,
+                            trade.token_amount as i64,
+                            trade.fee as i64,
+                        ])?;
+                    }
+                    StoreRow::Migration(migration) => {
+                        insert_migration.execute(params![
+                            migration.signature,
+                            migration.event_index as i64,
+                            migration.slot as i64,
+                            migration.block_time,
+                            migration.kind,
+                            migration.mint.to_string(),
+                            migration.user.to_string(),
+                            migration.bonding_curve.map(|curve| curve.to_string()),
+                            migration.pool.map(|pool| pool.to_string()),
+                            migration.base_amount.map(|amount| amount as i64),
+                            migration.quote_amount.map(|amount| amount as i64),
+                        ])?;
+                    }
+                    StoreRow::Slot(slot) => {
+                        upsert_slot.execute(params![
+                            slot.slot as i64,
+                            slot.parent.map(|parent| parent as i64),
+                            slot.status,
+                        ])?;
+                    }
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn token(&self, mint: &Pubkey) -> anyhow::Result<Option<TokenRow>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT mint, name, symbol, uri, creator, user, bonding_curve, total_supply, slot, signature, block_time
+                 FROM tokens WHERE mint = ?1",
+                [mint.to_string()],
+                |row| {
+                    Ok(TokenRow {
+                        mint: pubkey(row, 0)?,
+                        name: row.get(1)?,
+                        symbol: row.get(2)?,
+                        uri: row.get(3)?,
+                        creator: pubkey(row, 4)?,
+                        user: pubkey(row, 5)?,
+                        bonding_curve: pubkey(row, 6)?,
+                        total_supply: row.get::<_, i64>(7)? as u64,
+                        slot: row.get::<_, i64>(8)? as u64,
+                        signature: row.get(9)?,
+                        block_time: row.get(10)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    /// Tokens launched by `creator`, oldest first.
+    pub fn tokens_by_creator(&self, creator: &Pubkey) -> anyhow::Result<Vec<Pubkey>> {
+        let mut statement = self
+            .conn
+            .prepare_cached("SELECT mint FROM tokens WHERE creator = ?1 ORDER BY slot")?;
+        let mints = statement
+            .query_map([creator.to_string()], |row| pubkey(row, 0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(mints)
+    }
+
+    /// Latest `limit` trades of a mint, newest first.
+    pub fn trades_by_mint(&self, mint: &Pubkey, limit: usize) -> anyhow::Result<Vec<TradeRow>> {
+        self.trades("mint", mint, limit)
+    }
+
+    /// Latest `limit` trades of a wallet, newest first.
+    pub fn trades_by_wallet(&self, wallet: &Pubkey, limit: usize) -> anyhow::Result<Vec<TradeRow>> {
+        self.trades("wallet", wallet, limit)
+    }
+
+    fn trades(&self, column: &str, key: &Pubkey, limit: usize) -> anyhow::Result<Vec<TradeRow>> {
+        let mut statement = self.conn.prepare_cached(&format!(
+            "SELECT signature, event_index, slot, block_time, venue, mint, wallet, pool, is_buy, sol_amount, token_amount, fee
+             FROM trades WHERE {column} = ?1 ORDER BY slot DESC, event_index DESC LIMIT ?2"
+        ))?;
+        let trades = statement
+            .query_map(params![key.to_string(), limit as i64], |row| {
+                Ok(TradeRow {
+                    signature: row.get(0)?,
+                    event_index: row.get::<_, i64>(1)? as u64,
+                    slot: row.get::<_, i64>(2)? as u64,
+                    block_time: row.get(3)?,
+                    venue: row.get(4)?,
+                    mint: pubkey(row, 5)?,
+                    wallet: pubkey(row, 6)?,
+                    pool: pubkey(row, 7)?,
+                    is_buy: row.get(8)?,
+                    sol_amount: row.get::<_, i64>(9)? as u64,
+                    token_amount: row.get::<_, i64>(10)? as u64,
+                    fee: row.get::<_, i64>(11)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(trades)
+    }
+}
+
+pub const SQLITE_BATCH_SIZE: usize = 1000;
+pub const SQLITE_BATCH_INTERVAL: Duration = Duration::from_secs(1);
+const SQLITE_QUEUE_CAPACITY: usize = 100_000;
+const SQLITE_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+const SQLITE_MAX_BACKOFF: Duration = Duration::from_secs(5);
+const SQLITE_MAX_RETRIES: u32 = 10;
+
+/// Busy, locked, full or failing I/O: worth retrying the same batch.
+fn is_transient(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<rusqlite::Error>(),
+        Some(rusqlite::Error::SqliteFailure(failure, _))
+            if matches!(
+                failure.code,
+                ErrorCode::DatabaseBusy
+                    | ErrorCode::DatabaseLocked
+                    | ErrorCode::DiskFull
+                    | ErrorCode::SystemIoFailure
+            )
+    )
+}
+
+/// Inserts a batch, retrying with backoff while the error is transient. A batch
+/// that is rejected is inserted record by record, so only the bad ones are lost.
+fn insert_batch(store: &mut SqliteStore, batch: &[Record]) {
+    let mut backoff = SQLITE_RETRY_BACKOFF;
+    let mut retries = 0;
+    loop {
+        match store.insert(batch) {
+            Ok(()) => return,
+            Err(error) if is_transient(&error) && retries < SQLITE_MAX_RETRIES => {
+                warn!(
+                    "failed to write {} records to sqlite, retrying in {backoff:?}: {error:#}",
+                    batch.len()
+                );
+                thread::sleep(backoff);
+                backoff = (backoff * 2).min(SQLITE_MAX_BACKOFF);
+                retries += 1;
+            }
+            Err(error) if batch.len() > 1 && !is_transient(&error) => {
+                warn!(
+                    "sqlite rejected a batch of {}, inserting one by one: {error:#}",
+                    batch.len()
+                );
+                for record in batch {
+                    insert_batch(store, std::slice::from_ref(record));
+                }
+                return;
+            }
+            Err(error) => {
+                error!(
+                    "failed to write {} records to sqlite: {error:#}",
+                    batch.len()
+                );
+                return;
+            }
+        }
+    }
+}
+
+/// Writes to a `SqliteStore` on its own thread, in batches of up to
+/// `SQLITE_BATCH_SIZE` records or every `SQLITE_BATCH_INTERVAL`. A busy or locked
+/// database is retried with backoff, see [`insert_batch`].
+/// Never blocks the stream: when the writer falls behind, records are dropped and counted.
+pub struct SqliteSink {
+    tx: Option<SyncSender<Record>>,
+    writer: Option<JoinHandle<()>>,
+    dropped: u64,
+}
+
+impl SqliteSink {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let mut store = SqliteStore::open(path)?;
+        let (tx, rx) = mpsc::sync_channel::<Record>(SQLITE_QUEUE_CAPACITY);
+        let writer = thread::spawn(move || {
+            let mut batch = Vec::with_capacity(SQLITE_BATCH_SIZE);
+            let mut deadline = Instant::now() + SQLITE_BATCH_INTERVAL;
+            loop {
+                let closed =
+                    match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                        Ok(record) => {
+                            batch.push(record);
+                            false
+                        }
+                        Err(RecvTimeoutError::Timeout) => false,
+                        Err(RecvTimeoutError::Disconnected) => true,
+                    };
+                if closed || batch.len() >= SQLITE_BATCH_SIZE || Instant::now() >= deadline {
+                    if !batch.is_empty() {
+                        insert_batch(&mut store, &batch);
+                        batch.clear();
+                    }
+                    deadline = Instant::now() + SQLITE_BATCH_INTERVAL;
+                }
+                if closed {
+                    break;
+                }
+            }
+        });
+        Ok(Self {
+            tx: Some(tx),
+            writer: Some(writer),
+            dropped: 0,
+        })
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+impl Sink for SqliteSink {
+    fn write(&mut self, record: &Record) -> anyhow::Result<()> {
+        let Some(tx) = &self.tx else {
+            anyhow::bail!("sqlite sink closed");
+        };
+        match tx.try_send(record.clone()) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                if self.dropped.is_power_of_two() {
+                    warn!("sqlite sink full, {} records dropped", self.dropped);
+                }
+                Ok(())
+            }
+            Err(TrySendError::Disconnected(_)) => anyhow::bail!("sqlite writer stopped"),
+        }
+    }
+
+    /// Writes what is queued and waits for the writer thread.
+    fn close(&mut self) -> anyhow::Result<()> {
+        self.tx.take();
+        if let Some(writer) = self.writer.take() {
+            writer
+                .join()
+                .map_err(|_| anyhow::anyhow!("sqlite writer panicked"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SqliteSink {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
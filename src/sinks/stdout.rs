@@ -0,0 +1,34 @@
+use std::io::{self, Write};
+
+use super::{Record, Sink};
+
+/// One JSON object per line on stdout.
+pub struct StdoutSink {
+    out: io::Stdout,
+}
+
+impl StdoutSink {
+    pub fn new() -> Self {
+        Self { out: io::stdout() }
+    }
+}
+
+impl Default for StdoutSink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sink for StdoutSink {
+    fn write(&mut self, record: &Record) -> anyhow::Result<()> {
+        let mut out = self.out.lock();
+        serde_json::to_writer(&mut out, &record.to_json())?;
+        out.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+use solana_sdk::instruction::Instruction;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use super::pump_fun::{self, CurveAccounts, CurveQuote};
+use crate::decoder::{Action, DecodedTransaction};
+use crate::types::pump_fun::TradeEvent;
+use crate::types::swap::SwapSide;
+
+pub const DEFAULT_COPY_SIZE: &str = "fixed:0.05";
+pub const DEFAULT_SLIPPAGE_BPS: u64 = 500;
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+
+/// How much SOL to put into a copied buy.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SizeRule {
+    /// The same amount every time, lamports. Written `fixed:<sol>`.
+    Fixed(u64),
+    /// A share of what the leader spent. Written `ratio:<share>`.
+    Ratio(f64),
+}
+
+impl SizeRule {
+    /// Lamports to spend, fees included, copying a buy of `leader_sol`.
+    pub fn size(&self, leader_sol: u64) -> u64 {
+        match self {
+            SizeRule::Fixed(lamports) => *lamports,
+            SizeRule::Ratio(share) => (leader_sol as f64 * share) as u64,
+        }
+    }
+}
+
+impl FromStr for SizeRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            || anyhow::anyhow!("invalid copy size {s}, expected fixed:<sol> or ratio:<share>");
+        let (rule, value) = s.split_once(':').ok_or_else(invalid)?;
+        match rule {
+            "fixed" => Ok(SizeRule::Fixed(parse_sol(value).map_err(|_| invalid())?)),
+            "ratio" => match value.parse::<f64>() {
+                Ok(share) if share.is_finite() && share > 0.0 => Ok(SizeRule::Ratio(share)),
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// A positive SOL amount, e.g. `0.05`, in lamports.
+pub fn parse_sol(sol: &str) -> anyhow::Result<u64> {
+    match sol.parse::<f64>() {
+        Ok(sol) if sol.is_finite() && sol > 0.0 => Ok((sol * LAMPORTS_PER_SOL) as u64),
+        _ => anyhow::bail!("invalid SOL amount {sol}"),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CopyConfig {
+    /// The wallet the instructions are built for; it signs and pays.
+    pub wallet: Pubkey,
+    pub size: SizeRule,
+    /// Cap on a single buy, lamports.
+    pub max_sol: Option<u64>,
+    pub slippage_bps: u64,
+}
+
+/// A leader's trade and the instructions copying it, ready to sign but not sent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CopySignal {
+    pub leader: Pubkey,
+    pub leader_signature: Signature,
+    pub slot: u64,
+    pub mint: Pubkey,
+    pub side: SwapSide,
+    /// What the leader paid or got, fees included, and the tokens traded.
+    pub leader_sol: u64,
+    pub leader_tokens: u64,
+    pub wallet: Pubkey,
+    /// Buy: lamports to spend, fees included. Sell: lamports expected, fees taken.
+    pub sol: u64,
+    pub tokens: u64,
+    /// Buy: `max_sol_cost`. Sell: `min_sol_output`.
+    pub sol_limit: u64,
+    pub slippage_bps: u64,
+    pub instructions: Vec<Instruction>,
+}
+
+/// Copies the pump.fun trades of leader wallets with `config.wallet`.
+///
+/// Nothing is sent: the holdings the sells are sized from are those the
+/// signals would have bought, and a sell copies the share of the leader's
+/// tracked balance the leader sold.
+pub struct CopyTrader {
+    config: CopyConfig,
+    leaders: HashSet<Pubkey>,
+    /// (leader, mint) -> tokens bought since watched, minus sold
+    leader_balances: HashMap<(Pubkey, Pubkey), u64>,
+    /// mint -> tokens the signals bought, minus sold
+    holdings: HashMap<Pubkey, u64>,
+}
+
+impl CopyTrader {
+    pub fn new(config: CopyConfig, leaders: HashSet<Pubkey>) -> Self {
+        Self {
+            config,
+            leaders,
+            leader_balances: HashMap::new(),
+            holdings: HashMap::new(),
+        }
+    }
+
+    pub fn holdings(&self, mint: &Pubkey) -> u64 {
+        self.holdings.get(mint).copied().unwrap_or_default()
+    }
+
+    pub fn observe(&mut self, decoded: &DecodedTransaction) -> Vec<CopySignal> {
+        if !decoded.success {
+            return Vec::new();
+        }
+        decoded
+            .actions
+            .iter()
+            .filter_map(|action| match &action.action {
+                Action::PumpFunTrade(trade) if self.leaders.contains(&trade.user) => {
+                    self.copy(trade, decoded)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn copy(&mut self, trade: &TradeEvent, decoded: &DecodedTransaction) -> Option<CopySignal> {
+        let curve = CurveAccounts::from_trade(trade);
+        let quote = CurveQuote::from_trade(trade);
+        let wallet = self.config.wallet;
+        let slippage_bps = self.config.slippage_bps;
+        let leader_balance = self
+            .leader_balances
+            .entry((trade.user, trade.mint))
+            .or_default();
+        let holdings = self.holdings.entry(trade.mint).or_default();
+
+        let (side, leader_sol, sol, tokens, sol_limit, instructions) = if trade.is_buy {
+            *leader_balance += trade.token_amount;
+            let leader_sol = trade.sol_amount + trade.fee + trade.creator_fee;
+            let mut sol = self.config.size.size(leader_sol);
+            if let Some(max_sol) = self.config.max_sol {
+                sol = sol.min(max_sol);
+            }
+            let (tokens, instructions) =
+                pump_fun::buy_instructions(&wallet, &curve, &quote, sol, slippage_bps)?;
+            *holdings += tokens;
+            let sol_limit = pump_fun::with_slippage_up(sol, slippage_bps);
+            (
+                SwapSide::Buy,
+                leader_sol,
+                sol,
+                tokens,
+                sol_limit,
+                instructions,
+            )
+        } else {
+            let before = *leader_balance;
+            *leader_balance = before.saturating_sub(trade.token_amount);
+            // a leader selling more than we saw it buy sells out
+            let tokens = if trade.token_amount >= before {
+                *holdings
+            } else {
+                (*holdings as u128 * trade.token_amount as u128 / before as u128) as u64
+            };
+            let (sol, instructions) =
+                pump_fun::sell_instructions(&wallet, &curve, &quote, tokens, slippage_bps)?;
+            *holdings -= tokens;
+            let leader_sol = trade
+                .sol_amount
+                .saturating_sub(trade.fee + trade.creator_fee);
+            let sol_limit = pump_fun::with_slippage_down(sol, slippage_bps);
+            (
+                SwapSide::Sell,
+                leader_sol,
+                sol,
+                tokens,
+                sol_limit,
+                instructions,
+            )
+        };
+
+        Some(CopySignal {
+            leader: trade.user,
+            leader_signature: decoded.signature,
+            slot: decoded.slot,
+            mint: trade.mint,
+            side,
+            leader_sol,
+            leader_tokens: trade.token_amount,
+            wallet,
+            sol,
+            tokens,
+            sol_limit,
+            slippage_bps,
+            instructions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_rule_from_str() {
+        assert_eq!(
+            "fixed:0.05".parse::<SizeRule>().unwrap(),
+            SizeRule::Fixed(50_000_000)
+        );
+        assert_eq!(
+            "fixed:2".parse::<SizeRule>().unwrap(),
+            SizeRule::Fixed(2_000_000_000)
+        );
+        assert_eq!(
+            "ratio:0.5".parse::<SizeRule>().unwrap(),
+            SizeRule::Ratio(0.5)
+        );
+        assert_eq!(SizeRule::Ratio(0.5).size(3_000_000), 1_500_000);
+        assert_eq!(SizeRule::Fixed(7).size(3_000_000), 7);
+        for invalid in [
+            "",
+            "0.05",
+            "fixed",
+            "fixed:",
+            "fixed:0",
+            "fixed:-1",
+            "fixed:abc",
+            "fixed:inf",
+            "ratio:0",
+            "ratio:-0.5",
+            "ratio:NaN",
+            "ratio:inf",
+            "percent:50",
+            "Fixed:1",
+        ] {
+            assert!(invalid.parse::<SizeRule>().is_err(), "{invalid}");
+        }
+    }
+}
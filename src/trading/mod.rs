@@ -0,0 +1,3 @@
+pub mod copy;
+pub mod pump_fun;
+pub mod submit;
@@ -0,0 +1,447 @@
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::types::constant::{
+    ASSOCIATED_TOKEN_PROGRAM_ID, CREATOR_VAULT_SEED, EVENT_AUTHORITY_SEED, GLOBAL_SEED,
+    PUMP_FUN_PROGRAM_ID, SYSTEM_PROGRAM_ID, TOKEN_PROGRAM_ID,
+};
+use crate::types::pump_fun::TradeEvent;
+use crate::types::swap::bonding_curve_address;
+
+pub const BUY_DISCRIMINATOR: [u8; 8] = [102, 6, 61, 18, 1, 218, 235, 234];
+pub const SELL_DISCRIMINATOR: [u8; 8] = [51, 230, 133, 164, 1, 127, 131, 173];
+/// Associated token program `CreateIdempotent`.
+const CREATE_ATA_IDEMPOTENT: u8 = 1;
+const BPS: u128 = 10_000;
+
+pub fn global_address() -> Pubkey {
+    Pubkey::find_program_address(&[GLOBAL_SEED], &PUMP_FUN_PROGRAM_ID).0
+}
+
+pub fn event_authority_address() -> Pubkey {
+    Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], &PUMP_FUN_PROGRAM_ID).0
+}
+
+pub fn creator_vault_address(creator: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[CREATOR_VAULT_SEED, creator.as_ref()],
+        &PUMP_FUN_PROGRAM_ID,
+    )
+    .0
+}
+
+/// The associated token account of `owner` for a legacy SPL token mint.
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), TOKEN_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Creates `owner`'s associated token account for `mint` unless it already exists.
+pub fn create_associated_token_account_idempotent(
+    payer: &Pubkey,
+    owner: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: ASSOCIATED_TOKEN_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(associated_token_address(owner, mint), false),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ],
+        data: vec![CREATE_ATA_IDEMPOTENT],
+    }
+}
+
+/// The accounts a pump.fun trade of `mint` touches besides the user's.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CurveAccounts {
+    pub mint: Pubkey,
+    pub bonding_curve: Pubkey,
+    pub associated_bonding_curve: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub creator_vault: Pubkey,
+}
+
+impl CurveAccounts {
+    pub fn new(mint: Pubkey, creator: &Pubkey, fee_recipient: Pubkey) -> Self {
+        let bonding_curve = bonding_curve_address(&mint);
+        Self {
+            mint,
+            associated_bonding_curve: associated_token_address(&bonding_curve, &mint),
+            bonding_curve,
+            fee_recipient,
+            creator_vault: creator_vault_address(creator),
+        }
+    }
+
+    /// Everything but the user's accounts is in the trade event.
+    pub fn from_trade(trade: &TradeEvent) -> Self {
+        Self::new(trade.mint, &trade.creator, trade.fee_recipient)
+    }
+}
+
+/// Bonding curve state a quote is made against, e.g. the reserves after the
+/// last trade seen. Fees are charged on top of the curve amount on buys and
+/// taken from it on sells.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CurveQuote {
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+    pub real_token_reserves: u64,
+    /// Protocol and creator fee together.
+    pub fee_basis_points: u64,
+}
+
+impl CurveQuote {
+    pub fn from_trade(trade: &TradeEvent) -> Self {
+        Self {
+            virtual_sol_reserves: trade.virtual_sol_reserves,
+            virtual_token_reserves: trade.virtual_token_reserves,
+            real_token_reserves: trade.real_token_reserves,
+            fee_basis_points: trade.fee_basis_points + trade.creator_fee_basis_points,
+        }
+    }
+
+    /// Tokens out for `sol` lamports spent in total, fees included.
+    pub fn buy_tokens(&self, sol: u64) -> u64 {
+        let into_curve = sol as u128 * BPS / (BPS + self.fee_basis_points as u128);
+        let (v_sol, v_token) = (
+            self.virtual_sol_reserves as u128,
+            self.virtual_token_reserves as u128,
+        );
+        if v_sol + into_curve == 0 {
+            return 0;
+        }
+        let tokens = (v_token * into_curve / (v_sol + into_curve)) as u64;
+        tokens.min(self.real_token_reserves)
+    }
+
+    /// Lamports received for selling `tokens`, fees taken.
+    pub fn sell_sol(&self, tokens: u64) -> u64 {
+        let (v_sol, v_token) = (
+            self.virtual_sol_reserves as u128,
+            self.virtual_token_reserves as u128,
+        );
+        if v_token + tokens as u128 == 0 {
+            return 0;
+        }
+        let out = v_sol * tokens as u128 / (v_token + tokens as u128);
+        (out - out * self.fee_basis_points as u128 / BPS) as u64
+    }
+}
+
+pub fn with_slippage_up(amount: u64, slippage_bps: u64) -> u64 {
+    (amount as u128 * (BPS + slippage_bps as u128) / BPS) as u64
+}
+
+pub fn with_slippage_down(amount: u64, slippage_bps: u64) -> u64 {
+    (amount as u128 * BPS.saturating_sub(slippage_bps as u128) / BPS) as u64
+}
+
+fn data(discriminator: [u8; 8], amount: u64, sol_limit: u64) -> Vec<u8> {
+    let mut data = discriminator.to_vec();
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(&sol_limit.to_le_bytes());
+    data
+}
+
+/// `buy`: `amount` tokens for at most `max_sol_cost` lamports, fees included.
+pub fn buy(user: &Pubkey, curve: &CurveAccounts, amount: u64, max_sol_cost: u64) -> Instruction {
+    Instruction {
+        program_id: PUMP_FUN_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new_readonly(global_address(), false),
+            AccountMeta::new(curve.fee_recipient, false),
+            AccountMeta::new_readonly(curve.mint, false),
+            AccountMeta::new(curve.bonding_curve, false),
+            AccountMeta::new(curve.associated_bonding_curve, false),
+            AccountMeta::new(associated_token_address(user, &curve.mint), false),
+            AccountMeta::new(*user, true),
+            AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new(curve.creator_vault, false),
+            AccountMeta::new_readonly(event_authority_address(), false),
+            AccountMeta::new_readonly(PUMP_FUN_PROGRAM_ID, false),
+        ],
+        data: data(BUY_DISCRIMINATOR, amount, max_sol_cost),
+    }
+}
+
+/// `sell`: `amount` tokens for at least `min_sol_output` lamports, fees taken.
+pub fn sell(user: &Pubkey, curve: &CurveAccounts, amount: u64, min_sol_output: u64) -> Instruction {
+    Instruction {
+        program_id: PUMP_FUN_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new_readonly(global_address(), false),
+            AccountMeta::new(curve.fee_recipient, false),
+            AccountMeta::new_readonly(curve.mint, false),
+            AccountMeta::new(curve.bonding_curve, false),
+            AccountMeta::new(curve.associated_bonding_curve, false),
+            AccountMeta::new(associated_token_address(user, &curve.mint), false),
+            AccountMeta::new(*user, true),
+            AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+            AccountMeta::new(curve.creator_vault, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(event_authority_address(), false),
+            AccountMeta::new_readonly(PUMP_FUN_PROGRAM_ID, false),
+        ],
+        data: data(SELL_DISCRIMINATOR, amount, min_sol_output),
+    }
+}
+
+/// Instructions buying with `sol` lamports in total: the user's token account
+/// (idempotent) and the buy, `max_sol_cost` being `sol` plus slippage.
+pub fn buy_instructions(
+    user: &Pubkey,
+    curve: &CurveAccounts,
+    quote: &CurveQuote,
+    sol: u64,
+    slippage_bps: u64,
+) -> Option<(u64, Vec<Instruction>)> {
+    let tokens = quote.buy_tokens(sol);
+    if tokens == 0 {
+        return None;
+    }
+    let instructions = vec![
+        create_associated_token_account_idempotent(user, user, &curve.mint),
+        buy(user, curve, tokens, with_slippage_up(sol, slippage_bps)),
+    ];
+    Some((tokens, instructions))
+}
+
+/// Instructions selling `tokens`, `min_sol_output` being the quote minus slippage.
+pub fn sell_instructions(
+    user: &Pubkey,
+    curve: &CurveAccounts,
+    quote: &CurveQuote,
+    tokens: u64,
+    slippage_bps: u64,
+) -> Option<(u64, Vec<Instruction>)> {
+    let sol = quote.sell_sol(tokens);
+    if tokens == 0 {
+        return None;
+    }
+    let instructions = vec![sell(
+        user,
+        curve,
+        tokens,
+        with_slippage_down(sol, slippage_bps),
+    )];
+    Some((sol, instructions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::pump_fun::{CreateEvent, EventTrait};
+    use base64::{Engine, engine::general_purpose};
+
+    // logs of a mainnet create and the dev's first buy (the samples in main.rs)
+    const CREATE: &str = "G3KpTd7rY3YeAAAAZnVsbHkgYXV0aXN0aWMgcmV0YXJkcyB0cmFkaW5nBAAAAGZhcnRDAAAAaHR0cHM6Ly9pcGZzLmlvL2lwZnMvUW1TMXJjVTFWdlBUVnB1SnFXclRrYzVnNkM5R05xYmlIenY4MjRWaW1XR0tnUBhzl4PXzOleE1tsweuIRUdj/NSS8IXR60VWb59eum+Y5XXOxUjcNAcYU2PJsE48P5j3xbZRTPkCPTz/yj73g9qoTpfLJUgat5sqB+S0hUDriQGAz5X4OaQOuCUGItBQQKhOl8slSBq3myoH5LSFQOuJAYDPlfg5pA64JQYi0FBAZqZgaAAAAAAAENhH488DAACsI/wGAAAAAHjF+1HRAgAAgMakfo0DAA==";
+    const TRADE: &str = "vdt/007mYe4Yc5eD18zpXhNbbMHriEVHY/zUkvCF0etFVm+fXrpvmGRW0hAAAAAAAKByThgJAAABqE6XyyVIGrebKgfktIVA64kBgM+V+DmkDrglBiLQUEBmpmBoAAAAAGQC9gwHAAAAAHBl+crGAwBkVtIQAAAAAADYUq05yAIASsL40N1cvJfjKJwZfLUGKlTz2Va5zm5RFfllZ6pcs+ZfAAAAAAAAAATpKAAAAAAAqE6XyyVIGrebKgfktIVA64kBgM+V+DmkDrglBiLQUEAFAAAAAAAAADcnAgAAAAAA";
+
+    fn decode<T: EventTrait>(data: &str) -> T {
+        T::decode(&general_purpose::STANDARD.decode(data).unwrap()).unwrap()
+    }
+
+    fn pubkey(address: &str) -> Pubkey {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn addresses_match_mainnet() {
+        assert_eq!(
+            global_address(),
+            pubkey("4wTV1YmiEkRvAtNtsSGPtUrqRYQMe5SKy2uB4Jjaxnjf")
+        );
+        assert_eq!(
+            event_authority_address(),
+            pubkey("Ce6TQqeHC9p8KetsN6JsjHK7UTZk7nasjjnr7XxXp9F1")
+        );
+        let create: CreateEvent = decode(CREATE);
+        assert_eq!(
+            create.mint,
+            pubkey("2eT1rgfLTTAGHC5gPPVdPPr5zmZV5Ka3Gd4e9twUvw7m")
+        );
+        assert_eq!(bonding_curve_address(&create.mint), create.bonding_curve);
+
+        let trade: TradeEvent = decode(TRADE);
+        let curve = CurveAccounts::from_trade(&trade);
+        assert_eq!(curve.bonding_curve, create.bonding_curve);
+        for (owner, mint) in [
+            (trade.user, trade.mint),
+            (create.bonding_curve, create.mint),
+        ] {
+            assert_eq!(
+                associated_token_address(&owner, &mint),
+                spl_associated_token_account_client::address::get_associated_token_address(
+                    &owner, &mint
+                )
+            );
+        }
+        assert_eq!(
+            curve.associated_bonding_curve,
+            associated_token_address(&create.bonding_curve, &create.mint)
+        );
+        // the logs don't carry the creator vault, this is ["creator-vault", creator] derived once
+        assert_eq!(
+            creator_vault_address(&trade.creator),
+            pubkey("G8fbqS2rW5TohS3RY5mBXcSM8Vt3zyhBK2yz5KtfUNrU")
+        );
+    }
+
+    #[test]
+    fn quote_matches_recorded_trade() {
+        let trade: TradeEvent = decode(TRADE);
+        assert!(trade.is_buy);
+        let fee_basis_points = trade.fee_basis_points + trade.creator_fee_basis_points;
+        // reserves before the trade
+        let before = CurveQuote {
+            virtual_sol_reserves: trade.virtual_sol_reserves - trade.sol_amount,
+            virtual_token_reserves: trade.virtual_token_reserves + trade.token_amount,
+            real_token_reserves: trade.real_token_reserves + trade.token_amount,
+            fee_basis_points,
+        };
+        let paid = trade.sol_amount + trade.fee + trade.creator_fee;
+        // fees round up on chain, so the payment buys just token_amount and 2 lamports less doesn't
+        assert!(before.buy_tokens(paid) >= trade.token_amount);
+        assert!(before.buy_tokens(paid - 2) < trade.token_amount);
+        assert!(before.buy_tokens(paid) - trade.token_amount < trade.token_amount / 100_000);
+
+        // selling right back returns the SOL minus the fee twice
+        let after = CurveQuote::from_trade(&trade);
+        assert_eq!(after.fee_basis_points, fee_basis_points);
+        let sol = after.sell_sol(trade.token_amount);
+        let expected = trade.sol_amount - trade.sol_amount * fee_basis_points / 10_000;
+        assert!(
+            sol <= expected && expected - sol <= 2,
+            "{sol} vs {expected}"
+        );
+    }
+
+    /// The repo has no recorded buy/sell transaction with its account keys and
+    /// the tests run offline, so the expected accounts follow the order of the
+    /// pump.fun IDL, written as literal mainnet addresses: the program
+    /// constants and the mint, bonding curve and fee recipient from the
+    /// recorded logs. The token accounts come from the SPL crate. The creator
+    /// vault is not in any log and is the one derived address taken on trust,
+    /// see `addresses_match_mainnet`.
+    #[test]
+    fn buy_and_sell_layout() {
+        let trade: TradeEvent = decode(TRADE);
+        let curve = CurveAccounts::from_trade(&trade);
+        let user = Pubkey::new_unique();
+        let mint = pubkey("2eT1rgfLTTAGHC5gPPVdPPr5zmZV5Ka3Gd4e9twUvw7m");
+        let bonding_curve = pubkey("GSiZ3epRZzSv1PFwLVMpE29Gr1nqdr1LgED9bjp4zp2D");
+        let ata = spl_associated_token_account_client::address::get_associated_token_address;
+        let meta = |pubkey, writable, signer| AccountMeta {
+            pubkey,
+            is_signer: signer,
+            is_writable: writable,
+        };
+        let accounts = |is_buy: bool| {
+            let mut accounts = vec![
+                // global
+                meta(
+                    pubkey("4wTV1YmiEkRvAtNtsSGPtUrqRYQMe5SKy2uB4Jjaxnjf"),
+                    false,
+                    false,
+                ),
+                // fee recipient
+                meta(
+                    pubkey("62qc2CNXwrYqQScmEdiZFFAnJR262PxWEuNQtxfafNgV"),
+                    true,
+                    false,
+                ),
+                meta(mint, false, false),
+                meta(bonding_curve, true, false),
+                meta(ata(&bonding_curve, &mint), true, false),
+                meta(ata(&user, &mint), true, false),
+                meta(user, true, true),
+                meta(pubkey("11111111111111111111111111111111"), false, false),
+            ];
+            let token_program = meta(
+                pubkey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+                false,
+                false,
+            );
+            let creator_vault = meta(
+                pubkey("G8fbqS2rW5TohS3RY5mBXcSM8Vt3zyhBK2yz5KtfUNrU"),
+                true,
+                false,
+            );
+            // the IDL swaps these two between buy and sell
+            if is_buy {
+                accounts.extend([token_program, creator_vault]);
+            } else {
+                accounts.extend([creator_vault, token_program]);
+            }
+            accounts.extend([
+                // event authority
+                meta(
+                    pubkey("Ce6TQqeHC9p8KetsN6JsjHK7UTZk7nasjjnr7XxXp9F1"),
+                    false,
+                    false,
+                ),
+                meta(
+                    pubkey("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P"),
+                    false,
+                    false,
+                ),
+            ]);
+            accounts
+        };
+
+        let buy = buy(&user, &curve, 1_000, 2_000);
+        assert_eq!(buy.program_id, PUMP_FUN_PROGRAM_ID);
+        assert_eq!(buy.accounts, accounts(true));
+        assert_eq!(
+            buy.data,
+            [
+                &BUY_DISCRIMINATOR[..],
+                &1_000u64.to_le_bytes(),
+                &2_000u64.to_le_bytes()
+            ]
+            .concat()
+        );
+
+        let sell = sell(&user, &curve, 3_000, 4_000);
+        assert_eq!(sell.accounts, accounts(false));
+        assert_eq!(
+            sell.data,
+            [
+                &SELL_DISCRIMINATOR[..],
+                &3_000u64.to_le_bytes(),
+                &4_000u64.to_le_bytes()
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn buy_instructions_create_the_token_account_first() {
+        let trade: TradeEvent = decode(TRADE);
+        let curve = CurveAccounts::from_trade(&trade);
+        let quote = CurveQuote::from_trade(&trade);
+        let user = Pubkey::new_unique();
+        let (tokens, instructions) =
+            buy_instructions(&user, &curve, &quote, 100_000_000, 500).unwrap();
+        assert_eq!(tokens, quote.buy_tokens(100_000_000));
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0].program_id, ASSOCIATED_TOKEN_PROGRAM_ID);
+        assert_eq!(instructions[0].data, [CREATE_ATA_IDEMPOTENT]);
+        assert_eq!(
+            instructions[0].accounts[1].pubkey,
+            associated_token_address(&user, &curve.mint)
+        );
+        assert_eq!(instructions[1].data[16..], 105_000_000u64.to_le_bytes());
+        assert!(buy_instructions(&user, &curve, &quote, 0, 500).is_none());
+    }
+}
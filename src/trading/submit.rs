@@ -0,0 +1,681 @@
+use anyhow::Context;
+use log::warn;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer, read_keypair_file};
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status::TransactionConfirmationStatus;
+use std::collections::HashMap;
+use std::env;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, oneshot};
+use yellowstone_grpc_proto::convert_from;
+use yellowstone_grpc_proto::geyser::{
+    self, SubscribeRequest, SubscribeRequestFilterTransactions, SubscribeUpdateTransactionStatus,
+};
+
+use crate::analytics::compute_budget::{Percentiles, PriorityFeeTracker};
+use crate::types::constant::{PUMP_FUN_PROGRAM_ID, SYSTEM_PROGRAM_ID};
+
+pub const DEFAULT_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+pub const DEFAULT_SEND_ATTEMPTS: u32 = 10;
+pub const DEFAULT_RESEND_INTERVAL: Duration = Duration::from_secs(2);
+const SYSTEM_TRANSFER_TAG: u32 = 2;
+
+pub fn load_keypair(path: impl AsRef<Path>) -> anyhow::Result<Keypair> {
+    let path = path.as_ref();
+    read_keypair_file(path)
+        .map_err(|error| anyhow::anyhow!("failed to read keypair {}: {error}", path.display()))
+}
+
+/// Compute unit price, micro-lamports per compute unit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PriorityFee {
+    Fixed(u64),
+    /// A percentile (50, 75, 90 or 99) of recent pump.fun transactions, which
+    /// needs a [`PriorityFeeTracker`] with samples.
+    Percentile(u8),
+}
+
+impl PriorityFee {
+    fn pick(percentile: u8, prices: &Percentiles) -> u64 {
+        match percentile {
+            50 => prices.p50,
+            75 => prices.p75,
+            90 => prices.p90,
+            _ => prices.p99,
+        }
+    }
+}
+
+impl FromStr for PriorityFee {
+    type Err = anyhow::Error;
+
+    /// `5000` or `p75`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('p') {
+            Some(percentile @ ("50" | "75" | "90" | "99")) => {
+                Ok(PriorityFee::Percentile(percentile.parse()?))
+            }
+            Some(_) => anyhow::bail!("invalid priority fee {s}, expected p50, p75, p90 or p99"),
+            None => Ok(PriorityFee::Fixed(
+                s.parse()
+                    .with_context(|| format!("invalid priority fee {s}"))?,
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubmitConfig {
+    pub compute_unit_limit: u32,
+    pub priority_fee: PriorityFee,
+    /// Sends of the same signed transaction before giving up.
+    pub max_attempts: u32,
+    /// How long to wait for the transaction to land before sending it again.
+    pub resend_interval: Duration,
+    pub skip_preflight: bool,
+}
+
+impl Default for SubmitConfig {
+    fn default() -> Self {
+        Self {
+            compute_unit_limit: DEFAULT_COMPUTE_UNIT_LIMIT,
+            priority_fee: PriorityFee::Fixed(0),
+            max_attempts: DEFAULT_SEND_ATTEMPTS,
+            resend_interval: DEFAULT_RESEND_INTERVAL,
+            skip_preflight: false,
+        }
+    }
+}
+
+impl SubmitConfig {
+    /// `COMPUTE_UNIT_LIMIT`, `PRIORITY_FEE`, `SEND_ATTEMPTS` and `SKIP_PREFLIGHT`
+    /// over the defaults.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mut config = Self::default();
+        if let Ok(limit) = env::var("COMPUTE_UNIT_LIMIT") {
+            config.compute_unit_limit = limit.parse().context("invalid COMPUTE_UNIT_LIMIT")?;
+        }
+        if let Ok(fee) = env::var("PRIORITY_FEE") {
+            config.priority_fee = fee.parse()?;
+        }
+        if let Ok(attempts) = env::var("SEND_ATTEMPTS") {
+            config.max_attempts = attempts.parse().context("invalid SEND_ATTEMPTS")?;
+            if config.max_attempts == 0 {
+                anyhow::bail!("SEND_ATTEMPTS must be at least 1");
+            }
+        }
+        config.skip_preflight = env::var("SKIP_PREFLIGHT").is_ok_and(|skip| skip == "true");
+        Ok(config)
+    }
+}
+
+/// System program `Transfer` of `lamports` from `from` to `to`.
+pub fn transfer(from: &Pubkey, to: &Pubkey, lamports: u64) -> Instruction {
+    let mut data = SYSTEM_TRANSFER_TAG.to_le_bytes().to_vec();
+    data.extend_from_slice(&lamports.to_le_bytes());
+    Instruction {
+        program_id: SYSTEM_PROGRAM_ID,
+        accounts: vec![AccountMeta::new(*from, true), AccountMeta::new(*to, false)],
+        data,
+    }
+}
+
+/// `instructions` behind a compute unit limit and price, signed by `payer`, the
+/// only signer they may need.
+pub fn build_transaction(
+    payer: &Keypair,
+    instructions: &[Instruction],
+    compute_unit_limit: u32,
+    unit_price: u64,
+    blockhash: Hash,
+) -> anyhow::Result<Transaction> {
+    let mut all = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+        compute_unit_limit,
+    )];
+    if unit_price > 0 {
+        all.push(ComputeBudgetInstruction::set_compute_unit_price(unit_price));
+    }
+    all.extend_from_slice(instructions);
+    let mut tx = Transaction::new_with_payer(&all, Some(&payer.pubkey()));
+    tx.try_sign(&[payer], blockhash)
+        .context("the instructions need signers besides the payer")?;
+    Ok(tx)
+}
+
+/// A transaction seen in a block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Landed {
+    pub slot: u64,
+    /// The commitment it was seen at: the subscription's on the gRPC stream,
+    /// the status's confirmation level when polled.
+    pub commitment: CommitmentLevel,
+    /// `None` when it succeeded.
+    pub error: Option<String>,
+}
+
+/// Resolves the signatures being waited on from `transactions_status` updates.
+///
+/// Cheap to clone. The stream loop adds [`StatusWatcher::filters`] to its
+/// subscription whenever [`StatusWatcher::changed`] fires, so updates arrive at
+/// the subscription's commitment.
+#[derive(Clone)]
+pub struct StatusWatcher {
+    pending: Arc<Mutex<HashMap<Signature, oneshot::Sender<Landed>>>>,
+    changed: Arc<Notify>,
+    commitment: CommitmentLevel,
+}
+
+impl StatusWatcher {
+    /// For updates arriving on `request`'s subscription.
+    pub fn for_request(request: &SubscribeRequest) -> Self {
+        let commitment = match request
+            .commitment
+            .and_then(|level| geyser::CommitmentLevel::try_from(level).ok())
+        {
+            Some(geyser::CommitmentLevel::Confirmed) => CommitmentLevel::Confirmed,
+            Some(geyser::CommitmentLevel::Finalized) => CommitmentLevel::Finalized,
+            // Geyser defaults to processed when unset
+            _ => CommitmentLevel::Processed,
+        };
+        Self {
+            pending: Arc::default(),
+            changed: Arc::default(),
+            commitment,
+        }
+    }
+
+    pub fn watch(&self, signature: Signature) -> oneshot::Receiver<Landed> {
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .expect("status watcher poisoned")
+            .insert(signature, tx);
+        self.changed.notify_one();
+        rx
+    }
+
+    pub fn unwatch(&self, signature: &Signature) {
+        let removed = self
+            .pending
+            .lock()
+            .expect("status watcher poisoned")
+            .remove(signature);
+        if removed.is_some() {
+            self.changed.notify_one();
+        }
+    }
+
+    /// Completes when signatures were added or removed since the last call.
+    pub async fn changed(&self) {
+        self.changed.notified().await
+    }
+
+    /// One `transactions_status` filter per watched signature.
+    pub fn filters(&self) -> HashMap<String, SubscribeRequestFilterTransactions> {
+        self.pending
+            .lock()
+            .expect("status watcher poisoned")
+            .keys()
+            .map(|signature| {
+                (
+                    signature.to_string(),
+                    SubscribeRequestFilterTransactions {
+                        signature: Some(signature.to_string()),
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect()
+    }
+
+    pub fn observe(&self, update: &SubscribeUpdateTransactionStatus) -> anyhow::Result<()> {
+        let signature = Signature::try_from(update.signature.as_slice())
+            .map_err(|_| anyhow::anyhow!("invalid signature"))?;
+        let Some(tx) = self
+            .pending
+            .lock()
+            .expect("status watcher poisoned")
+            .remove(&signature)
+        else {
+            return Ok(());
+        };
+        self.changed.notify_one();
+        let error = convert_from::create_tx_error(update.err.as_ref())
+            .map_err(|error| anyhow::anyhow!(error))?
+            .map(|error| error.to_string());
+        let _ = tx.send(Landed {
+            slot: update.slot,
+            commitment: self.commitment,
+            error,
+        });
+        Ok(())
+    }
+}
+
+/// The result of a submission.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Submission {
+    pub signature: Signature,
+    /// `None` when it did not land before the attempts ran out or the blockhash expired.
+    pub landed: Option<Landed>,
+    pub attempts: u32,
+    pub unit_price: u64,
+    pub elapsed: Duration,
+}
+
+/// Signs and sends transactions over RPC, resending the same signed
+/// transaction until it lands.
+///
+/// Landing is learnt from the gRPC stream when a [`StatusWatcher`] is set,
+/// otherwise by polling `getSignatureStatuses`, e.g. against
+/// `solana-test-validator`.
+pub struct Submitter {
+    rpc: RpcClient,
+    payer: Keypair,
+    config: SubmitConfig,
+    fees: Option<PriorityFeeTracker>,
+    watcher: Option<StatusWatcher>,
+}
+
+impl Submitter {
+    pub fn new(rpc_url: String, payer: Keypair, config: SubmitConfig) -> Self {
+        Self::with_rpc(
+            RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed()),
+            payer,
+            config,
+        )
+    }
+
+    /// From `KEYPAIR_PATH`, `RPC_URL` and [`SubmitConfig::from_env`], `None`
+    /// without a keypair.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Ok(path) = env::var("KEYPAIR_PATH") else {
+            return Ok(None);
+        };
+        Ok(Some(Self::new(
+            env::var("RPC_URL").context("RPC_URL is not set")?,
+            load_keypair(path)?,
+            SubmitConfig::from_env()?,
+        )))
+    }
+
+    /// E.g. an `RpcClient::new_mock` one.
+    pub fn with_rpc(rpc: RpcClient, payer: Keypair, config: SubmitConfig) -> Self {
+        Self {
+            rpc,
+            payer,
+            config,
+            fees: None,
+            watcher: None,
+        }
+    }
+
+    pub fn fee_tracker(mut self, fees: PriorityFeeTracker) -> Self {
+        self.fees = Some(fees);
+        self
+    }
+
+    pub fn status_watcher(mut self, watcher: StatusWatcher) -> Self {
+        self.watcher = Some(watcher);
+        self
+    }
+
+    pub fn payer(&self) -> Pubkey {
+        self.payer.pubkey()
+    }
+
+    /// Fails for a percentile without a fee tracker or before it has samples.
+    pub fn unit_price(&self) -> anyhow::Result<u64> {
+        match self.config.priority_fee {
+            PriorityFee::Fixed(price) => Ok(price),
+            PriorityFee::Percentile(percentile) => {
+                let stats = self
+                    .fees
+                    .as_ref()
+                    .context("a percentile priority fee needs the gRPC stream's fee tracker")?
+                    .stats(&PUMP_FUN_PROGRAM_ID)
+                    .with_context(|| {
+                        format!("no recent pump.fun priority fees to take p{percentile} of")
+                    })?;
+                Ok(PriorityFee::pick(percentile, &stats.unit_price))
+            }
+        }
+    }
+
+    pub async fn submit(&self, instructions: &[Instruction]) -> anyhow::Result<Submission> {
+        let started = Instant::now();
+        let (blockhash, last_valid_block_height) = self
+            .rpc
+            .get_latest_blockhash_with_commitment(self.rpc.commitment())
+            .await
+            .context("failed to get a blockhash")?;
+        let unit_price = self.unit_price()?;
+        let tx = build_transaction(
+            &self.payer,
+            instructions,
+            self.config.compute_unit_limit,
+            unit_price,
+            blockhash,
+        )?;
+        let signature = tx.signatures[0];
+        // watch before sending so the status can't arrive first
+        let mut status = self
+            .watcher
+            .as_ref()
+            .map(|watcher| watcher.watch(signature));
+        let result = self
+            .send_until_landed(&tx, last_valid_block_height, status.as_mut())
+            .await;
+        if let Some(watcher) = &self.watcher {
+            watcher.unwatch(&signature);
+        }
+        let (landed, attempts) = result?;
+        Ok(Submission {
+            signature,
+            landed,
+            attempts,
+            unit_price,
+            elapsed: started.elapsed(),
+        })
+    }
+
+    async fn send_until_landed(
+        &self,
+        tx: &Transaction,
+        last_valid_block_height: u64,
+        mut status: Option<&mut oneshot::Receiver<Landed>>,
+    ) -> anyhow::Result<(Option<Landed>, u32)> {
+        let config = RpcSendTransactionConfig {
+            skip_preflight: self.config.skip_preflight,
+            preflight_commitment: Some(self.rpc.commitment().commitment),
+            // resending is done here
+            max_retries: Some(0),
+            ..Default::default()
+        };
+        let signature = tx.signatures[0];
+        for attempt in 1..=self.config.max_attempts {
+            match self.rpc.send_transaction_with_config(tx, config).await {
+                Ok(_) => {}
+                // a failing simulation won't get better
+                Err(error) if attempt == 1 => {
+                    return Err(error).with_context(|| format!("failed to send {signature}"));
+                }
+                Err(error) => warn!("failed to resend {signature}: {error}"),
+            }
+            let landed = match status.as_deref_mut() {
+                Some(status) => tokio::time::timeout(self.config.resend_interval, status)
+                    .await
+                    .ok()
+                    .and_then(Result::ok),
+                None => {
+                    tokio::time::sleep(self.config.resend_interval).await;
+                    self.poll(&signature).await.unwrap_or_else(|error| {
+                        warn!("failed to get the status of {signature}: {error}");
+                        None
+                    })
+                }
+            };
+            if landed.is_some() {
+                return Ok((landed, attempt));
+            }
+            // a failed query counts as not expired yet, keep resending
+            match self.rpc.get_block_height().await {
+                Ok(height) if height > last_valid_block_height => return Ok((None, attempt)),
+                Ok(_) => {}
+                Err(error) => warn!("failed to get the block height: {error}"),
+            }
+        }
+        Ok((None, self.config.max_attempts))
+    }
+
+    async fn poll(&self, signature: &Signature) -> anyhow::Result<Option<Landed>> {
+        let statuses = self.rpc.get_signature_statuses(&[*signature]).await?.value;
+        Ok(statuses
+            .into_iter()
+            .flatten()
+            .find(|status| status.satisfies_commitment(self.rpc.commitment()))
+            .map(|status| Landed {
+                slot: status.slot,
+                commitment: match status.confirmation_status() {
+                    TransactionConfirmationStatus::Processed => CommitmentLevel::Processed,
+                    TransactionConfirmationStatus::Confirmed => CommitmentLevel::Confirmed,
+                    TransactionConfirmationStatus::Finalized => CommitmentLevel::Finalized,
+                },
+                error: status.err.map(|error| error.to_string()),
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::summary::TransactionSummary;
+    use serde_json::{Value, json};
+    use solana_client::rpc_request::RpcRequest;
+    use solana_sdk::transaction::TransactionError;
+    use yellowstone_grpc_proto::convert_to;
+
+    /// `RpcClient::new_mock` answers by `url`: "succeeds" lands right away,
+    /// "sig_not_found" never does, "instruction_error" lands failed. The block
+    /// height is 1234, the same as the blockhash's last valid one.
+    fn mock_submitter(
+        url: &str,
+        mocks: HashMap<RpcRequest, Value>,
+        max_attempts: u32,
+    ) -> Submitter {
+        Submitter::with_rpc(
+            RpcClient::new_mock_with_mocks(url.into(), mocks),
+            Keypair::new(),
+            SubmitConfig {
+                max_attempts,
+                resend_interval: Duration::from_millis(10),
+                ..Default::default()
+            },
+        )
+    }
+
+    fn transfer_to_self(submitter: &Submitter) -> Instruction {
+        transfer(&submitter.payer(), &submitter.payer(), 1_000)
+    }
+
+    #[tokio::test]
+    async fn polls_until_landed() {
+        let submitter = mock_submitter("succeeds", HashMap::new(), 3);
+        let submission = submitter
+            .submit(&[transfer_to_self(&submitter)])
+            .await
+            .unwrap();
+        assert_eq!(submission.attempts, 1);
+        assert_eq!(
+            submission.landed,
+            Some(Landed {
+                slot: 1,
+                commitment: CommitmentLevel::Finalized,
+                error: None,
+            })
+        );
+
+        let submitter = mock_submitter("instruction_error", HashMap::new(), 3);
+        let submission = submitter
+            .submit(&[transfer_to_self(&submitter)])
+            .await
+            .unwrap();
+        assert!(submission.landed.unwrap().error.is_some());
+    }
+
+    #[test]
+    fn percentile_fees_need_samples() {
+        let submitter = || {
+            Submitter::with_rpc(
+                RpcClient::new_mock("succeeds".into()),
+                Keypair::new(),
+                SubmitConfig {
+                    priority_fee: PriorityFee::Percentile(75),
+                    ..Default::default()
+                },
+            )
+        };
+        assert!(submitter().unit_price().is_err());
+
+        let fees = PriorityFeeTracker::new(150, [PUMP_FUN_PROGRAM_ID]);
+        let submitter = submitter().fee_tracker(fees.clone());
+        assert!(submitter.unit_price().is_err());
+        for price in 1..=4 {
+            fees.record(&TransactionSummary {
+                signature: Signature::new_unique(),
+                slot: 10,
+                fee_payer: Pubkey::new_unique(),
+                success: true,
+                error: None,
+                fee: 5_000,
+                compute_unit_price: price * 1_000,
+                compute_unit_limit: 200_000,
+                priority_fee: price * 200,
+                compute_units_consumed: None,
+                sol_changes: Vec::new(),
+                token_changes: Vec::new(),
+                programs: vec![PUMP_FUN_PROGRAM_ID],
+            });
+        }
+        let p75 = fees.stats(&PUMP_FUN_PROGRAM_ID).unwrap().unit_price.p75;
+        assert!(p75 > 0);
+        assert_eq!(submitter.unit_price().unwrap(), p75);
+    }
+
+    #[tokio::test]
+    async fn resends_until_attempts_run_out_or_blockhash_expires() {
+        let submitter = mock_submitter("sig_not_found", HashMap::new(), 3);
+        let submission = submitter
+            .submit(&[transfer_to_self(&submitter)])
+            .await
+            .unwrap();
+        assert_eq!((submission.landed, submission.attempts), (None, 3));
+
+        let expired = HashMap::from([(RpcRequest::GetBlockHeight, json!(1235))]);
+        let submitter = mock_submitter("sig_not_found", expired, 3);
+        let submission = submitter
+            .submit(&[transfer_to_self(&submitter)])
+            .await
+            .unwrap();
+        assert_eq!((submission.landed, submission.attempts), (None, 1));
+    }
+
+    #[tokio::test]
+    async fn block_height_errors_do_not_stop_resending() {
+        // only the first getBlockHeight returns an unparsable result
+        let broken = HashMap::from([(RpcRequest::GetBlockHeight, json!("oops"))]);
+        let submitter = mock_submitter("sig_not_found", broken, 2);
+        let submission = submitter
+            .submit(&[transfer_to_self(&submitter)])
+            .await
+            .unwrap();
+        assert_eq!((submission.landed, submission.attempts), (None, 2));
+    }
+
+    #[tokio::test]
+    async fn first_send_failure_is_an_error() {
+        // "malicious" returns another signature
+        let submitter = mock_submitter("malicious", HashMap::new(), 3);
+        assert!(
+            submitter
+                .submit(&[transfer_to_self(&submitter)])
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn lands_from_the_stream() {
+        let watcher = StatusWatcher::for_request(&SubscribeRequest::default());
+        let submitter =
+            mock_submitter("sig_not_found", HashMap::new(), 100).status_watcher(watcher.clone());
+        let stream = tokio::spawn({
+            let watcher = watcher.clone();
+            async move {
+                watcher.changed().await;
+                let filters = watcher.filters();
+                let signature: Signature = filters.keys().next().unwrap().parse().unwrap();
+                watcher
+                    .observe(&SubscribeUpdateTransactionStatus {
+                        slot: 77,
+                        signature: signature.as_ref().to_vec(),
+                        err: convert_to::create_transaction_error(&Err(
+                            TransactionError::AccountInUse,
+                        )),
+                        ..Default::default()
+                    })
+                    .unwrap();
+                filters
+            }
+        });
+        let submission = submitter
+            .submit(&[transfer_to_self(&submitter)])
+            .await
+            .unwrap();
+        let filters = stream.await.unwrap();
+        assert_eq!(
+            filters[&submission.signature.to_string()].signature,
+            Some(submission.signature.to_string())
+        );
+        assert_eq!(submission.attempts, 1);
+        assert_eq!(
+            submission.landed,
+            Some(Landed {
+                slot: 77,
+                commitment: CommitmentLevel::Processed,
+                error: Some(TransactionError::AccountInUse.to_string()),
+            })
+        );
+        assert!(watcher.filters().is_empty());
+    }
+
+    #[tokio::test]
+    async fn observe_resolves_watched_signatures_only() {
+        let watcher = StatusWatcher::for_request(&SubscribeRequest {
+            commitment: Some(geyser::CommitmentLevel::Confirmed as i32),
+            ..Default::default()
+        });
+        let (watched, other) = (Signature::from([1; 64]), Signature::from([2; 64]));
+        let mut landed = watcher.watch(watched);
+        let update = |signature: &Signature| SubscribeUpdateTransactionStatus {
+            slot: 5,
+            signature: signature.as_ref().to_vec(),
+            ..Default::default()
+        };
+
+        watcher.observe(&update(&other)).unwrap();
+        assert!(landed.try_recv().is_err());
+        assert!(
+            watcher
+                .observe(&SubscribeUpdateTransactionStatus {
+                    signature: vec![1; 10],
+                    ..Default::default()
+                })
+                .is_err()
+        );
+        assert_eq!(watcher.filters().len(), 1);
+
+        watcher.observe(&update(&watched)).unwrap();
+        assert_eq!(
+            landed.try_recv().unwrap(),
+            Landed {
+                slot: 5,
+                commitment: CommitmentLevel::Confirmed,
+                error: None,
+            }
+        );
+        assert!(watcher.filters().is_empty());
+        // nobody waits for the second update
+        watcher.observe(&update(&watched)).unwrap();
+    }
+}
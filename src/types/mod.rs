@@ -1,3 +1,4 @@
 pub mod constant;
 pub mod pump_fun;
 pub mod raydium;
+pub mod swap;
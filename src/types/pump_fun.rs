@@ -147,6 +147,14 @@ pub trait EventTrait: Sized + std::fmt::Debug {
     fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>>;
     fn valid_discrminator(head: &[u8]) -> bool;
 
+    /// Decode one `Program data:` payload (discriminator included).
+    fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 || !Self::valid_discrminator(&data[..8]) {
+            return None;
+        }
+        Self::from_bytes(&data[8..]).ok()
+    }
+
     fn parse_logs<T: EventTrait + Clone>(logs: &[String]) -> Option<T> {
         // println!("{:?}", logs);
 
@@ -160,9 +168,6 @@ pub trait EventTrait: Sized + std::fmt::Debug {
             let (discr, rest) = bytes.split_at(8);
             //如果匹配discrminator正确
             if Self::valid_discrminator(discr) {
-                println!("*******************************************************",);
-                println!("匹配成功");
-                println!("*******************************************************",);
                 T::from_bytes(rest).ok()
             } else {
                 // println!("匹配失败discrminator {:?}", discr);
@@ -0,0 +1,99 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::pubkey::Pubkey;
+
+//raydium amm v4 instruction tag
+pub const SWAP_BASE_IN_TAG: u8 = 9;
+pub const SWAP_BASE_OUT_TAG: u8 = 11;
+
+//ray_log log_type
+pub const SWAP_BASE_IN_LOG_TYPE: u8 = 3;
+pub const SWAP_BASE_OUT_LOG_TYPE: u8 = 4;
+
+//direction in ray_log
+pub const DIRECTION_PC_TO_COIN: u64 = 1;
+pub const DIRECTION_COIN_TO_PC: u64 = 2;
+
+pub const RAY_LOG: &str = "ray_log: ";
+
+//amm v4 trade fee, taken from the input amount
+pub const RAYDIUM_SWAP_FEE_BPS: u64 = 25;
+
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+pub struct SwapBaseIn {
+    pub amount_in: u64,
+    pub minimum_amount_out: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+pub struct SwapBaseOut {
+    pub max_amount_in: u64,
+    pub amount_out: u64,
+}
+
+/// `ray_log` emitted by swap_base_in, pool amounts are before the swap.
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+pub struct SwapBaseInLog {
+    pub log_type: u8,
+    pub amount_in: u64,
+    pub minimum_out: u64,
+    pub direction: u64,
+    pub user_source: u64,
+    pub pool_coin: u64,
+    pub pool_pc: u64,
+    pub out_amount: u64,
+}
+
+/// `ray_log` emitted by swap_base_out, pool amounts are before the swap.
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+pub struct SwapBaseOutLog {
+    pub log_type: u8,
+    pub max_in: u64,
+    pub amount_out: u64,
+    pub direction: u64,
+    pub user_source: u64,
+    pub pool_coin: u64,
+    pub pool_pc: u64,
+    pub deduct_in: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RaydiumSwapLog {
+    BaseIn(SwapBaseInLog),
+    BaseOut(SwapBaseOutLog),
+}
+
+/// A swap_base_in/swap_base_out instruction joined with its `ray_log`.
+/// Mints come from the vaults' token balances and may be missing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RaydiumSwap {
+    pub amm: Pubkey,
+    pub user: Pubkey,
+    pub coin_mint: Option<Pubkey>,
+    pub pc_mint: Option<Pubkey>,
+    pub log: RaydiumSwapLog,
+}
+
+impl RaydiumSwap {
+    pub fn direction(&self) -> u64 {
+        match &self.log {
+            RaydiumSwapLog::BaseIn(log) => log.direction,
+            RaydiumSwapLog::BaseOut(log) => log.direction,
+        }
+    }
+
+    /// (amount in, amount out)
+    pub fn amounts(&self) -> (u64, u64) {
+        match &self.log {
+            RaydiumSwapLog::BaseIn(log) => (log.amount_in, log.out_amount),
+            RaydiumSwapLog::BaseOut(log) => (log.deduct_in, log.amount_out),
+        }
+    }
+
+    /// (coin, pc) vault amounts before the swap
+    pub fn pool_amounts(&self) -> (u64, u64) {
+        match &self.log {
+            RaydiumSwapLog::BaseIn(log) => (log.pool_coin, log.pool_pc),
+            RaydiumSwapLog::BaseOut(log) => (log.pool_coin, log.pool_pc),
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use super::constant::{
+    BONDING_CURVE_SEED, POOL_AUTHORITY_SEED, POOL_SEED, PUMP_AMM_PROGRAM_ID, PUMP_FUN_PROGRAM_ID,
+    WSOL_MINT,
+};
+use super::pump_fun::{BuyEvent, SellEvent, TradeEvent};
+use super::raydium::{DIRECTION_PC_TO_COIN, RAYDIUM_SWAP_FEE_BPS, RaydiumSwap};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Venue {
+    PumpFun,
+    PumpAmm,
+    Raydium,
+}
+
+impl Venue {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Venue::PumpFun => "pump_fun",
+            Venue::PumpAmm => "pump_amm",
+            Venue::Raydium => "raydium",
+        }
+    }
+}
+
+/// Buy = quote (SOL) in, base token out. Sell is the reverse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SwapSide {
+    Buy,
+    Sell,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SwapFees {
+    pub lp_fee: u64,
+    pub protocol_fee: u64,
+    pub creator_fee: u64,
+}
+
+impl SwapFees {
+    pub fn total(&self) -> u64 {
+        self.lp_fee + self.protocol_fee + self.creator_fee
+    }
+}
+
+/// Pool reserves after the swap has been applied.
+/// For the pump.fun bonding curve these are the virtual reserves.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PoolReserves {
+    pub base: u64,
+    pub quote: u64,
+}
+
+/// Venue-independent swap, the shape downstream consumers should depend on.
+///
+/// The venue events don't know where they were emitted, so `slot`, `signature`
+/// and `instruction_index` are filled by the caller through [`SwapEvent::with_context`].
+/// Pump AMM events don't carry mints either: the quote mint is assumed to be WSOL and
+/// the base mint stays `Pubkey::default()` until [`SwapEvent::with_mints`] is called.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwapEvent {
+    pub venue: Venue,
+    pub side: SwapSide,
+    pub pool: Pubkey,
+    pub trader: Pubkey,
+    pub input_mint: Pubkey,
+    pub input_amount: u64,
+    pub output_mint: Pubkey,
+    pub output_amount: u64,
+    pub fees: SwapFees,
+    pub reserves: PoolReserves,
+    pub timestamp: i64,
+    pub slot: u64,
+    pub signature: Signature,
+    pub instruction_index: usize,
+}
+
+impl SwapEvent {
+    pub fn with_context(
+        mut self,
+        slot: u64,
+        signature: Signature,
+        instruction_index: usize,
+    ) -> Self {
+        self.slot = slot;
+        self.signature = signature;
+        self.instruction_index = instruction_index;
+        self
+    }
+
+    pub fn with_mints(mut self, base_mint: Pubkey, quote_mint: Pubkey) -> Self {
+        match self.side {
+            SwapSide::Buy => {
+                self.input_mint = quote_mint;
+                self.output_mint = base_mint;
+            }
+            SwapSide::Sell => {
+                self.input_mint = base_mint;
+                self.output_mint = quote_mint;
+            }
+        }
+        self
+    }
+
+    pub fn base_mint(&self) -> Pubkey {
+        match self.side {
+            SwapSide::Buy => self.output_mint,
+            SwapSide::Sell => self.input_mint,
+        }
+    }
+
+    pub fn quote_mint(&self) -> Pubkey {
+        match self.side {
+            SwapSide::Buy => self.input_mint,
+            SwapSide::Sell => self.output_mint,
+        }
+    }
+
+    pub fn base_amount(&self) -> u64 {
+        match self.side {
+            SwapSide::Buy => self.output_amount,
+            SwapSide::Sell => self.input_amount,
+        }
+    }
+
+    pub fn quote_amount(&self) -> u64 {
+        match self.side {
+            SwapSide::Buy => self.input_amount,
+            SwapSide::Sell => self.output_amount,
+        }
+    }
+
+    /// The quote amount that moved against the pool, fees excluded. pump.fun
+    /// amounts already are; pump AMM user amounts have the fees added to a buy
+    /// and taken out of a sell.
+    pub fn quote_amount_without_fees(&self) -> u64 {
+        match (self.venue, self.side) {
+            (Venue::PumpAmm, SwapSide::Buy) => {
+                self.quote_amount().saturating_sub(self.fees.total())
+            }
+            (Venue::PumpAmm, SwapSide::Sell) => {
+                self.quote_amount().saturating_add(self.fees.total())
+            }
+            _ => self.quote_amount(),
+        }
+    }
+}
+
+pub fn bonding_curve_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[BONDING_CURVE_SEED, mint.as_ref()], &PUMP_FUN_PROGRAM_ID).0
+}
+
+/// The pump AMM pool a pump.fun mint migrates to: index 0 against WSOL, created
+/// by the mint's pool authority PDA.
+pub fn canonical_pool_address(mint: &Pubkey) -> Pubkey {
+    let authority =
+        Pubkey::find_program_address(&[POOL_AUTHORITY_SEED, mint.as_ref()], &PUMP_FUN_PROGRAM_ID).0;
+    Pubkey::find_program_address(
+        &[
+            POOL_SEED,
+            &0u16.to_le_bytes(),
+            authority.as_ref(),
+            mint.as_ref(),
+            WSOL_MINT.as_ref(),
+        ],
+        &PUMP_AMM_PROGRAM_ID,
+    )
+    .0
+}
+
+impl From<TradeEvent> for SwapEvent {
+    fn from(event: TradeEvent) -> Self {
+        let (side, input_mint, input_amount, output_mint, output_amount) = if event.is_buy {
+            (
+                SwapSide::Buy,
+                WSOL_MINT,
+                event.sol_amount,
+                event.mint,
+                event.token_amount,
+            )
+        } else {
+            (
+                SwapSide::Sell,
+                event.mint,
+                event.token_amount,
+                WSOL_MINT,
+                event.sol_amount,
+            )
+        };
+
+        Self {
+            venue: Venue::PumpFun,
+            side,
+            pool: bonding_curve_address(&event.mint),
+            trader: event.user,
+            input_mint,
+            input_amount,
+            output_mint,
+            output_amount,
+            fees: SwapFees {
+                lp_fee: 0,
+                protocol_fee: event.fee,
+                creator_fee: event.creator_fee,
+            },
+            reserves: PoolReserves {
+                base: event.virtual_token_reserves,
+                quote: event.virtual_sol_reserves,
+            },
+            timestamp: event.timestamp,
+            slot: 0,
+            signature: Signature::default(),
+            instruction_index: 0,
+        }
+    }
+}
+
+impl From<BuyEvent> for SwapEvent {
+    fn from(event: BuyEvent) -> Self {
+        Self {
+            venue: Venue::PumpAmm,
+            side: SwapSide::Buy,
+            pool: event.pool,
+            trader: event.user,
+            input_mint: Pubkey::default(),
+            input_amount: event.user_quote_amount_in,
+            output_mint: Pubkey::default(),
+            output_amount: event.base_amount_out,
+            fees: SwapFees {
+                lp_fee: event.lp_fee,
+                protocol_fee: event.protocol_fee,
+                creator_fee: event.coin_creator_fee,
+            },
+            reserves: PoolReserves {
+                base: event
+                    .pool_base_token_reserves
+                    .saturating_sub(event.base_amount_out),
+                quote: event
+                    .pool_quote_token_reserves
+                    .saturating_add(event.quote_amount_in_with_lp_fee),
+            },
+            timestamp: event.timestamp,
+            slot: 0,
+            signature: Signature::default(),
+            instruction_index: 0,
+        }
+        .with_mints(Pubkey::default(), WSOL_MINT)
+    }
+}
+
+impl From<SellEvent> for SwapEvent {
+    fn from(event: SellEvent) -> Self {
+        Self {
+            venue: Venue::PumpAmm,
+            side: SwapSide::Sell,
+            pool: event.pool,
+            trader: event.user,
+            input_mint: Pubkey::default(),
+            input_amount: event.base_amount_in,
+            output_mint: Pubkey::default(),
+            output_amount: event.user_quote_amount_out,
+            fees: SwapFees {
+                lp_fee: event.lp_fee,
+                protocol_fee: event.protocol_fee,
+                creator_fee: event.coin_creator_fee,
+            },
+            reserves: PoolReserves {
+                base: event
+                    .pool_base_token_reserves
+                    .saturating_add(event.base_amount_in),
+                quote: event
+                    .pool_quote_token_reserves
+                    .saturating_sub(event.quote_amount_out_without_lp_fee),
+            },
+            timestamp: event.timestamp,
+            slot: 0,
+            signature: Signature::default(),
+            instruction_index: 0,
+        }
+        .with_mints(Pubkey::default(), WSOL_MINT)
+    }
+}
+
+/// Coin is treated as base and pc as quote; a missing mint stays `Pubkey::default()`.
+impl From<RaydiumSwap> for SwapEvent {
+    fn from(swap: RaydiumSwap) -> Self {
+        let (amount_in, amount_out) = swap.amounts();
+        let (pool_coin, pool_pc) = swap.pool_amounts();
+        let side = if swap.direction() == DIRECTION_PC_TO_COIN {
+            SwapSide::Buy
+        } else {
+            SwapSide::Sell
+        };
+        let reserves = match side {
+            SwapSide::Buy => PoolReserves {
+                base: pool_coin.saturating_sub(amount_out),
+                quote: pool_pc.saturating_add(amount_in),
+            },
+            SwapSide::Sell => PoolReserves {
+                base: pool_coin.saturating_add(amount_in),
+                quote: pool_pc.saturating_sub(amount_out),
+            },
+        };
+
+        Self {
+            venue: Venue::Raydium,
+            side,
+            pool: swap.amm,
+            trader: swap.user,
+            input_mint: Pubkey::default(),
+            input_amount: amount_in,
+            output_mint: Pubkey::default(),
+            output_amount: amount_out,
+            fees: SwapFees {
+                lp_fee: (amount_in as u128 * RAYDIUM_SWAP_FEE_BPS as u128 / 10_000) as u64,
+                protocol_fee: 0,
+                creator_fee: 0,
+            },
+            reserves,
+            timestamp: 0,
+            slot: 0,
+            signature: Signature::default(),
+            instruction_index: 0,
+        }
+        .with_mints(
+            swap.coin_mint.unwrap_or_default(),
+            swap.pc_mint.unwrap_or_default(),
+        )
+    }
+}
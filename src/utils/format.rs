@@ -2,22 +2,136 @@ use anyhow::Context;
 use serde_json::{Value, json};
 use solana_sdk::{pubkey::Pubkey, signature::Signature};
 use solana_transaction_status::UiTransactionEncoding;
+use spl_token_2022::{
+    extension::{
+        BaseStateWithExtensions, StateWithExtensions,
+        metadata_pointer::MetadataPointer,
+        transfer_fee::{TransferFee, TransferFeeAmount, TransferFeeConfig},
+    },
+    state::{Account, Mint},
+};
+use spl_token_metadata_interface::state::TokenMetadata;
 use yellowstone_grpc_proto::{
     convert_from,
     prelude::{SubscribeUpdateAccountInfo, SubscribeUpdateTransactionInfo},
 };
 
+use crate::types::constant::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+
+/// Token program accounts get a decoded `parsed` field instead of the hex `data`.
 pub fn create_pretty_account(account: SubscribeUpdateAccountInfo) -> anyhow::Result<Value> {
-    Ok(json!({
+    let owner =
+        Pubkey::try_from(account.owner).map_err(|_| anyhow::anyhow!("invalid account owner"))?;
+    let mut value = json!({
         "pubkey": Pubkey::try_from(account.pubkey).map_err(|_| anyhow::anyhow!("invalid account pubkey"))?.to_string(),
         "lamports": account.lamports,
-        "owner": Pubkey::try_from(account.owner).map_err(|_| anyhow::anyhow!("invalid account owner"))?.to_string(),
+        "owner": owner.to_string(),
         "executable": account.executable,
         "rentEpoch": account.rent_epoch,
-        "data": hex::encode(account.data),
         "writeVersion": account.write_version,
         "txnSignature": account.txn_signature.map(|sig| bs58::encode(sig).into_string()),
-    }))
+    });
+
+    let parsed = if owner == TOKEN_PROGRAM_ID || owner == TOKEN_2022_PROGRAM_ID {
+        parse_token_account_data(&account.data)
+    } else {
+        None
+    };
+    match parsed {
+        Some(parsed) => value["parsed"] = parsed,
+        None => value["data"] = json!(hex::encode(account.data)),
+    }
+
+    Ok(value)
+}
+
+/// Decode SPL Token / Token-2022 account data, a token account or a mint.
+pub fn parse_token_account_data(data: &[u8]) -> Option<Value> {
+    if let Ok(state) = StateWithExtensions::<Account>::unpack(data) {
+        let account = state.base;
+        let mut value = json!({
+            "type": "account",
+            "mint": account.mint.to_string(),
+            "owner": account.owner.to_string(),
+            "amount": account.amount,
+            "delegate": Option::<Pubkey>::from(account.delegate).map(|key| key.to_string()),
+            "delegatedAmount": account.delegated_amount,
+            "state": format!("{:?}", account.state).to_lowercase(),
+            "isNative": account.is_native.is_some(),
+            "closeAuthority": Option::<Pubkey>::from(account.close_authority).map(|key| key.to_string()),
+        });
+        let mut extensions = extension_names(&state);
+        if let Ok(fee) = state.get_extension::<TransferFeeAmount>() {
+            extensions["transferFeeAmount"] = json!({
+                "withheldAmount": u64::from(fee.withheld_amount),
+            });
+        }
+        value["extensions"] = extensions;
+        return Some(value);
+    }
+
+    let state = StateWithExtensions::<Mint>::unpack(data).ok()?;
+    let mint = state.base;
+    let mut value = json!({
+        "type": "mint",
+        "supply": mint.supply,
+        "decimals": mint.decimals,
+        "isInitialized": mint.is_initialized,
+        "mintAuthority": Option::<Pubkey>::from(mint.mint_authority).map(|key| key.to_string()),
+        "freezeAuthority": Option::<Pubkey>::from(mint.freeze_authority).map(|key| key.to_string()),
+    });
+    let mut extensions = extension_names(&state);
+    if let Ok(config) = state.get_extension::<TransferFeeConfig>() {
+        extensions["transferFeeConfig"] = json!({
+            "transferFeeConfigAuthority": optional_key(config.transfer_fee_config_authority),
+            "withdrawWithheldAuthority": optional_key(config.withdraw_withheld_authority),
+            "withheldAmount": u64::from(config.withheld_amount),
+            "olderTransferFee": pretty_transfer_fee(&config.older_transfer_fee),
+            "newerTransferFee": pretty_transfer_fee(&config.newer_transfer_fee),
+        });
+    }
+    if let Ok(pointer) = state.get_extension::<MetadataPointer>() {
+        extensions["metadataPointer"] = json!({
+            "authority": optional_key(pointer.authority),
+            "metadataAddress": optional_key(pointer.metadata_address),
+        });
+    }
+    if let Ok(metadata) = state.get_variable_len_extension::<TokenMetadata>() {
+        extensions["tokenMetadata"] = json!({
+            "updateAuthority": optional_key(metadata.update_authority),
+            "mint": metadata.mint.to_string(),
+            "name": metadata.name,
+            "symbol": metadata.symbol,
+            "uri": metadata.uri,
+            "additionalMetadata": metadata.additional_metadata,
+        });
+    }
+    value["extensions"] = extensions;
+    Some(value)
+}
+
+fn extension_names<S: BaseStateWithExtensions<T>, T: spl_token_2022::extension::BaseState>(
+    state: &S,
+) -> Value {
+    let types = state
+        .get_extension_types()
+        .unwrap_or_default()
+        .iter()
+        .map(|extension| format!("{:?}", extension))
+        .collect::<Vec<_>>();
+    json!({ "types": types })
+}
+
+fn optional_key(key: impl Into<Option<Pubkey>>) -> Option<String> {
+    key.into().map(|key| key.to_string())
+}
+
+fn pretty_transfer_fee(fee: &TransferFee) -> Value {
+    json!({
+        "epoch": u64::from(fee.epoch),
+        "maximumFee": u64::from(fee.maximum_fee),
+        "transferFeeBasisPoints": u16::from(fee.transfer_fee_basis_points),
+    })
 }
 
 pub fn create_pretty_transaction(tx: SubscribeUpdateTransactionInfo) -> anyhow::Result<Value> {
@@ -1 +1,2 @@
-pub mod format;
\ No newline at end of file
+pub mod format;
+pub mod summary;
\ No newline at end of file
@@ -0,0 +1,240 @@
+use anyhow::Context;
+use serde_json::{Value, json};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::collections::BTreeMap;
+use std::fmt;
+use yellowstone_grpc_proto::{
+    convert_from,
+    prelude::{SubscribeUpdateTransactionInfo, TokenBalance},
+};
+
+use crate::analytics::compute_budget::ComputeBudget;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SolBalanceChange {
+    pub account: Pubkey,
+    pub pre: u64,
+    pub post: u64,
+    pub change: i128,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenBalanceChange {
+    pub owner: String,
+    pub mint: String,
+    pub decimals: u32,
+    pub pre: u64,
+    pub post: u64,
+    pub change: i128,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransactionSummary {
+    pub signature: Signature,
+    pub slot: u64,
+    pub fee_payer: Pubkey,
+    pub success: bool,
+    pub error: Option<String>,
+    pub fee: u64,
+    /// Micro-lamports per compute unit.
+    pub compute_unit_price: u64,
+    pub compute_unit_limit: u64,
+    /// Lamports, `compute_unit_price * compute_unit_limit` rounded up.
+    pub priority_fee: u64,
+    pub compute_units_consumed: Option<u64>,
+    pub sol_changes: Vec<SolBalanceChange>,
+    pub token_changes: Vec<TokenBalanceChange>,
+    pub programs: Vec<Pubkey>,
+}
+
+pub fn create_transaction_summary(
+    tx: &SubscribeUpdateTransactionInfo,
+    slot: u64,
+) -> anyhow::Result<TransactionSummary> {
+    let signature = Signature::try_from(tx.signature.as_slice()).context("invalid signature")?;
+    let message = tx
+        .transaction
+        .as_ref()
+        .and_then(|tx| tx.message.as_ref())
+        .context("no message in the transaction")?;
+    let meta = tx.meta.as_ref().context("no meta in the transaction")?;
+
+    let account_keys = message
+        .account_keys
+        .iter()
+        .chain(&meta.loaded_writable_addresses)
+        .chain(&meta.loaded_readonly_addresses)
+        .map(|key| Pubkey::try_from(key.as_slice()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| anyhow::anyhow!("invalid account key"))?;
+    let fee_payer = *account_keys.first().context("no fee payer")?;
+
+    let error = convert_from::create_tx_error(meta.err.as_ref())
+        .map_err(|error| anyhow::anyhow!(error))?
+        .map(|error| error.to_string());
+
+    let compute_budget =
+        ComputeBudget::from_instructions(message.instructions.iter().filter_map(|ix| {
+            account_keys
+                .get(ix.program_id_index as usize)
+                .map(|program_id| (*program_id, ix.data.as_slice()))
+        }));
+
+    let sol_changes = account_keys
+        .iter()
+        .zip(meta.pre_balances.iter().zip(&meta.post_balances))
+        .filter(|(_, (pre, post))| pre != post)
+        .map(|(account, (pre, post))| SolBalanceChange {
+            account: *account,
+            pre: *pre,
+            post: *post,
+            change: *post as i128 - *pre as i128,
+        })
+        .collect();
+
+    // (owner, mint) -> (decimals, pre, post)
+    let mut token_balances: BTreeMap<(String, String), (u32, u64, u64)> = BTreeMap::new();
+    let mut add_balances = |balances: &[TokenBalance], is_post: bool| {
+        for balance in balances {
+            let Some(amount) = balance.ui_token_amount.as_ref() else {
+                continue;
+            };
+            let entry = token_balances
+                .entry((balance.owner.clone(), balance.mint.clone()))
+                .or_insert((amount.decimals, 0, 0));
+            let value = amount.amount.parse::<u64>().unwrap_or_default();
+            if is_post {
+                entry.2 += value;
+            } else {
+                entry.1 += value;
+            }
+        }
+    };
+    add_balances(&meta.pre_token_balances, false);
+    add_balances(&meta.post_token_balances, true);
+    let token_changes = token_balances
+        .into_iter()
+        .filter(|(_, (_, pre, post))| pre != post)
+        .map(
+            |((owner, mint), (decimals, pre, post))| TokenBalanceChange {
+                owner,
+                mint,
+                decimals,
+                pre,
+                post,
+                change: post as i128 - pre as i128,
+            },
+        )
+        .collect();
+
+    let mut programs: Vec<Pubkey> = Vec::new();
+    let program_indexes = message
+        .instructions
+        .iter()
+        .map(|ix| ix.program_id_index)
+        .chain(
+            meta.inner_instructions
+                .iter()
+                .flat_map(|inner| &inner.instructions)
+                .map(|ix| ix.program_id_index),
+        );
+    for index in program_indexes {
+        if let Some(program_id) = account_keys.get(index as usize)
+            && !programs.contains(program_id)
+        {
+            programs.push(*program_id);
+        }
+    }
+
+    Ok(TransactionSummary {
+        signature,
+        slot,
+        fee_payer,
+        success: error.is_none(),
+        error,
+        fee: meta.fee,
+        compute_unit_price: compute_budget.unit_price.unwrap_or_default(),
+        compute_unit_limit: compute_budget.effective_unit_limit(),
+        priority_fee: compute_budget.priority_fee(),
+        compute_units_consumed: meta.compute_units_consumed,
+        sol_changes,
+        token_changes,
+        programs,
+    })
+}
+
+impl TransactionSummary {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "signature": self.signature.to_string(),
+            "slot": self.slot,
+            "feePayer": self.fee_payer.to_string(),
+            "success": self.success,
+            "error": self.error,
+            "fee": self.fee,
+            "priorityFee": self.priority_fee,
+            "computeUnitPrice": self.compute_unit_price,
+            "computeUnitLimit": self.compute_unit_limit,
+            "computeUnitsConsumed": self.compute_units_consumed,
+            "solChanges": self.sol_changes.iter().map(|change| json!({
+                "account": change.account.to_string(),
+                "pre": change.pre,
+                "post": change.post,
+                "change": change.change as i64,
+            })).collect::<Vec<_>>(),
+            "tokenChanges": self.token_changes.iter().map(|change| json!({
+                "owner": change.owner,
+                "mint": change.mint,
+                "decimals": change.decimals,
+                "pre": change.pre,
+                "post": change.post,
+                "change": change.change as i64,
+            })).collect::<Vec<_>>(),
+            "programs": self.programs.iter().map(|program| program.to_string()).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// One line: `<sig> slot=.. payer=.. ok fee=.. priority=.. cu=.. sol=[..] tokens=[..] programs=[..]`
+impl fmt::Display for TransactionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} slot={} payer={} {} fee={} priority={} cu={}",
+            self.signature,
+            self.slot,
+            self.fee_payer,
+            match &self.error {
+                Some(error) => format!("err({})", error),
+                None => "ok".to_string(),
+            },
+            self.fee,
+            self.priority_fee,
+            self.compute_units_consumed
+                .map_or("-".to_string(), |cu| cu.to_string()),
+        )?;
+
+        let sol = self
+            .sol_changes
+            .iter()
+            .map(|change| format!("{}:{:+}", change.account, change.change))
+            .collect::<Vec<_>>();
+        let tokens = self
+            .token_changes
+            .iter()
+            .map(|change| format!("{}/{}:{:+}", change.owner, change.mint, change.change))
+            .collect::<Vec<_>>();
+        let programs = self
+            .programs
+            .iter()
+            .map(|program| program.to_string())
+            .collect::<Vec<_>>();
+        write!(
+            f,
+            " sol=[{}] tokens=[{}] programs=[{}]",
+            sol.join(","),
+            tokens.join(","),
+            programs.join(",")
+        )
+    }
+}